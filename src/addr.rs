@@ -0,0 +1,250 @@
+/// # Fantom Libtransport/addr
+///
+/// This file contains helpers for handling peer network addresses.
+///
+/// The same endpoint can be written in several ways ("localhost:8000", "127.0.0.1:8000",
+/// "[0:0:0:0:0:0:0:1]:8000" vs "[::1]:8000"). Transports should call `normalize_address` before
+/// using an address as a connection pool key, so connections to one endpoint are deduplicated.
+///
+/// A transport bound to a wildcard address (e.g. "0.0.0.0:0") must not advertise that address to
+/// peers, since it isn't dialable; `advertised_address` replaces the wildcard with a routable local
+/// IP.
+///
+/// Unix domain socket addresses are filesystem paths. Binding fails while a socket file exists at
+/// the path, even one left behind by a crashed process, so transports call `remove_stale_socket`
+/// before binding.
+///
+/// `BindAddr` validates a bind address up front, so a malformed one fails at construction with
+/// `Error::AddrParse` instead of when the transport tries to bind.
+use crate::errors::{Error, Result};
+use crate::TransportType;
+use core::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+
+/// Resolves and canonicalizes a `host:port` address. Hostnames are resolved, preferring an IPv4
+/// address when the name resolves to both families, and IPv6 addresses are written in their
+/// shortest bracketed form.
+pub fn normalize_address(addr: &str) -> Result<String> {
+    if let Ok(socket_addr) = addr.parse::<SocketAddr>() {
+        return Ok(socket_addr.to_string());
+    }
+    let resolved: Vec<SocketAddr> = addr.to_socket_addrs().map_err(Error::from)?.collect();
+    resolved
+        .iter()
+        .find(|a| a.is_ipv4())
+        .or_else(|| resolved.first())
+        .map(|a| a.to_string())
+        .ok_or_else(|| Error::AddrParse(addr.to_string()).into())
+}
+
+/// Returns the address peers should dial to reach a listener bound to `local_addr`. A wildcard IP
+/// ("0.0.0.0" or "::") is replaced with a routable local IP of the same family, keeping the port;
+/// other addresses are returned normalized.
+///
+/// The std library can't enumerate network interfaces, so the routable IP is found by asking the
+/// OS which local address it would use to reach a public address (a connected UDP socket; no
+/// packet is sent), i.e. the first non-loopback interface on the default route. Without a default
+/// route the loopback address is used, which is only dialable from the same host.
+pub fn advertised_address(local_addr: &str) -> Result<String> {
+    let addr: SocketAddr = local_addr
+        .parse()
+        .map_err(|_| Error::AddrParse(local_addr.to_string()))?;
+    if !addr.ip().is_unspecified() {
+        return Ok(addr.to_string());
+    }
+    let (bind, probe, loopback): (&str, &str, IpAddr) = if addr.is_ipv4() {
+        ("0.0.0.0:0", "192.0.2.1:9", IpAddr::V4(Ipv4Addr::LOCALHOST))
+    } else {
+        ("[::]:0", "[2001:db8::1]:9", IpAddr::V6(Ipv6Addr::LOCALHOST))
+    };
+    let ip = UdpSocket::bind(bind)
+        .and_then(|socket| {
+            socket.connect(probe)?;
+            socket.local_addr()
+        })
+        .map(|a| a.ip())
+        .ok()
+        .filter(|ip| !ip.is_unspecified())
+        .unwrap_or(loopback);
+    Ok(SocketAddr::new(ip, addr.port()).to_string())
+}
+
+/// Removes the Unix domain socket file at `path` if it is stale, i.e. no process accepts
+/// connections on it anymore. Returns true if a stale file was removed and false if there was no
+/// file. Fails with an `AddrInUse` I/O error if a live listener owns the path.
+#[cfg(unix)]
+pub fn remove_stale_socket(path: &str) -> Result<bool> {
+    use std::io::ErrorKind;
+    use std::os::unix::net::UnixStream;
+    use std::path::Path;
+
+    if !Path::new(path).exists() {
+        return Ok(false);
+    }
+    match UnixStream::connect(path) {
+        Ok(_) => Err(Error::Io(std::io::Error::new(ErrorKind::AddrInUse, path.to_string())).into()),
+        Err(ref e) if e.kind() == ErrorKind::ConnectionRefused => {
+            std::fs::remove_file(path).map_err(Error::from)?;
+            Ok(true)
+        }
+        Err(e) => Err(Error::Io(e).into()),
+    }
+}
+
+/// A validated bind address: `host:port` for network transports, or a filesystem path for Unix
+/// domain sockets.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BindAddr(String);
+
+impl BindAddr {
+    /// Validates `addr`, taking it for a Unix domain socket path if it starts with '/' or '.',
+    /// and for `host:port` otherwise. IPv6 hosts must be bracketed ("[::1]:8000"). Fails with
+    /// `Error::AddrParse` if malformed.
+    pub fn new(addr: &str) -> Result<BindAddr> {
+        if addr.starts_with('/') || addr.starts_with('.') {
+            BindAddr::for_type(addr, &TransportType::UnixSocket)
+        } else {
+            BindAddr::for_type(addr, &TransportType::TCP)
+        }
+    }
+
+    /// Validates `addr` in the form used by `transport_type`: a path for `UnixSocket`, and
+    /// `host:port` for every other type.
+    pub fn for_type(addr: &str, transport_type: &TransportType) -> Result<BindAddr> {
+        let valid = match transport_type {
+            TransportType::UnixSocket => is_valid_path(addr),
+            _ => is_valid_host_port(addr),
+        };
+        if valid {
+            Ok(BindAddr(addr.to_string()))
+        } else {
+            Err(Error::AddrParse(addr.to_string()).into())
+        }
+    }
+
+    /// Returns the address as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for BindAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<BindAddr> for String {
+    fn from(addr: BindAddr) -> String {
+        addr.0
+    }
+}
+
+fn is_valid_path(addr: &str) -> bool {
+    !addr.is_empty() && !addr.contains('\0')
+}
+
+// Accepts socket addresses and `hostname:port`; an unbracketed host containing ':' is ambiguous.
+fn is_valid_host_port(addr: &str) -> bool {
+    if addr.parse::<SocketAddr>().is_ok() {
+        return true;
+    }
+    match addr.rfind(':') {
+        Some(i) => {
+            let (host, port) = (&addr[..i], &addr[i + 1..]);
+            !host.is_empty()
+                && host
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+                && port.parse::<u16>().is_ok()
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /*
+        Tests address normalization: "localhost" and "127.0.0.1" must normalize to the same address
+        (in an IPv4-only environment), and IPv6 forms must canonicalize to the shortest form.
+    */
+    #[test]
+    fn normalize_address_test() -> Result<()> {
+        assert_eq!(
+            normalize_address("127.0.0.1:80")?,
+            normalize_address("localhost:80")?
+        );
+        assert_eq!("[::1]:8000", normalize_address("[0:0:0:0:0:0:0:1]:8000")?);
+        assert_eq!(
+            normalize_address("[fe80:0:0:0:0:0:0:1]:8000")?,
+            normalize_address("[FE80::1]:8000")?
+        );
+        assert!(normalize_address("not an address").is_err());
+
+        Ok(())
+    }
+
+    /*
+        Tests BindAddr validation: IPv4, bracketed IPv6 and hostname addresses and Unix paths must
+        be accepted, malformed addresses rejected with Error::AddrParse.
+    */
+    #[test]
+    fn bind_addr_test() -> Result<()> {
+        assert_eq!("127.0.0.1:8000", BindAddr::new("127.0.0.1:8000")?.as_str());
+        assert_eq!("[::1]:8000", BindAddr::new("[::1]:8000")?.as_str());
+        BindAddr::new("localhost:8000")?;
+        BindAddr::new("/tmp/node.sock")?;
+        BindAddr::for_type("node.sock", &TransportType::UnixSocket)?;
+
+        for invalid in &[
+            "not an address",
+            "::1:8000",
+            "127.0.0.1:99999",
+            "localhost",
+            "",
+        ] {
+            match BindAddr::new(invalid) {
+                Err(e) => match e.downcast_ref::<Error>() {
+                    Some(Error::AddrParse(addr)) => assert_eq!(*invalid, addr.as_str()),
+                    _ => panic!("unexpected error: {}", e),
+                },
+                Ok(addr) => panic!("{} accepted", addr),
+            }
+        }
+
+        Ok(())
+    }
+
+    /*
+        Tests the "unix" TransportType and stale socket handling: a socket file left behind by a
+        dropped listener must be removed so the path can be bound again, while the file of a live
+        listener must be reported in use.
+    */
+    #[cfg(unix)]
+    #[test]
+    fn unix_socket_rebind_test() -> Result<()> {
+        use crate::generic_test::free_unix_addrs;
+        use std::os::unix::net::UnixListener;
+
+        assert_eq!(TransportType::UnixSocket, "unix".parse::<TransportType>()?);
+        assert_eq!("unix", TransportType::UnixSocket.to_string());
+
+        let path = free_unix_addrs(1).remove(0);
+        assert!(!remove_stale_socket(&path)?);
+
+        let listener = UnixListener::bind(&path).map_err(Error::from)?;
+        assert!(remove_stale_socket(&path).is_err(), "live socket removed");
+        drop(listener);
+
+        // The dropped listener left its socket file behind.
+        assert!(UnixListener::bind(&path).is_err());
+        assert!(remove_stale_socket(&path)?);
+        let listener = UnixListener::bind(&path).map_err(Error::from)?;
+        drop(listener);
+        std::fs::remove_file(&path).map_err(Error::from)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+/// # Fantom Libtransport/audit
+///
+/// This file defines `DropAudit`, a helper catching message loss on shutdown. Implementors embed a
+/// `DropAudit` in their transport, record every message queued for and flushed to a peer, and the
+/// audit reports, once dropped together with the transport, the messages and bytes still sitting
+/// in the send buffers, per peer. This turns silent data loss into a visible diagnostic.
+///
+/// Only available with the `debug` feature.
+use std::collections::BTreeMap;
+
+/// Messages and bytes queued for a peer but not flushed yet.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Unflushed {
+    pub messages: u64,
+    pub bytes: u64,
+}
+
+/// Tracks unflushed messages per peer and reports them when dropped.
+pub struct DropAudit {
+    pending: BTreeMap<String, Unflushed>,
+    reporter: Box<dyn Fn(&str, Unflushed) + Send + Sync>,
+}
+
+impl Default for DropAudit {
+    fn default() -> DropAudit {
+        DropAudit::new()
+    }
+}
+
+impl DropAudit {
+    /// Creates an audit which logs a warning to stderr for every peer with unflushed messages.
+    pub fn new() -> DropAudit {
+        DropAudit::with_reporter(|peer, unflushed| {
+            eprintln!(
+                "warning: transport dropped with {} unflushed messages ({} bytes) for {}",
+                unflushed.messages, unflushed.bytes, peer
+            )
+        })
+    }
+
+    /// Creates an audit calling `reporter` with every peer address and its unflushed messages.
+    pub fn with_reporter(reporter: impl Fn(&str, Unflushed) + Send + Sync + 'static) -> DropAudit {
+        DropAudit {
+            pending: BTreeMap::new(),
+            reporter: Box::new(reporter),
+        }
+    }
+
+    /// Records a message of `bytes` bytes queued for the peer.
+    pub fn queued(&mut self, peer_address: &str, bytes: u64) {
+        let entry = self.pending.entry(peer_address.to_string()).or_default();
+        entry.messages += 1;
+        entry.bytes += bytes;
+    }
+
+    /// Records a message of `bytes` bytes flushed to the peer.
+    pub fn flushed(&mut self, peer_address: &str, bytes: u64) {
+        if let Some(entry) = self.pending.get_mut(peer_address) {
+            entry.messages = entry.messages.saturating_sub(1);
+            entry.bytes = entry.bytes.saturating_sub(bytes);
+            if entry.messages == 0 {
+                self.pending.remove(peer_address);
+            }
+        }
+    }
+
+    /// Returns the unflushed messages of the peer.
+    pub fn unflushed(&self, peer_address: &str) -> Unflushed {
+        self.pending.get(peer_address).copied().unwrap_or_default()
+    }
+}
+
+impl Drop for DropAudit {
+    fn drop(&mut self) {
+        for (peer, unflushed) in self.pending.iter() {
+            (self.reporter)(peer, *unflushed);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+/// # Fantom Libtransport/boxed
+///
+/// This file defines `BoxedTransport`, a wrapper which lets transports that are not `Unpin` (for
+/// example, implementations that store `async` state or other self-referential futures) be used
+/// wherever a `Transport` is expected.
+///
+/// Such implementations implement the `PinnedTransport` trait instead of `Transport`. Its methods
+/// take `self: Pin<&mut Self>`, so the implementation may rely on never being moved once pinned.
+/// `BoxedTransport` keeps the inner transport in a `Pin<Box<T>>`, which is itself `Unpin`, and
+/// re-exposes the `Transport` API on top of it.
+///
+/// The trade-off is one heap allocation per transport and a pointer indirection on every call;
+/// implementations which are already `Unpin` should implement `Transport` directly.
+use crate::errors::Result;
+use crate::{Transport, TransportConfiguration};
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use futures::stream::{FusedStream, Stream};
+use libcommon_rs::peer::{PeerId, PeerList};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::sync::mpsc::Sender;
+
+/// PinnedTransport mirrors the `Transport` trait without the `Unpin` requirement. All runtime
+/// methods operate on a pinned reference. The stream follows the termination contract of
+/// `Transport`.
+pub trait PinnedTransport<Id, Data, Error, Pl>: FusedStream<Item = Data>
+where
+    Id: PeerId,
+    Pl: PeerList<Id, Error>,
+    Data: Serialize + DeserializeOwned,
+{
+    /// Creates a new PinnedTransport type from a configuration, binding a listener to its
+    /// `bind_addr`, as `Transport::new`.
+    fn new<C>(cfg: C) -> Result<Self>
+    where
+        C: TransportConfiguration,
+        Self: Sized;
+
+    /// Sends a message of type 'Data' to the specified peer (as specified by `peer_address`)
+    fn send(self: Pin<&mut Self>, peer_address: String, data: Data) -> Result<()>;
+
+    /// Broadcasts a message of type 'Data' to all peers on the network using `base` address.
+    fn broadcast(self: Pin<&mut Self>, peers: &mut Pl, data: Data) -> Result<()>;
+
+    /// Broadcasts a message of type 'Data' to all peers on the network using nth address in `net_addr`.
+    fn broadcast_n(self: Pin<&mut Self>, peers: &mut Pl, n: usize, data: Data) -> Result<()>;
+
+    /// Returns true once the transport has finished binding/listening, see
+    /// `Transport::is_ready`.
+    fn is_ready(&self) -> bool;
+
+    /// returns quit send channel
+    fn get_quit_tx(&self) -> Option<Sender<()>>;
+}
+
+/// A heap-pinned PinnedTransport which implements `Transport`.
+pub struct BoxedTransport<T> {
+    inner: Pin<Box<T>>,
+}
+
+impl<T> BoxedTransport<T> {
+    /// Pins an already constructed transport.
+    pub fn from_inner(inner: T) -> BoxedTransport<T> {
+        BoxedTransport {
+            inner: Box::pin(inner),
+        }
+    }
+
+    /// Returns a pinned reference to the inner transport.
+    pub fn inner_mut(&mut self) -> Pin<&mut T> {
+        self.inner.as_mut()
+    }
+}
+
+impl<T> Drop for BoxedTransport<T> {
+    fn drop(&mut self) {}
+}
+
+impl<T: Stream> Stream for BoxedTransport<T> {
+    type Item = T::Item;
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+impl<T: FusedStream> FusedStream for BoxedTransport<T> {
+    fn is_terminated(&self) -> bool {
+        self.inner.is_terminated()
+    }
+}
+
+impl<Id, Data, Error, Pl, T> Transport<Id, Data, Error, Pl> for BoxedTransport<T>
+where
+    Id: PeerId,
+    Pl: PeerList<Id, Error>,
+    Data: Serialize + DeserializeOwned,
+    T: PinnedTransport<Id, Data, Error, Pl>,
+{
+    fn new<C: TransportConfiguration>(cfg: C) -> Result<Self> {
+        Ok(BoxedTransport::from_inner(T::new(cfg)?))
+    }
+
+    fn is_ready(&self) -> bool {
+        self.inner.is_ready()
+    }
+
+    fn send(&mut self, peer_address: String, data: Data) -> Result<()> {
+        self.inner.as_mut().send(peer_address, data)
+    }
+
+    fn broadcast(&mut self, peers: &mut Pl, data: Data) -> Result<()> {
+        self.inner.as_mut().broadcast(peers, data)
+    }
+
+    fn broadcast_n(&mut self, peers: &mut Pl, n: usize, data: Data) -> Result<()> {
+        self.inner.as_mut().broadcast_n(peers, n, data)
+    }
+
+    fn get_quit_tx(&self) -> Option<Sender<()>> {
+        self.inner.get_quit_tx()
+    }
+}
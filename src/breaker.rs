@@ -0,0 +1,129 @@
+/// # Fantom Libtransport/breaker
+///
+/// This file contains a per-peer circuit breaker which Transport implementations can use to
+/// honour `Transport::set_circuit_breaker`, so a peer that keeps failing stops costing connect
+/// attempts.
+///
+/// Each peer's breaker starts closed. After `failures` consecutive send failures it opens, and
+/// sends to the peer fail immediately with `Error::CircuitOpen` for the `cooldown`. Once the
+/// cooldown elapsed the breaker is half-open: the next send is attempted, closing the breaker if
+/// it succeeds and reopening it for another cooldown if it fails.
+use crate::errors::{Error, Result};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// The state of a peer's breaker.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BreakerState {
+    /// Sends are attempted normally.
+    Closed,
+    /// Sends fail immediately until the cooldown ends.
+    Open,
+    /// The cooldown ended; the next send decides whether the breaker closes or reopens.
+    HalfOpen,
+}
+
+#[derive(Clone, Debug, Default)]
+struct PeerBreaker {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+}
+
+/// Per-peer circuit breakers sharing one configuration.
+#[derive(Clone, Debug)]
+pub struct CircuitBreaker {
+    failures: u32,
+    cooldown: Duration,
+    peers: HashMap<String, PeerBreaker>,
+}
+
+impl CircuitBreaker {
+    /// Creates breakers opening after `failures` consecutive failures (at least 1) for
+    /// `cooldown`.
+    pub fn new(failures: u32, cooldown: Duration) -> CircuitBreaker {
+        CircuitBreaker {
+            failures: failures.max(1),
+            cooldown,
+            peers: HashMap::new(),
+        }
+    }
+
+    /// Returns the state of the peer's breaker.
+    pub fn state(&self, peer_address: &str) -> BreakerState {
+        match self.peers.get(peer_address).and_then(|p| p.opened_at) {
+            None => BreakerState::Closed,
+            Some(opened_at) if opened_at.elapsed() < self.cooldown => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+
+    /// Checks whether a send to the peer may be attempted, failing with `Error::CircuitOpen`
+    /// while the peer's breaker is open.
+    pub fn check(&self, peer_address: &str) -> Result<()> {
+        match self.state(peer_address) {
+            BreakerState::Open => Err(Error::CircuitOpen(peer_address.to_string()).into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Records a successful send to the peer, closing its breaker.
+    pub fn record_success(&mut self, peer_address: &str) {
+        self.peers.remove(peer_address);
+    }
+
+    /// Records a failed send to the peer, opening its breaker once the failure threshold is
+    /// reached or if it was half-open.
+    pub fn record_failure(&mut self, peer_address: &str) {
+        let half_open = self.state(peer_address) == BreakerState::HalfOpen;
+        let peer = self.peers.entry(peer_address.to_string()).or_default();
+        peer.consecutive_failures = peer.consecutive_failures.saturating_add(1);
+        if half_open || peer.consecutive_failures >= self.failures {
+            peer.opened_at = Some(Instant::now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    /*
+        Tests the CircuitBreaker state machine: it opens after the configured number of consecutive
+        failures, turns half-open after the cooldown, reopens on a failed trial and closes on
+        success.
+    */
+    #[test]
+    fn circuit_breaker_test() -> Result<()> {
+        let cooldown = Duration::from_millis(100);
+        let mut breaker = CircuitBreaker::new(3, cooldown);
+        let peer = "127.0.0.1:1";
+
+        for _ in 0..2 {
+            breaker.record_failure(peer);
+            assert_eq!(BreakerState::Closed, breaker.state(peer));
+            breaker.check(peer)?;
+        }
+        breaker.record_failure(peer);
+        assert_eq!(BreakerState::Open, breaker.state(peer));
+        match breaker.check(peer) {
+            Err(e) => match e.downcast_ref::<Error>() {
+                Some(Error::CircuitOpen(p)) => assert_eq!(peer, p),
+                _ => panic!("unexpected error: {}", e),
+            },
+            Ok(()) => panic!("open breaker allowed a send"),
+        }
+
+        thread::sleep(cooldown);
+        assert_eq!(BreakerState::HalfOpen, breaker.state(peer));
+        breaker.check(peer)?;
+        breaker.record_failure(peer);
+        assert_eq!(BreakerState::Open, breaker.state(peer));
+
+        thread::sleep(cooldown);
+        breaker.record_success(peer);
+        assert_eq!(BreakerState::Closed, breaker.state(peer));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+/// # Fantom Libtransport/bridge
+///
+/// This file defines `FormatBridge`, which relays messages between clusters speaking different
+/// serialization formats, e.g. a legacy cluster on bincode and a new one on JSON. A bridge node
+/// reads the raw frames of its ingress transport (see `Transport::raw_stream`), decodes each into
+/// `Data` with the inbound format, re-encodes it with the outbound format and sends it to the
+/// egress peer set with `Transport::send_raw`.
+///
+/// Both transports must implement the raw methods, which are not supported by default.
+use crate::errors::Result;
+use crate::format::SerializationFormat;
+use crate::Transport;
+use libcommon_rs::peer::{Peer, PeerId, PeerList};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Re-serializes frames from an inbound to an outbound format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FormatBridge {
+    inbound: SerializationFormat,
+    outbound: SerializationFormat,
+}
+
+impl FormatBridge {
+    /// Creates a bridge decoding frames in `inbound` format and re-encoding them in `outbound`.
+    pub fn new(inbound: SerializationFormat, outbound: SerializationFormat) -> FormatBridge {
+        FormatBridge { inbound, outbound }
+    }
+
+    /// Returns the format frames are received in.
+    pub fn inbound(&self) -> SerializationFormat {
+        self.inbound
+    }
+
+    /// Returns the format frames are sent in.
+    pub fn outbound(&self) -> SerializationFormat {
+        self.outbound
+    }
+
+    /// Decodes an inbound `frame` as `Data` and returns it encoded in the outbound format.
+    pub fn translate<Data: Serialize + DeserializeOwned>(&self, frame: &[u8]) -> Result<Vec<u8>> {
+        let data: Data = self.inbound.decode(frame)?;
+        self.outbound.encode(&data)
+    }
+
+    /// Translates an inbound `frame` and sends it to every peer of `peers`, using their `base`
+    /// address, through `egress`.
+    pub fn forward<Id, Data, Error, Pl, T>(
+        &self,
+        frame: &[u8],
+        egress: &mut T,
+        peers: &mut Pl,
+    ) -> Result<()>
+    where
+        Id: PeerId,
+        Pl: PeerList<Id, Error>,
+        Data: Serialize + DeserializeOwned,
+        T: Transport<Id, Data, Error, Pl>,
+    {
+        let bytes = self.translate::<Data>(frame)?;
+        for peer in peers.iter() {
+            egress.send_raw(peer.get_base_addr(), bytes.clone())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+    use super::*;
+    use crate::generic_test::Data;
+
+    /*
+        Tests FormatBridge from bincode ingress to JSON egress: the egress receiver must decode the
+        bridged frame to the original Data, and an undecodable ingress frame must be rejected.
+    */
+    #[test]
+    fn format_bridge_test() -> Result<()> {
+        let bridge = FormatBridge::new(SerializationFormat::Bincode, SerializationFormat::Json);
+        let d: Data = Data(32);
+        let ingress = SerializationFormat::Bincode.encode(&d)?;
+
+        let egress = bridge.translate::<Data>(&ingress)?;
+        assert_eq!(d, SerializationFormat::Json.decode::<Data>(&egress)?);
+        assert_eq!(b"32".to_vec(), egress);
+
+        assert!(bridge.translate::<Data>(&[1]).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+/// # Fantom Libtransport/channel
+///
+/// This file defines logical channels, which allow several sub-protocols (e.g. sync and consensus)
+/// to share one Transport without wrapping their `Data` in an enum. Each frame carries its channel
+/// id as a small prefix:
+///
+/// [ channel: u16, big-endian ][ bincode(data) ]
+///
+/// Messages sent with a plain `send` belong to `DEFAULT_CHANNEL`.
+use crate::codec::bincode_decode;
+use crate::errors::{Error, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Channel of messages sent without an explicit channel.
+pub const DEFAULT_CHANNEL: u16 = 0;
+
+/// Size in bytes of the channel prefix.
+const CHANNEL_SIZE: usize = 2;
+
+/// Serializes `data` prefixed with its `channel` id.
+pub fn encode<Data: Serialize>(channel: u16, data: &Data) -> Result<Vec<u8>> {
+    let data_bytes = bincode::serialize(data).map_err(Error::from)?;
+    let mut out = Vec::with_capacity(CHANNEL_SIZE + data_bytes.len());
+    out.extend_from_slice(&channel.to_be_bytes());
+    out.extend_from_slice(&data_bytes);
+    Ok(out)
+}
+
+/// Splits a frame produced by `encode` into its channel id and `Data`.
+pub fn decode<Data: DeserializeOwned>(bytes: &[u8]) -> Result<(u16, Data)> {
+    if bytes.len() < CHANNEL_SIZE {
+        return Err(Error::Incomplete.into());
+    }
+    let channel = u16::from_be_bytes([bytes[0], bytes[1]]);
+    let data: Data = bincode_decode(&bytes[CHANNEL_SIZE..])?;
+    Ok((channel, data))
+}
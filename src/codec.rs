@@ -0,0 +1,199 @@
+/// # Fantom Libtransport/codec
+///
+/// This file contains the serialization helpers used to turn `Data` into frames and received frames
+/// back into `Data`.
+///
+/// Decoding untrusted input with plain `bincode::deserialize` is unbounded: a crafted frame can
+/// declare a huge length and trigger a huge allocation. Transports should therefore decode with
+/// `bincode_decode`, which applies `DEFAULT_MAX_MESSAGE_SIZE`, or with `bincode_decode_limited`
+/// when they are configured with a different limit. The encoding is the same as that of
+/// `bincode::serialize`.
+///
+/// Transports which let users choose the serialization go through the `Codec` trait instead of
+/// these helpers, see `Transport::set_codec`. `BincodeCodec` is the default; with the `json`
+/// feature `JsonCodec` can be swapped in without forking a transport. Each
+/// `SerializationFormat` maps to one of these codecs, see `SerializationFormat::codec`.
+use crate::errors::{Error, Result};
+use bincode::Options;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Default upper bound in bytes for a single decoded message.
+pub const DEFAULT_MAX_MESSAGE_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Encodes `data` with bincode, mapping failures to `Error::Bincode`.
+pub fn bincode_encode<T: Serialize>(data: &T) -> Result<Vec<u8>> {
+    bincode::serialize(data).map_err(|e| Error::Bincode(e).into())
+}
+
+/// Returns the number of bytes `data` occupies once encoded with `bincode_encode`, without
+/// encoding it, so callers can enforce size or queue-capacity policies up front.
+pub fn serialized_size<T: Serialize>(data: &T) -> Result<u64> {
+    bincode::serialized_size(data).map_err(|e| Error::Bincode(e).into())
+}
+
+/// Decodes a bincode `bytes` buffer, failing with `Error::MessageTooLarge` if decoding would read
+/// or allocate more than `limit` bytes.
+pub fn bincode_decode_limited<T: DeserializeOwned>(bytes: &[u8], limit: u64) -> Result<T> {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(limit)
+        .deserialize(bytes)
+        .map_err(|e| match *e {
+            bincode::ErrorKind::SizeLimit => Error::MessageTooLarge(limit).into(),
+            _ => Error::Bincode(e).into(),
+        })
+}
+
+/// Decodes a bincode `bytes` buffer using `DEFAULT_MAX_MESSAGE_SIZE` as the size limit.
+pub fn bincode_decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    bincode_decode_limited(bytes, DEFAULT_MAX_MESSAGE_SIZE)
+}
+
+/// Serializes `Data` into the bytes sent on the wire and deserializes received bytes back.
+pub trait Codec<Data> {
+    /// Serializes `data`.
+    fn encode(&self, data: &Data) -> Result<Vec<u8>>;
+
+    /// Deserializes a value written by `encode`.
+    fn decode(&self, bytes: &[u8]) -> Result<Data>;
+}
+
+/// The bincode Codec, encoding as `bincode_encode` and decoding with a size limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BincodeCodec {
+    limit: u64,
+}
+
+impl BincodeCodec {
+    /// Creates a codec decoding with `DEFAULT_MAX_MESSAGE_SIZE` as the size limit.
+    pub fn new() -> BincodeCodec {
+        BincodeCodec::with_limit(DEFAULT_MAX_MESSAGE_SIZE)
+    }
+
+    /// Creates a codec failing with `Error::MessageTooLarge` when decoding would read or allocate
+    /// more than `limit` bytes.
+    pub fn with_limit(limit: u64) -> BincodeCodec {
+        BincodeCodec { limit }
+    }
+}
+
+impl Default for BincodeCodec {
+    fn default() -> BincodeCodec {
+        BincodeCodec::new()
+    }
+}
+
+impl<Data: Serialize + DeserializeOwned> Codec<Data> for BincodeCodec {
+    fn encode(&self, data: &Data) -> Result<Vec<u8>> {
+        bincode_encode(data)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Data> {
+        bincode_decode_limited(bytes, self.limit)
+    }
+}
+
+/// The JSON Codec.
+#[cfg(feature = "json")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct JsonCodec;
+
+/// Encodes `data` as JSON, mapping failures to `Error::Json`.
+#[cfg(feature = "json")]
+pub fn json_encode<T: Serialize>(data: &T) -> Result<Vec<u8>> {
+    serde_json::to_vec(data).map_err(|e| Error::Json(e).into())
+}
+
+/// Decodes a JSON `bytes` buffer, mapping failures to `Error::Json`.
+#[cfg(feature = "json")]
+pub fn json_decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    serde_json::from_slice(bytes).map_err(|e| Error::Json(e).into())
+}
+
+#[cfg(feature = "json")]
+impl<Data: Serialize + DeserializeOwned> Codec<Data> for JsonCodec {
+    fn encode(&self, data: &Data) -> Result<Vec<u8>> {
+        json_encode(data)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Data> {
+        json_decode(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::SerializationFormat;
+    use crate::generic_test::Data;
+
+    /*
+        Tests that decoding is size limited: a valid frame decodes, while a frame declaring a huge
+        length fails with Error::MessageTooLarge instead of allocating.
+    */
+    #[test]
+    fn decode_limit_test() -> Result<()> {
+        let d: Data = Data(55);
+        let bytes = bincode_encode(&d)?;
+        assert_eq!(d, bincode_decode_limited::<Data>(&bytes, 1024)?);
+
+        // A String whose length prefix claims u64::MAX bytes.
+        let huge = [0xffu8; 8];
+        match bincode_decode_limited::<String>(&huge, 1024) {
+            Ok(_) => panic!("expected MessageTooLarge"),
+            Err(e) => match e.downcast_ref::<Error>() {
+                Some(Error::MessageTooLarge(1024)) => {}
+                _ => panic!("unexpected error: {:?}", e),
+            },
+        }
+
+        Ok(())
+    }
+
+    /*
+        Tests serialized_size: it must equal the length of the encoded bytes.
+    */
+    #[test]
+    fn serialized_size_test() -> Result<()> {
+        let d: Data = Data(55);
+        assert_eq!(bincode_encode(&d)?.len() as u64, serialized_size(&d)?);
+
+        Ok(())
+    }
+
+    /*
+        Tests the codecs: BincodeCodec and, with the `json` feature, JsonCodec must round-trip Data
+        and encode as their SerializationFormat does, and decoding garbage with BincodeCodec must
+        fail with Error::Bincode.
+    */
+    #[test]
+    fn codec_test() -> Result<()> {
+        let d: Data = Data(25);
+        let bincode = BincodeCodec::new();
+        let bytes = Codec::<Data>::encode(&bincode, &d)?;
+        assert_eq!(bincode_encode(&d)?, bytes);
+        assert_eq!(SerializationFormat::Bincode.encode(&d)?, bytes);
+        assert_eq!(d, Codec::<Data>::decode(&bincode, &bytes)?);
+
+        #[cfg(feature = "json")]
+        {
+            let json = SerializationFormat::Json.codec::<Data>();
+            let bytes = json.encode(&d)?;
+            assert_eq!(SerializationFormat::Json.encode(&d)?, bytes);
+            assert_eq!(d, json.decode(&bytes)?);
+        }
+
+        let garbage: &[u8] = &[0xff];
+        match Codec::<Data>::decode(&bincode, garbage) {
+            Err(e) => match e.downcast_ref::<Error>() {
+                Some(Error::Bincode(_)) => {}
+                _ => panic!("unexpected error: {}", e),
+            },
+            Ok(d) => panic!("unexpected {:?}", d),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+/// # Fantom Libtransport/compression
+///
+/// This file defines the connection-level compression algorithms a Transport may negotiate with
+/// `Transport::set_stream_compression`. Unlike per-message compression, a streaming compressor
+/// wraps the whole byte stream of a connection, so redundancy across messages (repeated field
+/// names, similar payloads) is exploited as well.
+///
+/// Compression is negotiated in the handshake: a side with compression enabled advertises
+/// `handshake::FEATURE_COMPRESSION`, and the connection is compressed only if both sides do (see
+/// `negotiate`). When the peer doesn't support it the connection silently falls back to no
+/// compression, so compression-enabled and plain nodes interoperate.
+use crate::handshake::FEATURE_COMPRESSION;
+
+/// A streaming compression algorithm for a connection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// No compression (the default).
+    None,
+    /// A DEFLATE stream, flushed after every frame so each message can be decoded on arrival.
+    Deflate,
+}
+
+impl Compression {
+    /// Returns the handshake feature bits advertised for this algorithm.
+    pub fn features(self) -> u32 {
+        match self {
+            Compression::None => 0,
+            Compression::Deflate => FEATURE_COMPRESSION,
+        }
+    }
+}
+
+/// Returns the compression to use on a connection, given the local setting and the feature
+/// bitmask negotiated with the peer: `local` if the peer supports compression, else `None`.
+pub fn negotiate(local: Compression, negotiated_features: u32) -> Compression {
+    if negotiated_features & FEATURE_COMPRESSION != 0 {
+        local
+    } else {
+        Compression::None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /*
+        Tests compression negotiation: the local algorithm is used only if the peer advertised
+        compression support, else the connection falls back to no compression.
+    */
+    #[test]
+    fn compression_negotiation_test() {
+        let features = Compression::Deflate.features();
+        assert_eq!(
+            Compression::Deflate,
+            negotiate(Compression::Deflate, features)
+        );
+        assert_eq!(Compression::None, negotiate(Compression::Deflate, 0));
+        assert_eq!(Compression::None, negotiate(Compression::None, features));
+    }
+}
@@ -0,0 +1,82 @@
+/// # Fantom Libtransport/concurrency
+///
+/// This file defines `SendLimiter`, a cap on simultaneous in-flight sends. Broadcasting in
+/// parallel to many peers can otherwise exhaust OS resources (sockets, threads).
+///
+/// A limiter is handed to a transport with `Transport::set_send_limiter`; the transport holds a
+/// permit from `acquire` for the duration of every send. While the limit is reached, further sends
+/// wait for a permit to be released. Clones share their permits, so one limiter set on several
+/// transports caps their sends together. The default limiter is unbounded.
+use crate::errors::Result;
+use crate::semaphore::{Semaphore, SemaphoreGuard};
+use std::sync::Arc;
+
+/// A cap on the number of simultaneous in-flight sends.
+#[derive(Clone, Default)]
+pub struct SendLimiter {
+    // None when unbounded.
+    semaphore: Option<Arc<Semaphore>>,
+}
+
+impl SendLimiter {
+    /// Creates a limiter allowing at most `limit` sends in flight at once; 0 means unbounded.
+    pub fn new(limit: usize) -> SendLimiter {
+        SendLimiter {
+            semaphore: if limit == 0 {
+                None
+            } else {
+                Some(Arc::new(Semaphore::new(limit)))
+            },
+        }
+    }
+
+    /// Waits until fewer than the limit sends are in flight and takes a permit, released when
+    /// the returned guard is dropped. Returns `None` without waiting if the limiter is unbounded.
+    pub fn acquire(&self) -> Result<Option<SemaphoreGuard<'_>>> {
+        match self.semaphore {
+            Some(ref semaphore) => semaphore.acquire().map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    // With the limit set to 2, ten concurrent sends must never have more than 2 in flight.
+    #[test]
+    fn send_limiter_test() {
+        let limiter = SendLimiter::new(2);
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let sends: Vec<_> = (0..10)
+            .map(|_| {
+                let limiter = limiter.clone();
+                let in_flight = in_flight.clone();
+                let max_in_flight = max_in_flight.clone();
+                thread::spawn(move || {
+                    let _permit = limiter.acquire().unwrap();
+                    let n = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_in_flight.fetch_max(n, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(20));
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        for send in sends {
+            send.join().unwrap();
+        }
+        let max = max_in_flight.load(Ordering::SeqCst);
+        assert!(max >= 1 && max <= 2, "{} sends in flight at once", max);
+    }
+
+    #[test]
+    fn unbounded_send_limiter_test() {
+        assert!(SendLimiter::new(0).acquire().unwrap().is_none());
+        assert!(SendLimiter::default().acquire().unwrap().is_none());
+    }
+}
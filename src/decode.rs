@@ -0,0 +1,159 @@
+/// # Fantom Libtransport/decode
+///
+/// This file defines `DecodeStream`, the default receive wrapper for Transport implementors. It
+/// turns a stream of received frames (raw bincode bytes) into a stream of `Data`, skipping frames
+/// which fail to decode instead of ending the stream, so a single malformed frame cannot cut a
+/// peer off. Skipped frames are counted, see `DecodeStream::malformed`.
+///
+/// Consumers which want to see decode errors instead, and decide themselves whether they are
+/// fatal, use `decode_results`, which backs `Transport::result_stream`.
+use crate::codec::bincode_decode;
+use crate::errors::Result;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use futures::stream::{FusedStream, Stream, StreamExt};
+use serde::de::DeserializeOwned;
+
+/// A stream decoding frames into `Data`, skipping malformed ones.
+pub struct DecodeStream<S, Data> {
+    inner: S,
+    malformed: u64,
+    _data: PhantomData<fn() -> Data>,
+}
+
+impl<S, Data> DecodeStream<S, Data> {
+    /// Wraps a stream of received frames.
+    pub fn new(inner: S) -> DecodeStream<S, Data> {
+        DecodeStream {
+            inner,
+            malformed: 0,
+            _data: PhantomData,
+        }
+    }
+
+    /// Returns the number of frames skipped because they failed to decode.
+    pub fn malformed(&self) -> u64 {
+        self.malformed
+    }
+
+    /// Unwraps the inner stream.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+// The fields are never pinned: the inner stream is polled through `Pin::new`.
+impl<S: Unpin, Data> Unpin for DecodeStream<S, Data> {}
+
+impl<S, Data> Stream for DecodeStream<S, Data>
+where
+    S: Stream<Item = Vec<u8>> + Unpin,
+    Data: DeserializeOwned,
+{
+    type Item = Data;
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Data>> {
+        let this = &mut *self;
+        loop {
+            match Pin::new(&mut this.inner).poll_next(cx) {
+                Poll::Ready(Some(bytes)) => match bincode_decode(&bytes) {
+                    Ok(data) => return Poll::Ready(Some(data)),
+                    Err(_) => this.malformed += 1,
+                },
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<S, Data> FusedStream for DecodeStream<S, Data>
+where
+    S: FusedStream<Item = Vec<u8>> + Unpin,
+    Data: DeserializeOwned,
+{
+    fn is_terminated(&self) -> bool {
+        self.inner.is_terminated()
+    }
+}
+
+/// Decodes a stream of received frames into `Data`, yielding decode errors inline rather than
+/// skipping the frame.
+pub fn decode_results<S, Data>(frames: S) -> impl Stream<Item = Result<Data>>
+where
+    S: Stream<Item = Vec<u8>>,
+    Data: DeserializeOwned,
+{
+    frames.map(|bytes| bincode_decode(&bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::bincode_encode;
+    use crate::errors::Error;
+    use crate::generic_test::Data;
+    use futures::executor::block_on;
+
+    /*
+        Tests the receive stream's malformed frame contract through DecodeStream: a corrupt frame
+        between two valid ones must be skipped and counted, and both valid messages still received.
+    */
+    #[test]
+    fn decode_recovery_test() -> Result<()> {
+        let frames: Vec<Vec<u8>> = vec![
+            bincode_encode(&Data(1))?,
+            vec![0xff],
+            bincode_encode(&Data(2))?,
+        ];
+        let mut stream: DecodeStream<_, Data> = DecodeStream::new(futures::stream::iter(frames));
+
+        block_on(async {
+            for expected in [Data(1), Data(2)].iter() {
+                match stream.next().await {
+                    Some(t) => assert_eq!(*expected, t),
+                    None => panic!("unexpected None"),
+                }
+            }
+            assert_eq!(None, stream.next().await);
+        });
+        assert_eq!(1, stream.malformed());
+
+        Ok(())
+    }
+
+    /*
+        Tests the receive error contract of result_stream through decode_results: a corrupt frame
+        between two valid ones must be yielded as Ok, Err, Ok in order.
+    */
+    #[test]
+    fn result_stream_test() -> Result<()> {
+        let frames: Vec<Vec<u8>> = vec![
+            bincode_encode(&Data(1))?,
+            vec![0xff],
+            bincode_encode(&Data(2))?,
+        ];
+        let mut stream = Box::pin(decode_results::<_, Data>(futures::stream::iter(frames)));
+
+        block_on(async {
+            match stream.next().await {
+                Some(Ok(t)) => assert_eq!(Data(1), t),
+                other => panic!("unexpected {:?}", other.map(|r| r.is_ok())),
+            }
+            match stream.next().await {
+                Some(Err(e)) => match e.downcast_ref::<Error>() {
+                    Some(Error::Bincode(_)) => {}
+                    _ => panic!("unexpected error: {}", e),
+                },
+                other => panic!("unexpected {:?}", other.map(|r| r.is_ok())),
+            }
+            match stream.next().await {
+                Some(Ok(t)) => assert_eq!(Data(2), t),
+                other => panic!("unexpected {:?}", other.map(|r| r.is_ok())),
+            }
+            assert!(stream.next().await.is_none());
+        });
+
+        Ok(())
+    }
+}
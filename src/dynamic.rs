@@ -0,0 +1,91 @@
+/// # Fantom Libtransport/dynamic
+///
+/// This file defines `DynTransport`, an object-safe subset of the `Transport` trait. `Transport`
+/// itself cannot be used as a trait object because of its `new` constructor and `Stream`
+/// associated type, so this trait exposes only the runtime methods: `send`, `broadcast`,
+/// `broadcast_n`, `close`, and polling for received data.
+///
+/// Every `Transport` implements `DynTransport` through a blanket implementation, so a boxed trait
+/// object is obtained by constructing a concrete transport and boxing it:
+///
+/// ```ignore
+/// let t: Box<dyn DynTransport<Id, Data, Error, Pl>> = Box::new(TCPtransport::new(addr)?);
+/// ```
+///
+/// `dyn DynTransport` implements `Stream`, so `t.next().await` works on the boxed value. This
+/// allows choosing e.g. between TCP and UDP at runtime.
+///
+/// NOTE: as the methods share their names with `Transport`, avoid importing both traits into a
+/// module which calls them on concrete transports.
+use crate::errors::Result;
+use crate::Transport;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use futures::stream::Stream;
+use libcommon_rs::peer::{PeerId, PeerList};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::Unpin;
+
+/// DynTransport is the object-safe runtime interface of a `Transport`.
+pub trait DynTransport<Id, Data, Error, Pl>: Unpin
+where
+    Id: PeerId,
+    Pl: PeerList<Id, Error>,
+    Data: Serialize + DeserializeOwned,
+{
+    /// Sends a message of type 'Data' to the specified peer (as specified by `peer_address`)
+    fn send(&mut self, peer_address: String, data: Data) -> Result<()>;
+
+    /// Broadcasts a message of type 'Data' to all peers on the network using `base` address.
+    fn broadcast(&mut self, peers: &mut Pl, data: Data) -> Result<()>;
+
+    /// Broadcasts a message of type 'Data' to all peers on the network using nth address in `net_addr`.
+    fn broadcast_n(&mut self, peers: &mut Pl, n: usize, data: Data) -> Result<()>;
+
+    /// Closes the transport.
+    fn close(&mut self) -> Result<()>;
+
+    /// Polls for the next received message, as `Stream::poll_next` does.
+    fn poll_recv(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Data>>;
+}
+
+impl<Id, Data, Error, Pl, T> DynTransport<Id, Data, Error, Pl> for T
+where
+    Id: PeerId,
+    Pl: PeerList<Id, Error>,
+    Data: Serialize + DeserializeOwned,
+    T: Transport<Id, Data, Error, Pl>,
+{
+    fn send(&mut self, peer_address: String, data: Data) -> Result<()> {
+        Transport::send(self, peer_address, data)
+    }
+
+    fn broadcast(&mut self, peers: &mut Pl, data: Data) -> Result<()> {
+        Transport::broadcast(self, peers, data)
+    }
+
+    fn broadcast_n(&mut self, peers: &mut Pl, n: usize, data: Data) -> Result<()> {
+        Transport::broadcast_n(self, peers, n, data)
+    }
+
+    fn close(&mut self) -> Result<()> {
+        Transport::close(self)
+    }
+
+    fn poll_recv(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Data>> {
+        Stream::poll_next(self, cx)
+    }
+}
+
+impl<'a, Id, Data, Error, Pl> Stream for dyn DynTransport<Id, Data, Error, Pl> + 'a
+where
+    Id: PeerId,
+    Pl: PeerList<Id, Error>,
+    Data: Serialize + DeserializeOwned,
+{
+    type Item = Data;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Data>> {
+        self.poll_recv(cx)
+    }
+}
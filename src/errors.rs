@@ -6,6 +6,7 @@ use failure::Error as FailureError;
 use libcommon_rs::errors::Error as BaseError;
 use std::error::Error as StdError;
 use std::sync::{MutexGuard, PoisonError};
+use std::time::Duration;
 
 /// Standard Error type as defiend by the std library.
 pub type Result<T> = std::result::Result<T, FailureError>;
@@ -13,21 +14,72 @@ pub type Result<T> = std::result::Result<T, FailureError>;
 /// A set of enums to differentiate between different types of errors.
 #[derive(Debug, Fail)]
 pub enum Error {
-    #[fail(display = "Base Error: {:?}", 0)]
+    #[fail(display = "Base Error: {:?}", _0)]
     Base(BaseError),
     // Indicating a vector reached max capacity and can not receive new element
     #[fail(display = "Internal vector is at maximum capacity!")]
     AtMaxVecCapacity,
-    #[fail(display = "Bincode error: {:?}", 0)]
+    #[fail(display = "Bincode error: {:?}", _0)]
     Bincode(bincode::Error),
-    #[fail(display = "Io error: {:?}", 0)]
+    #[fail(display = "Io error: {:?}", _0)]
     Io(std::io::Error),
+    #[cfg(feature = "json")]
+    #[fail(display = "Json error: {:?}", _0)]
+    Json(serde_json::Error),
     // Indicating read/write operation was unable to read/write complete size of data
     #[fail(display = "Incomplete!")]
     Incomplete,
-    #[fail(display = "Poison error: {:?}", 0)]
+    #[fail(display = "Poison error: {:?}", _0)]
     PoisonError(String),
+    // Indicating an operation was aborted through its AbortHandle before it completed
+    #[fail(display = "Operation aborted!")]
+    Aborted,
+    // Indicating sends to a peer are suspended by its open circuit breaker
+    #[fail(display = "Circuit open for peer: {}", _0)]
+    CircuitOpen(String),
+    // Aggregates the errors of several failed operations, e.g. a best-effort broadcast
+    #[fail(display = "Multiple errors: {:?}", _0)]
+    MultipleErrors(Vec<String>),
+    // Indicating a network address could not be parsed or resolved
+    #[fail(display = "Invalid address: {}", _0)]
+    AddrParse(String),
+    // Indicating a message exceeded the given size limit in bytes
+    #[fail(display = "Message exceeds size limit of {} bytes", _0)]
+    MessageTooLarge(u64),
+    // Indicating the transport is not yet bound/listening and cannot send
+    #[fail(display = "Transport is not ready!")]
+    NotReady,
+    // Indicating no address could be found for the given peer
+    #[fail(display = "Peer not found: {}", _0)]
+    PeerNotFound(String),
+    // Indicating a frame carried a format version this crate cannot decode
+    #[fail(display = "Unsupported frame version: {}", _0)]
+    UnsupportedFrameVersion(u8),
+    // Indicating the transport implementation does not support the requested operation
+    #[fail(display = "Unsupported operation: {}", _0)]
+    Unsupported(String),
+    // Indicating an operation did not complete before its deadline
+    #[fail(display = "Timed out after {:?}", _0)]
+    Timeout(Duration),
+    // Indicating frames of a sequence-tracked stream were lost between `expected` and `got`
+    #[fail(display = "Sequence gap: expected {}, got {}", expected, got)]
+    SequenceGap { expected: u64, got: u64 },
+    // Indicating the queue of frames waiting for a peer's connection is full
+    #[fail(display = "Pending queue full for peer: {}", _0)]
+    QueueFull(String),
+    // Indicating a transport type string could not be parsed
+    #[fail(display = "Unknown transport type: {}", _0)]
+    UnknownTransportType(String),
 }
+
+impl Error {
+    /// Creates a `Timeout` error for a deadline which expired after `elapsed`, for timer
+    /// implementations without an elapsed error type of their own to convert from.
+    pub fn timeout(elapsed: Duration) -> Error {
+        Error::Timeout(elapsed)
+    }
+}
+
 /// Allow errors to be converted from a standard error to a BaseError type.
 impl From<BaseError> for Error {
     #[inline]
@@ -66,3 +118,29 @@ macro_rules! none_error {
         libtransport::errors::Error::Base(libcommon_rs::errors::Error::NoneError)
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /*
+        Tests the Timeout error: it must keep the elapsed duration, show it in its Display string
+        and stay distinguishable from I/O failures once converted to a failure::Error.
+    */
+    #[test]
+    fn timeout_error_test() -> Result<()> {
+        let e = Error::timeout(Duration::from_millis(200));
+        assert_eq!("Timed out after 200ms", format!("{}", e));
+
+        let result: Result<()> = Err(e.into());
+        match result {
+            Err(e) => match e.downcast_ref::<Error>() {
+                Some(Error::Timeout(d)) => assert_eq!(Duration::from_millis(200), *d),
+                _ => panic!("unexpected error: {}", e),
+            },
+            Ok(()) => panic!("unexpected Ok"),
+        }
+
+        Ok(())
+    }
+}
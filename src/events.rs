@@ -0,0 +1,28 @@
+/// # Fantom Libtransport/events
+///
+/// This file defines the connection events a Transport may report about its peer connections.
+/// Events are delivered over a std mpsc channel obtained from `Transport::subscribe_events`.
+
+/// A change in the state of a connection, identified by the remote peer address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TransportEvent {
+    /// A connection to the peer was established.
+    Connected(String),
+    /// The connection to the peer was closed, for the given reason.
+    Disconnected(String, DisconnectReason),
+}
+
+/// Why a connection was closed, so callers can pick the matching recovery.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// Closed on purpose, e.g. by `Transport::disconnect` or `close_all`, or by the peer.
+    Graceful,
+    /// Closed after a timeout, e.g. by the idle connection reaper.
+    Timeout,
+    /// Closed because the peer violated the protocol, e.g. sent a malformed handshake.
+    ProtocolError(String),
+    /// Closed because the peer is banned.
+    Banned,
+    /// Closed after an I/O error on the connection.
+    IoError(String),
+}
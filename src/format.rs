@@ -0,0 +1,82 @@
+/// # Fantom Libtransport/format
+///
+/// This file defines the serialization formats a Transport can speak on the wire, as reported by
+/// `Transport::capabilities`. Nodes of a cluster must agree on the format; `FormatBridge`
+/// connects clusters speaking different ones.
+///
+/// Each format is backed by a codec of the `codec` module: Bincode by `BincodeCodec` and JSON,
+/// available with the `json` feature, by `JsonCodec`. JSON is readable and widely interoperable,
+/// at the cost of larger frames.
+use crate::codec::{bincode_decode, bincode_encode, BincodeCodec, Codec};
+#[cfg(feature = "json")]
+use crate::codec::{json_decode, json_encode, JsonCodec};
+use crate::errors::{Error, Result};
+use core::fmt;
+use core::str::FromStr;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// A wire serialization format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SerializationFormat {
+    Bincode,
+    #[cfg(feature = "json")]
+    Json,
+}
+
+impl SerializationFormat {
+    /// Serializes `data` in this format, as the format's codec does.
+    pub fn encode<T: Serialize>(self, data: &T) -> Result<Vec<u8>> {
+        match self {
+            SerializationFormat::Bincode => bincode_encode(data),
+            #[cfg(feature = "json")]
+            SerializationFormat::Json => json_encode(data),
+        }
+    }
+
+    /// Deserializes a value written in this format, as the format's codec does.
+    pub fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T> {
+        match self {
+            SerializationFormat::Bincode => bincode_decode(bytes),
+            #[cfg(feature = "json")]
+            SerializationFormat::Json => json_decode(bytes),
+        }
+    }
+
+    /// Returns the codec of this format, e.g. for `Transport::set_codec`.
+    pub fn codec<Data>(self) -> Box<dyn Codec<Data> + Send + Sync>
+    where
+        Data: Serialize + DeserializeOwned,
+    {
+        match self {
+            SerializationFormat::Bincode => Box::new(BincodeCodec::new()),
+            #[cfg(feature = "json")]
+            SerializationFormat::Json => Box::new(JsonCodec),
+        }
+    }
+}
+
+/// Allows a SerializationFormat to be parsed from a configuration string (case insensitive).
+impl FromStr for SerializationFormat {
+    type Err = Error;
+    fn from_str(s: &str) -> std::result::Result<SerializationFormat, Error> {
+        match s.to_lowercase().as_str() {
+            "bincode" => Ok(SerializationFormat::Bincode),
+            #[cfg(feature = "json")]
+            "json" => Ok(SerializationFormat::Json),
+            _ => Err(Error::Unsupported(format!("serialization format {}", s))),
+        }
+    }
+}
+
+/// Displays a SerializationFormat in the same form accepted by `FromStr`.
+impl fmt::Display for SerializationFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            SerializationFormat::Bincode => "bincode",
+            #[cfg(feature = "json")]
+            SerializationFormat::Json => "json",
+        };
+        write!(f, "{}", name)
+    }
+}
@@ -6,17 +6,138 @@
 ///
 /// The common_test method allows us to quickly test the new(), send(), and broadcast() methods and
 /// (hopefully) verifies that they work.
+///
+/// common_test expects every message to arrive exactly once, so it suits reliable transports
+/// (`Transport::is_reliable`). Datagram transports which may lose or duplicate messages should use
+/// unreliable_common_test instead.
+///
+/// The test_* methods can be run against a transport type all at once with
+/// `transport_test_suite!` (see the `suite` module).
+use crate::addr::{advertised_address, normalize_address};
+#[cfg(feature = "debug")]
+use crate::audit::{DropAudit, Unflushed};
+use crate::boxed::{BoxedTransport, PinnedTransport};
+use crate::codec::{bincode_encode, serialized_size, BincodeCodec};
+use crate::compression::Compression;
 use crate::errors::{Error, Error::AtMaxVecCapacity, Result};
-use crate::Transport;
-use core::fmt::Display;
+use crate::events::{DisconnectReason, TransportEvent};
+#[cfg(feature = "json")]
+use crate::format::SerializationFormat;
+use crate::handshake::{FEATURE_ACK, FEATURE_CHANNELS, FEATURE_COMPRESSION};
+use crate::merge::merge_receivers;
+use crate::metrics::Metrics;
+use crate::observe::Direction;
+use crate::rpc::{RpcMessage, RpcNode};
+use crate::{
+    BroadcastOutcome, BroadcastPolicy, DefaultConfiguration, ThreadSafeTransport, Transport,
+    TransportConfiguration, TransportReceiver, TransportSender,
+};
+use core::fmt::{Debug, Display};
+use core::marker::PhantomPinned;
+use core::pin::Pin;
 use core::slice::{Iter, IterMut};
+use core::task::{Context, Poll, Waker};
 use futures::executor::block_on;
-use futures::stream::StreamExt;
+use futures::future::Future;
+use futures::stream::{FusedStream, Stream, StreamExt};
+use futures::task::noop_waker_ref;
 use libcommon_rs::peer::{Peer, PeerList};
-use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize, Serializer};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::{SocketAddr, TcpListener, TcpStream};
 use std::ops::{Index, IndexMut};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Barrier, Mutex};
 use std::{thread, time};
 
+// Destination of the progress messages printed by the generic tests.
+pub trait TestLogger {
+    fn log(&self, msg: &str);
+}
+
+// The default logger, writing to stderr.
+pub struct StderrLogger;
+
+impl TestLogger for StderrLogger {
+    fn log(&self, msg: &str) {
+        eprintln!("{}", msg);
+    }
+}
+
+// A logger which keeps the messages in memory, e.g. to assert on them or to keep CI output clean.
+#[derive(Default)]
+pub struct CapturingLogger {
+    lines: Mutex<Vec<String>>,
+}
+
+impl CapturingLogger {
+    // Returns the messages logged so far.
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.lock().map(|l| l.clone()).unwrap_or_default()
+    }
+}
+
+impl TestLogger for CapturingLogger {
+    fn log(&self, msg: &str) {
+        if let Ok(mut lines) = self.lines.lock() {
+            lines.push(msg.to_string());
+        }
+    }
+}
+
+thread_local! {
+    static LOGGER: RefCell<Option<Arc<dyn TestLogger + Send + Sync>>> = RefCell::new(None);
+}
+
+// Installs the logger used by the generic tests running on the current thread; None restores the
+// default StderrLogger.
+pub fn set_test_logger(logger: Option<Arc<dyn TestLogger + Send + Sync>>) {
+    LOGGER.with(|l| *l.borrow_mut() = logger);
+}
+
+// Logs a message through the current thread's logger.
+fn log(msg: &str) {
+    LOGGER.with(|l| match &*l.borrow() {
+        Some(logger) => logger.log(msg),
+        None => StderrLogger.log(msg),
+    });
+}
+
+thread_local! {
+    static SKIPPED: RefCell<Vec<String>> = RefCell::new(Vec::new());
+}
+
+// Records that the generic test running on the current thread skips checks because the transport
+// lacks a feature, and logs why. Returns Ok(()) so that a test can return it.
+fn skip(reason: &str) -> Result<()> {
+    log(&format!("skipped: {}", reason));
+    SKIPPED.with(|s| s.borrow_mut().push(reason.to_string()));
+    Ok(())
+}
+
+// Runs the generic test `name` for transport_test_suite!. Panics if the test fails, if it skips
+// checks while not listed in `unsupported`, or if it is listed but skips nothing, so that neither
+// a missing feature nor a stale list passes unnoticed.
+pub fn run_suite_test<F>(name: &str, unsupported: &[&str], test: F)
+where
+    F: FnOnce() -> Result<()>,
+{
+    SKIPPED.with(|s| s.borrow_mut().clear());
+    test().unwrap();
+    let skipped: Vec<String> = SKIPPED.with(|s| s.borrow_mut().drain(..).collect());
+    match (unsupported.contains(&name), skipped.is_empty()) {
+        (false, false) => panic!("{} skipped checks: {}", name, skipped.join(", ")),
+        (true, true) => panic!("{} is listed as unsupported but skipped nothing", name),
+        _ => {}
+    }
+}
+
+// How long wait_ready waits for transports to become ready.
+const READY_TIMEOUT: time::Duration = time::Duration::from_secs(3);
+
 // Dummy data struct. Simply uses a u32 for instantiation.
 #[derive(Clone, Debug, Deserialize, Serialize, Eq, Hash, PartialEq, PartialOrd, Ord)]
 pub struct Data(pub u32);
@@ -43,6 +164,34 @@ impl Default for Id {
     }
 }
 
+// Dummy data struct which always fails to serialize.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct FailingData;
+
+impl Serialize for FailingData {
+    fn serialize<S: Serializer>(&self, _serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        Err(serde::ser::Error::custom("FailingData never serializes"))
+    }
+}
+
+// Data is small and fixed-size, so it may be broadcast with broadcast_safe.
+crate::impl_broadcast_safe!(Data);
+
+// Dummy data which serializes only when Good, for batches mixing good and bad items.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub enum MixedData {
+    Good(u32),
+    Bad(FailingData),
+}
+
+// Dummy data backed by a vector, for payloads of arbitrary size and content.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct BulkData(pub Vec<u8>);
+
+// Dummy data which serializes to zero bytes.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct EmptyData;
+
 // Allows a usize to be used for Data struct creation.
 impl From<usize> for Data {
     fn from(x: usize) -> Data {
@@ -76,15 +225,19 @@ impl Peer<Id, Error> for TestPeer<Id> {
     fn get_base_addr(&self) -> String {
         self.base_addr.clone()
     }
+    // Getter for the nth network address, empty past the last one
     fn get_net_addr(&self, n: usize) -> String {
-        self.net_addr[n].clone()
+        self.net_addr.get(n).cloned().unwrap_or_default()
     }
+    // Sets the nth network address, growing the list with empty addresses if needed
     fn set_net_addr(&mut self, n: usize, addr: String) -> std::result::Result<(), Error> {
-        if self.net_addr.len() == std::usize::MAX {
+        if n == std::usize::MAX {
             return Err(AtMaxVecCapacity);
         }
-        // FIXME: insert panics in n > net_addr.len()
-        self.net_addr.insert(n, addr);
+        if n >= self.net_addr.len() {
+            self.net_addr.resize(n + 1, String::new());
+        }
+        self.net_addr[n] = addr;
         Ok(())
     }
 }
@@ -145,19 +298,145 @@ impl PeerList<Id, Error> for TestPeerList<Id> {
     }
 }
 
+// Waits until every transport reports `is_ready`, failing with Error::NotReady after
+// READY_TIMEOUT. Used instead of sleeping for a fixed time after constructing transports.
+pub fn wait_ready<'a, D, T, I>(trns: I) -> Result<()>
+where
+    D: Serialize + DeserializeOwned,
+    T: Transport<Id, D, Error, TestPeerList<Id>> + 'a,
+    I: IntoIterator<Item = &'a T>,
+{
+    let deadline = time::Instant::now() + READY_TIMEOUT;
+    for trn in trns {
+        while !trn.is_ready() {
+            if time::Instant::now() >= deadline {
+                return Err(Error::NotReady.into());
+            }
+            thread::sleep(time::Duration::from_millis(10));
+        }
+    }
+    Ok(())
+}
+
+// Polls a stream once without blocking, returning an item only if one is ready right away.
+pub fn try_next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+    let mut cx = Context::from_waker(noop_waker_ref());
+    match Pin::new(stream).poll_next(&mut cx) {
+        Poll::Ready(item) => item,
+        Poll::Pending => None,
+    }
+}
+
+// Waits for the next item of `stream`, panicking unless it equals `expected`.
+pub fn expect_next<S>(stream: &mut S, expected: &S::Item)
+where
+    S: Stream + Unpin,
+    S::Item: PartialEq + Debug,
+{
+    match block_on(stream.next()) {
+        Some(t) => assert_eq!(*expected, t),
+        None => panic!("unexpected None"),
+    }
+}
+
+// Creates transports on the first two of `net_addrs` and waits until both are ready. Returns the
+// transport on `net_addrs[0]` first.
+pub fn ready_pair<T>(net_addrs: &[String]) -> Result<(T, T)>
+where
+    T: Transport<Id, Data, Error, TestPeerList<Id>>,
+{
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let a = T::new(net_addrs[0].clone())?;
+    let b = T::new(net_addrs[1].clone())?;
+    wait_ready(vec![&a, &b])?;
+    Ok((a, b))
+}
+
+// How many copies of each message unreliable_common_test sends.
+const UNRELIABLE_COPIES: usize = 5;
+
+// Waits up to READY_TIMEOUT for `trn` to yield `expected`, skipping duplicates of earlier
+// messages. Panics if something else arrives or nothing does.
+fn expect_eventually<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    trn: &mut T,
+    expected: &Data,
+    earlier: &[Data],
+) {
+    let deadline = time::Instant::now() + READY_TIMEOUT;
+    loop {
+        match try_next(trn) {
+            Some(ref t) if t == expected => return,
+            Some(ref t) if earlier.contains(t) => {}
+            Some(t) => panic!("unexpected message {:?}", t),
+            None => {
+                assert!(
+                    time::Instant::now() < deadline,
+                    "{:?} never arrived",
+                    expected
+                );
+                thread::sleep(time::Duration::from_millis(10));
+            }
+        }
+    }
+}
+
 /*
-    The function used to actually test the Transport. It takes in a Transport Configuration and a
-    Transport trait implementor.
+    The unreliable counterpart of common_test, for datagram transports such as UDP: every message
+    is sent UNRELIABLE_COPIES times and receivers must get at least one copy, tolerating lost and
+    duplicated copies.
+*/
+pub fn unreliable_common_test<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let mut pl: TestPeerList<Id> = TestPeerList::new();
+    let mut trns: Vec<T> = Vec::with_capacity(net_addrs.len());
+    for (i, net_addr) in net_addrs.iter().enumerate() {
+        pl.add(TestPeer::new(i.into(), net_addr.clone()))?;
+        trns.push(T::new(net_addr.clone())?);
+    }
+    wait_ready(&trns)?;
+
+    log("Unreliable broadcast test");
+    let d: Data = Data(55);
+    for _ in 0..UNRELIABLE_COPIES {
+        trns[0].broadcast(&mut pl, d.clone())?;
+    }
+    for (i, trn) in trns.iter_mut().enumerate() {
+        log(&format!("receiving from peer {}", i));
+        expect_eventually(trn, &d, &[]);
+    }
+
+    log("Unreliable unicast test");
+    let u: Data = Data(0xaa);
+    for _ in 0..UNRELIABLE_COPIES {
+        trns[1].send(pl[0].base_addr.clone(), u.clone())?;
+    }
+    expect_eventually(&mut trns[0], &u, &[d]);
+
+    Ok(())
+}
+
+/*
+    The function used to actually test the Transport. It takes in a Transport trait implementor.
 
     THis method simply takes in a list of peers, instantiates them, and tests whether they can
     send/receive data to one another.
 */
-pub fn common_test<
-    //    C: TransportConfiguration<Data>,
-    T: Transport<Id, Data, Error, TestPeerList<Id>>,
->(
+pub fn common_test<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
     net_addrs: Vec<String>,
 ) -> Result<()> {
+    common_test_with_config::<DefaultConfiguration, T>(net_addrs)
+}
+
+/*
+    common_test creating every Transport from a configuration of type C, e.g. one of the
+    implementation's own.
+*/
+pub fn common_test_with_config<C, T>(net_addrs: Vec<String>) -> Result<()>
+where
+    C: TransportConfiguration,
+    T: Transport<Id, Data, Error, TestPeerList<Id>>,
+{
     let n_peers = net_addrs.len();
     // Create a new TestPeerList
     let mut pl: TestPeerList<Id> = TestPeerList::new();
@@ -167,14 +446,15 @@ pub fn common_test<
     // messaging.
     for (i, net_addr) in net_addrs.iter().enumerate() {
         pl.add(TestPeer::new(i.into(), net_addr.clone()))?;
-        trns.push(T::new(net_addr.clone())?);
+        let cfg = C::new(net_addr.clone())?;
+        trns.push(T::new(cfg)?);
     }
 
-    // Wait three seconds.
-    thread::sleep(time::Duration::from_secs(3));
+    // Wait until every transport is bound and listening.
+    wait_ready(&trns)?;
 
     // Test broadcast
-    println!("Broadcast test");
+    log("Broadcast test");
 
     // Create Data to send.
     let d: Data = Data(55);
@@ -183,7 +463,7 @@ pub fn common_test<
     for (i, trn) in trns.iter_mut().enumerate() {
         // Asynchronously check if all peers have received the message.
         block_on(async {
-            println!("receiving from peer {}", i);
+            log(&format!("receiving from peer {}", i));
             let n = trn.next().await;
             match n {
                 Some(t) => assert_eq!(d, t),
@@ -193,7 +473,7 @@ pub fn common_test<
     }
 
     // Test direct sending
-    println!("Unicast test");
+    log("Unicast test");
     let u: Data = Data(0xaa);
     // Send directed data between two peers.
     trns[1].send(pl[0].base_addr.clone(), u.clone())?;
@@ -208,3 +488,3049 @@ pub fn common_test<
 
     Ok(())
 }
+
+// A single-threaded dummy transport used to confirm that `!Send` types (here via `Rc`) can
+// implement LocalTransport. Messages sent through it are looped back into its own stream.
+#[cfg(feature = "wasm")]
+pub struct LocalDummy {
+    inbox: std::rc::Rc<std::cell::RefCell<LocalInbox>>,
+}
+
+// Messages looped back by a LocalDummy and the waker of its pending reader.
+#[cfg(feature = "wasm")]
+#[derive(Default)]
+struct LocalInbox {
+    queue: std::collections::VecDeque<Data>,
+    waker: Option<core::task::Waker>,
+}
+
+#[cfg(feature = "wasm")]
+impl LocalDummy {
+    fn push(&mut self, data: Data) {
+        let mut inbox = self.inbox.borrow_mut();
+        inbox.queue.push_back(data);
+        if let Some(waker) = inbox.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl Drop for LocalDummy {
+    fn drop(&mut self) {}
+}
+
+#[cfg(feature = "wasm")]
+impl futures::stream::Stream for LocalDummy {
+    type Item = Data;
+    fn poll_next(
+        self: core::pin::Pin<&mut Self>,
+        cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<Option<Data>> {
+        let mut inbox = self.inbox.borrow_mut();
+        match inbox.queue.pop_front() {
+            Some(d) => core::task::Poll::Ready(Some(d)),
+            None => {
+                inbox.waker = Some(cx.waker().clone());
+                core::task::Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl crate::local::LocalTransport<Id, Data, Error, TestPeerList<Id>> for LocalDummy {
+    fn new<C: TransportConfiguration>(_cfg: C) -> Result<Self> {
+        Ok(LocalDummy {
+            inbox: std::rc::Rc::new(std::cell::RefCell::new(LocalInbox::default())),
+        })
+    }
+    fn send(&mut self, _peer_address: String, data: Data) -> Result<()> {
+        self.push(data);
+        Ok(())
+    }
+    fn broadcast(&mut self, peers: &mut TestPeerList<Id>, data: Data) -> Result<()> {
+        for _ in peers.iter() {
+            self.push(data.clone());
+        }
+        Ok(())
+    }
+    fn broadcast_n(&mut self, peers: &mut TestPeerList<Id>, _n: usize, data: Data) -> Result<()> {
+        self.broadcast(peers, data)
+    }
+    fn get_quit_tx(&self) -> Option<std::sync::mpsc::Sender<()>> {
+        None
+    }
+}
+
+/*
+    Tests BroadcastPolicy::BestEffort: `unreachable_addr` is added to the peer list between the
+    live peers and nothing listens on it. The broadcast must report the failure, yet every live
+    peer must still receive the message.
+*/
+pub fn test_broadcast_best_effort<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+    unreachable_addr: String,
+) -> Result<()> {
+    let n_peers = net_addrs.len();
+    let mut pl: TestPeerList<Id> = TestPeerList::new();
+    let mut trns: Vec<T> = Vec::with_capacity(n_peers);
+    for (i, net_addr) in net_addrs.iter().enumerate() {
+        pl.add(TestPeer::new(i.into(), net_addr.clone()))?;
+        trns.push(T::new(net_addr.clone())?);
+        if i == 0 {
+            pl.add(TestPeer::new(n_peers.into(), unreachable_addr.clone()))?;
+        }
+    }
+
+    wait_ready(&trns)?;
+
+    let d: Data = Data(77);
+    match trns[0].broadcast_with_policy(&mut pl, d.clone(), BroadcastPolicy::BestEffort) {
+        Ok(()) => panic!("expected an error for the unreachable peer"),
+        Err(e) => match e.downcast_ref::<Error>() {
+            Some(Error::MultipleErrors(v)) => assert_eq!(1, v.len()),
+            _ => panic!("unexpected error: {:?}", e),
+        },
+    }
+    for trn in trns.iter_mut() {
+        expect_next(trn, &d);
+    }
+
+    Ok(())
+}
+
+/*
+    Tests broadcast_reporting with two reachable peers and one unreachable peer: the outcome must
+    count 3 attempted, 2 succeeded and 1 failed sends, and an empty peer list 0 attempted.
+*/
+pub fn test_broadcast_reporting<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+    unreachable_addr: String,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let mut pl: TestPeerList<Id> = TestPeerList::new();
+    let mut trns: Vec<T> = Vec::with_capacity(2);
+    for (i, net_addr) in net_addrs.iter().take(2).enumerate() {
+        pl.add(TestPeer::new(i.into(), net_addr.clone()))?;
+        trns.push(T::new(net_addr.clone())?);
+    }
+    pl.add(TestPeer::new(2.into(), unreachable_addr))?;
+    wait_ready(&trns)?;
+
+    let d: Data = Data(78);
+    let outcome = trns[0].broadcast_reporting(&mut pl, d.clone())?;
+    assert_eq!(
+        BroadcastOutcome {
+            attempted: 3,
+            succeeded: 2,
+            failed: 1
+        },
+        outcome
+    );
+    for trn in trns.iter_mut() {
+        expect_next(trn, &d);
+    }
+
+    let mut empty: TestPeerList<Id> = TestPeerList::new();
+    assert_eq!(0, trns[0].broadcast_reporting(&mut empty, d)?.attempted);
+
+    Ok(())
+}
+
+// A dummy transport which loops messages sent through it back into its own stream, regardless of
+// the address. Used to exercise the Transport helpers without any networking.
+pub struct LoopbackDummy {
+    queue: VecDeque<Data>,
+    // Addresses sent to, in order.
+    pub sent_to: Vec<String>,
+    closed: bool,
+    terminated: bool,
+    // The waker of the pending reader, woken by send and close.
+    waker: Option<Waker>,
+}
+
+impl LoopbackDummy {
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Drop for LoopbackDummy {
+    fn drop(&mut self) {}
+}
+
+impl Stream for LoopbackDummy {
+    type Item = Data;
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Data>> {
+        if self.closed {
+            self.terminated = true;
+            return Poll::Ready(None);
+        }
+        match self.queue.pop_front() {
+            Some(d) => Poll::Ready(Some(d)),
+            None => {
+                self.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl FusedStream for LoopbackDummy {
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
+
+impl Transport<Id, Data, Error, TestPeerList<Id>> for LoopbackDummy {
+    fn new<C: TransportConfiguration>(_cfg: C) -> Result<Self> {
+        Ok(LoopbackDummy {
+            queue: VecDeque::new(),
+            sent_to: Vec::new(),
+            closed: false,
+            terminated: false,
+            waker: None,
+        })
+    }
+    fn is_ready(&self) -> bool {
+        true
+    }
+    fn send(&mut self, peer_address: String, data: Data) -> Result<()> {
+        self.sent_to.push(peer_address);
+        self.queue.push_back(data);
+        self.wake();
+        Ok(())
+    }
+    fn broadcast(&mut self, peers: &mut TestPeerList<Id>, data: Data) -> Result<()> {
+        for peer in peers.iter() {
+            self.send(peer.get_base_addr(), data.clone())?;
+        }
+        Ok(())
+    }
+    fn broadcast_n(&mut self, peers: &mut TestPeerList<Id>, _n: usize, data: Data) -> Result<()> {
+        self.broadcast(peers, data)
+    }
+    fn get_quit_tx(&self) -> Option<Sender<()>> {
+        None
+    }
+    fn close(&mut self) -> Result<()> {
+        self.closed = true;
+        self.wake();
+        Ok(())
+    }
+}
+
+// A dummy transport which is deliberately `!Unpin` (via PhantomPinned), so it can only be used as
+// a Transport through BoxedTransport. Messages sent through it are looped back into its own stream.
+pub struct PinnedDummy {
+    queue: VecDeque<Data>,
+    waker: Option<Waker>,
+    _pin: PhantomPinned,
+}
+
+impl PinnedDummy {
+    fn push(&mut self, data: Data) {
+        self.queue.push_back(data);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Stream for PinnedDummy {
+    type Item = Data;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Data>> {
+        // Safe: `queue` and `waker` are never treated as pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        match this.queue.pop_front() {
+            Some(d) => Poll::Ready(Some(d)),
+            None => {
+                this.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl FusedStream for PinnedDummy {
+    fn is_terminated(&self) -> bool {
+        false
+    }
+}
+
+impl PinnedTransport<Id, Data, Error, TestPeerList<Id>> for PinnedDummy {
+    fn new<C: TransportConfiguration>(_cfg: C) -> Result<Self> {
+        Ok(PinnedDummy {
+            queue: VecDeque::new(),
+            waker: None,
+            _pin: PhantomPinned,
+        })
+    }
+    fn is_ready(&self) -> bool {
+        true
+    }
+    fn send(self: Pin<&mut Self>, _peer_address: String, data: Data) -> Result<()> {
+        // Safe: `queue` and `waker` are never treated as pinned.
+        unsafe { self.get_unchecked_mut() }.push(data);
+        Ok(())
+    }
+    fn broadcast(self: Pin<&mut Self>, peers: &mut TestPeerList<Id>, data: Data) -> Result<()> {
+        // Safe: `queue` and `waker` are never treated as pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        for _ in peers.iter() {
+            this.push(data.clone());
+        }
+        Ok(())
+    }
+    fn broadcast_n(
+        self: Pin<&mut Self>,
+        peers: &mut TestPeerList<Id>,
+        _n: usize,
+        data: Data,
+    ) -> Result<()> {
+        self.broadcast(peers, data)
+    }
+    fn get_quit_tx(&self) -> Option<Sender<()>> {
+        None
+    }
+}
+
+/*
+    Tests the ThreadSafeTransport contract: a transport is moved into a spawned thread and sends
+    from there, and the message is received on the main thread.
+*/
+pub fn test_send_across_threads<T>(net_addrs: Vec<String>) -> Result<()>
+where
+    T: ThreadSafeTransport<Id, Data, Error, TestPeerList<Id>> + 'static,
+{
+    let (mut receiver, mut sender) = ready_pair::<T>(&net_addrs)?;
+
+    let d: Data = Data(0x55);
+    let expected = d.clone();
+    let receiver_addr = net_addrs[0].clone();
+    let handle = thread::spawn(move || sender.send(receiver_addr, d));
+    handle.join().expect("sender thread panicked")?;
+    expect_next(&mut receiver, &expected);
+
+    Ok(())
+}
+
+/*
+    Tests set_max_concurrent_handshakes under a handshake flood: with the limit set to 2, senders
+    on every other address connect simultaneously; the receiver must process them all without
+    crashing, receive every message and, if it reports it, never process more than 2 handshakes
+    at once.
+*/
+pub fn test_max_concurrent_handshakes<T>(net_addrs: Vec<String>) -> Result<()>
+where
+    T: ThreadSafeTransport<Id, Data, Error, TestPeerList<Id>> + 'static,
+{
+    assert!(net_addrs.len() >= 3, "three net addresses required");
+    let mut receiver = T::new(net_addrs[0].clone())?;
+    receiver.set_max_concurrent_handshakes(2);
+    wait_ready(vec![&receiver])?;
+
+    let start = Arc::new(Barrier::new(net_addrs.len() - 1));
+    let handles: Vec<thread::JoinHandle<Result<()>>> = net_addrs[1..]
+        .iter()
+        .enumerate()
+        .map(|(i, addr)| {
+            let addr = addr.clone();
+            let receiver_addr = net_addrs[0].clone();
+            let start = start.clone();
+            thread::spawn(move || {
+                let sender =
+                    T::new(addr).and_then(|sender| wait_ready(vec![&sender]).map(|_| sender));
+                // Wait for every sender even if this one failed, so none blocks forever.
+                start.wait();
+                sender?.send(receiver_addr, Data(i as u32))
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().expect("sender thread panicked")?;
+    }
+
+    let mut received: Vec<Data> = block_on(async {
+        let mut received = Vec::new();
+        for _ in 1..net_addrs.len() {
+            match receiver.next().await {
+                Some(t) => received.push(t),
+                None => panic!("unexpected None"),
+            }
+        }
+        received
+    });
+    received.sort();
+    let expected: Vec<Data> = (0..net_addrs.len() - 1).map(Data::from).collect();
+    assert_eq!(expected, received);
+    if let Some(peak) = receiver.peak_concurrent_handshakes() {
+        assert!(peak <= 2, "{} handshakes processed at once", peak);
+    }
+
+    Ok(())
+}
+
+/*
+    Tests sending by peer id: a resolver backed by a HashMap maps ids to addresses. A known id must
+    be delivered, an unknown one must fail with Error::PeerNotFound. Skipped for transports which
+    ignore set_resolver, i.e. which can't resolve the known id.
+*/
+pub fn test_send_by_id<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let mut receiver = T::new(net_addrs[0].clone())?;
+    let mut sender = T::new(net_addrs[1].clone())?;
+    let mut addrs: HashMap<Id, String> = HashMap::new();
+    for (i, net_addr) in net_addrs.iter().enumerate() {
+        addrs.insert(i.into(), net_addr.clone());
+    }
+    sender.set_resolver(Box::new(move |id: &Id| addrs.get(id).cloned()));
+    if sender.resolve(&Id(0)).is_none() {
+        return skip("no resolver");
+    }
+
+    wait_ready(vec![&receiver, &sender])?;
+
+    let d: Data = Data(0x42);
+    sender.send_by_id(&Id(0), d.clone())?;
+    expect_next(&mut receiver, &d);
+
+    let unknown: Id = net_addrs.len().into();
+    match sender.send_by_id(&unknown, d) {
+        Ok(()) => panic!("expected PeerNotFound"),
+        Err(e) => match e.downcast_ref::<Error>() {
+            Some(Error::PeerNotFound(_)) => {}
+            _ => panic!("unexpected error: {:?}", e),
+        },
+    }
+
+    Ok(())
+}
+
+/*
+    Tests TransportSender::broadcast_parallel, intended to be run with many (e.g. 50) addresses.
+    Every receiver must get both a serial and a parallel broadcast. The receivers are Transports,
+    so that the test can wait for them to be ready.
+*/
+pub fn test_broadcast_parallel<S, R>(net_addrs: Vec<String>) -> Result<()>
+where
+    S: TransportSender<Id, Data, Error, TestPeerList<Id>> + Clone + Send + 'static,
+    R: Transport<Id, Data, Error, TestPeerList<Id>>,
+{
+    let mut pl: TestPeerList<Id> = TestPeerList::new();
+    let mut receivers: Vec<R> = Vec::with_capacity(net_addrs.len());
+    for (i, net_addr) in net_addrs.iter().enumerate() {
+        pl.add(TestPeer::new(i.into(), net_addr.clone()))?;
+        receivers.push(R::new(net_addr.clone())?);
+    }
+    let mut sender = S::new()?;
+    wait_ready(&receivers)?;
+
+    let serial: Data = Data(1);
+    sender.broadcast(&mut pl, serial.clone())?;
+    let parallel: Data = Data(2);
+    block_on(sender.broadcast_parallel(&mut pl, parallel.clone()))?;
+
+    for receiver in receivers.iter_mut() {
+        for expected in [&serial, &parallel].iter() {
+            expect_next(receiver, *expected);
+        }
+    }
+
+    Ok(())
+}
+
+/*
+    Tests preconnect_all with two live peers and one dead peer (`dead_addr`, nothing listening):
+    the result must contain two Oks and one Err, and sends to the live peers must then be fast.
+    The dead peer isn't checked for connectionless transports, i.e. which aren't connected to the
+    live peers afterwards.
+*/
+pub fn test_preconnect_all<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+    dead_addr: String,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 3, "three net addresses required");
+    let mut trns: Vec<T> = Vec::with_capacity(3);
+    for net_addr in net_addrs.iter().take(3) {
+        trns.push(T::new(net_addr.clone())?);
+    }
+    let mut pl: TestPeerList<Id> = TestPeerList::new();
+    pl.add(TestPeer::new(1.into(), net_addrs[1].clone()))?;
+    pl.add(TestPeer::new(2.into(), net_addrs[2].clone()))?;
+    pl.add(TestPeer::new(3.into(), dead_addr))?;
+
+    wait_ready(&trns)?;
+
+    let results = block_on(trns[0].preconnect_all(&mut pl));
+    assert_eq!(3, results.len());
+    let connectionless = !trns[0].is_connected_to(&net_addrs[1]);
+    for (id, result) in results.iter() {
+        if *id != Id(3) {
+            assert!(result.is_ok(), "live peer {} failed to connect", id);
+        } else if !connectionless {
+            assert!(result.is_err(), "dead peer connected");
+        }
+    }
+    if connectionless {
+        skip("connectionless, dead peer unchecked")?;
+    }
+
+    let d: Data = Data(12);
+    for i in 1..3 {
+        let start = time::Instant::now();
+        trns[0].send(net_addrs[i].clone(), d.clone())?;
+        assert!(start.elapsed() < time::Duration::from_millis(500));
+        expect_next(&mut trns[i], &d);
+    }
+
+    Ok(())
+}
+
+/*
+    Tests the idle connection reaper: with a short idle timeout a quiet connection must be closed
+    (reported as a Disconnected event) and re-established by the next send. Skipped for
+    transports which don't report events, i.e. whose subscribe_events returns None.
+*/
+pub fn test_idle_timeout<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let mut receiver = T::new(net_addrs[0].clone())?;
+    let mut sender = T::new(net_addrs[1].clone())?;
+    let idle = time::Duration::from_millis(200);
+    sender.set_idle_timeout(Some(idle));
+    let events = match sender.subscribe_events() {
+        Some(events) => events,
+        None => return skip("no events"),
+    };
+
+    wait_ready(vec![&receiver, &sender])?;
+
+    for value in 0..2 {
+        let d: Data = Data(value);
+        sender.send(net_addrs[0].clone(), d.clone())?;
+        expect_next(&mut receiver, &d);
+        if value == 0 {
+            // Stay quiet until the connection is reaped.
+            loop {
+                match events.recv_timeout(idle * 10) {
+                    Ok(TransportEvent::Disconnected(addr, reason)) => {
+                        assert_eq!(net_addrs[0], addr);
+                        assert_eq!(DisconnectReason::Timeout, reason);
+                        break;
+                    }
+                    Ok(_) => {}
+                    Err(e) => panic!("idle connection was not closed: {:?}", e),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/*
+    Tests disconnect reasons: a connection reaped by the idle timeout must be reported with
+    DisconnectReason::Timeout, and one closed with disconnect with DisconnectReason::Graceful.
+    Skipped for transports which don't report events, i.e. whose subscribe_events returns None.
+*/
+pub fn test_disconnect_reason<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let mut receiver = T::new(net_addrs[0].clone())?;
+    let mut sender = T::new(net_addrs[1].clone())?;
+    let idle = time::Duration::from_millis(200);
+    let events = match sender.subscribe_events() {
+        Some(events) => events,
+        None => return skip("no events"),
+    };
+    wait_ready(vec![&receiver, &sender])?;
+
+    let expect_disconnect = |expected: DisconnectReason| loop {
+        match events.recv_timeout(idle * 10) {
+            Ok(TransportEvent::Disconnected(addr, reason)) => {
+                assert_eq!(net_addrs[0], addr);
+                assert_eq!(expected, reason);
+                break;
+            }
+            Ok(_) => {}
+            Err(e) => panic!("connection was not closed: {:?}", e),
+        }
+    };
+
+    for (value, timeout) in [(0, Some(idle)), (1, None)].iter() {
+        sender.set_idle_timeout(*timeout);
+        let d: Data = Data(*value);
+        sender.send(net_addrs[0].clone(), d.clone())?;
+        expect_next(&mut receiver, &d);
+        match timeout {
+            Some(_) => expect_disconnect(DisconnectReason::Timeout),
+            None => {
+                sender.disconnect(net_addrs[0].clone())?;
+                expect_disconnect(DisconnectReason::Graceful);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/*
+    Tests the stream termination contract: after `close` the stream yields None (rather than
+    panicking or hanging) and reports itself as terminated.
+*/
+pub fn test_close_terminates<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addr: String,
+) -> Result<()> {
+    let mut trn = T::new(net_addr)?;
+
+    wait_ready(vec![&trn])?;
+
+    assert!(!trn.is_terminated());
+    trn.close()?;
+    block_on(async {
+        assert_eq!(None, trn.next().await);
+    });
+    assert!(trn.is_terminated());
+
+    Ok(())
+}
+
+/*
+    Tests last_remote_addr: a message from a sender connecting out of an ephemeral port must be
+    reported with that observed address, not with the sender's configured bind address. Skipped
+    for transports which don't record it, i.e. whose last_remote_addr stays None.
+*/
+pub fn test_last_remote_addr<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let (mut receiver, mut sender) = ready_pair::<T>(&net_addrs)?;
+
+    assert_eq!(None, receiver.last_remote_addr());
+    let d: Data = Data(3);
+    sender.send(net_addrs[0].clone(), d.clone())?;
+    expect_next(&mut receiver, &d);
+
+    let observed: SocketAddr = match receiver.last_remote_addr() {
+        Some(observed) => observed
+            .parse()
+            .map_err(|_| Error::AddrParse(String::from("observed address")))?,
+        None => return skip("no observed address"),
+    };
+    let configured: SocketAddr = net_addrs[1]
+        .parse()
+        .map_err(|_| Error::AddrParse(net_addrs[1].clone()))?;
+    assert_ne!(configured.port(), observed.port());
+
+    Ok(())
+}
+
+/*
+    Tests broadcasting to an empty PeerList: it must return Ok(()) and send nothing.
+*/
+pub fn test_broadcast_empty<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addr: String,
+) -> Result<()> {
+    let mut trn = T::new(net_addr)?;
+    let mut pl: TestPeerList<Id> = TestPeerList::new();
+
+    wait_ready(vec![&trn])?;
+
+    trn.broadcast(&mut pl, Data(55))?;
+    thread::sleep(time::Duration::from_millis(500));
+    assert_eq!(None, try_next(&mut trn));
+
+    Ok(())
+}
+
+/*
+    Tests logical channels: messages sent on channels 1 and 2 must each only be seen by the
+    stream of their own channel. Skipped for transports without channels, i.e. whose
+    send_on_channel returns Error::Unsupported for them.
+*/
+pub fn test_channels<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let (mut receiver, mut sender) = ready_pair::<T>(&net_addrs)?;
+
+    if let Err(e) = sender.send_on_channel(net_addrs[0].clone(), 1, Data(10)) {
+        return match e.downcast_ref::<Error>() {
+            Some(Error::Unsupported(_)) => skip("channels unsupported"),
+            _ => Err(e),
+        };
+    }
+    sender.send_on_channel(net_addrs[0].clone(), 2, Data(20))?;
+    sender.send_on_channel(net_addrs[0].clone(), 1, Data(11))?;
+    sender.send_on_channel(net_addrs[0].clone(), 2, Data(21))?;
+    for (channel, expected) in [(1, [Data(10), Data(11)]), (2, [Data(20), Data(21)])].iter() {
+        let mut s = receiver.stream_for_channel(*channel);
+        for d in expected.iter() {
+            expect_next(&mut s, d);
+        }
+    }
+
+    Ok(())
+}
+
+/*
+    Tests flush-on-drop: with set_flush_on_drop(true), a broadcast immediately followed by dropping
+    the sender must still be delivered to every receiver.
+*/
+pub fn test_flush_on_drop<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let mut pl: TestPeerList<Id> = TestPeerList::new();
+    let mut receivers: Vec<T> = Vec::with_capacity(net_addrs.len() - 1);
+    for (i, net_addr) in net_addrs.iter().enumerate().skip(1) {
+        pl.add(TestPeer::new(i.into(), net_addr.clone()))?;
+        receivers.push(T::new(net_addr.clone())?);
+    }
+    let mut sender = T::new(net_addrs[0].clone())?;
+    sender.set_flush_on_drop(true);
+
+    wait_ready(receivers.iter().chain(vec![&sender]))?;
+
+    let d: Data = Data(99);
+    sender.broadcast(&mut pl, d.clone())?;
+    drop(sender);
+    for receiver in receivers.iter_mut() {
+        expect_next(receiver, &d);
+    }
+
+    Ok(())
+}
+
+/*
+    Tests tracked sends: three messages sent concurrently from three threads sharing one
+    transport must get three distinct ids, and the receiver must observe all of them. Skipped for
+    transports whose send_tracked returns Error::Unsupported.
+*/
+pub fn test_send_tracked<T>(net_addrs: Vec<String>) -> Result<()>
+where
+    T: ThreadSafeTransport<Id, Data, Error, TestPeerList<Id>> + 'static,
+{
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let mut receiver = T::new(net_addrs[0].clone())?;
+    let sender = Arc::new(Mutex::new(T::new(net_addrs[1].clone())?));
+
+    wait_ready(vec![&receiver, &*sender.lock().map_err(Error::from)?])?;
+
+    let handles: Vec<thread::JoinHandle<Result<u64>>> = (0..3)
+        .map(|i| {
+            let sender = sender.clone();
+            let addr = net_addrs[0].clone();
+            thread::spawn(move || {
+                let mut guard = sender.lock().map_err(Error::from)?;
+                guard.send_tracked(addr, Data(i))
+            })
+        })
+        .collect();
+    let mut ids: HashSet<u64> = HashSet::new();
+    for handle in handles {
+        match handle.join().expect("sender thread panicked") {
+            Ok(id) => ids.insert(id),
+            Err(e) => {
+                return match e.downcast_ref::<Error>() {
+                    Some(Error::Unsupported(_)) => skip("send_tracked unsupported"),
+                    _ => Err(e),
+                };
+            }
+        };
+    }
+    assert_eq!(3, ids.len());
+
+    let mut observed: HashSet<u64> = HashSet::new();
+    let mut s = receiver.tracked_stream();
+    block_on(async {
+        for _ in 0..3 {
+            match s.next().await {
+                Some((id, _)) => observed.insert(id),
+                None => panic!("unexpected None"),
+            };
+        }
+    });
+    assert_eq!(ids, observed);
+
+    Ok(())
+}
+
+/*
+    Tests the readiness gate: sending from a just-constructed transport must either succeed and be
+    delivered, or fail with Error::NotReady; it must never be silently dropped.
+*/
+pub fn test_not_ready<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let mut receiver = T::new(net_addrs[0].clone())?;
+    let mut sender = T::new(net_addrs[1].clone())?;
+
+    let d: Data = Data(1);
+    let sent_early = match sender.send(net_addrs[0].clone(), d.clone()) {
+        Ok(()) => true,
+        Err(e) => match e.downcast_ref::<Error>() {
+            Some(Error::NotReady) => false,
+            _ => panic!("unexpected error: {:?}", e),
+        },
+    };
+    wait_ready(vec![&receiver, &sender])?;
+    if !sent_early {
+        sender.send(net_addrs[0].clone(), d.clone())?;
+    }
+    expect_next(&mut receiver, &d);
+
+    Ok(())
+}
+
+/*
+    Tests new_on_executor: the transport must hand its background tasks to the injected spawner
+    (counted here, then run on plain threads) and still deliver messages.
+*/
+pub fn test_new_on_executor<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let spawned = Arc::new(AtomicUsize::new(0));
+    let counter = spawned.clone();
+    let spawner = move |task: Box<dyn FnOnce() + Send>| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        thread::spawn(task);
+    };
+    let mut receiver = T::new_on_executor(net_addrs[0].clone(), spawner)?;
+    let mut sender = T::new(net_addrs[1].clone())?;
+    wait_ready(vec![&receiver, &sender])?;
+    assert!(spawned.load(Ordering::SeqCst) > 0, "spawner was not used");
+
+    let d: Data = Data(8);
+    sender.send(net_addrs[0].clone(), d.clone())?;
+    expect_next(&mut receiver, &d);
+
+    Ok(())
+}
+
+/*
+    Tests half-close: after shutdown_send to a peer, sending to it must fail, while messages from
+    that peer must still be received. Skipped for transports without half-close, i.e. whose
+    shutdown_send returns Error::Incomplete.
+*/
+pub fn test_shutdown_send<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let (mut local, mut remote) = ready_pair::<T>(&net_addrs)?;
+
+    let d: Data = Data(5);
+    local.send(net_addrs[1].clone(), d.clone())?;
+    expect_next(&mut remote, &d);
+    if let Err(e) = local.shutdown_send(net_addrs[1].clone()) {
+        return match e.downcast_ref::<Error>() {
+            Some(Error::Incomplete) => skip("shutdown_send unsupported"),
+            _ => Err(e),
+        };
+    }
+    assert!(local.send(net_addrs[1].clone(), d.clone()).is_err());
+
+    let u: Data = Data(6);
+    remote.send(net_addrs[0].clone(), u.clone())?;
+    expect_next(&mut local, &u);
+
+    Ok(())
+}
+
+/*
+    Tests that a broadcast of a value which fails to serialize returns Error::Bincode up front,
+    before any peer connection is opened (no Connected event is reported) or anything is sent, both
+    with broadcast and broadcast_reporting.
+*/
+pub fn test_broadcast_unserializable<T: Transport<Id, FailingData, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let mut pl: TestPeerList<Id> = TestPeerList::new();
+    let mut trns: Vec<T> = Vec::with_capacity(net_addrs.len());
+    for (i, net_addr) in net_addrs.iter().enumerate() {
+        pl.add(TestPeer::new(i.into(), net_addr.clone()))?;
+        trns.push(T::new(net_addr.clone())?);
+    }
+    wait_ready(&trns)?;
+    let events = trns[0].subscribe_events();
+
+    match trns[0].broadcast(&mut pl, FailingData) {
+        Ok(()) => panic!("expected a Bincode error"),
+        Err(e) => match e.downcast_ref::<Error>() {
+            Some(Error::Bincode(_)) => {}
+            _ => panic!("unexpected error: {:?}", e),
+        },
+    }
+    match trns[0].broadcast_reporting(&mut pl, FailingData) {
+        Ok(outcome) => panic!("expected a Bincode error, got {:?}", outcome),
+        Err(e) => match e.downcast_ref::<Error>() {
+            Some(Error::Bincode(_)) => {}
+            _ => panic!("unexpected error: {:?}", e),
+        },
+    }
+    thread::sleep(time::Duration::from_millis(500));
+    if let Some(events) = events {
+        assert!(events.try_recv().is_err(), "a connection was opened");
+    }
+    for trn in trns.iter_mut() {
+        assert_eq!(None, try_next(trn));
+    }
+
+    Ok(())
+}
+
+/*
+    Tests the inbound rate limit: a receiver limited to `bytes_per_sec` is flooded, and the payload
+    bytes it yields within a short window must stay under the limit (allowing one second of
+    burst).
+*/
+pub fn test_inbound_rate_limit<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+    bytes_per_sec: u64,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let mut receiver = T::new(net_addrs[0].clone())?;
+    let mut sender = T::new(net_addrs[1].clone())?;
+    receiver.set_inbound_rate_limit(bytes_per_sec);
+    wait_ready(vec![&receiver, &sender])?;
+
+    let message_size = serialized_size(&Data(0))?;
+    let n_messages = 4 * bytes_per_sec / message_size + 1;
+    for i in 0..n_messages {
+        sender.send(net_addrs[0].clone(), Data(i as u32))?;
+    }
+
+    let window = time::Duration::from_secs(2);
+    let start = time::Instant::now();
+    let mut received: u64 = 0;
+    block_on(async {
+        while start.elapsed() < window && received < n_messages * message_size {
+            match receiver.next().await {
+                Some(_) => received += message_size,
+                None => panic!("unexpected None"),
+            }
+        }
+    });
+    let elapsed = start.elapsed().as_secs_f64();
+    let bound = bytes_per_sec as f64 * (elapsed + 1.0);
+    assert!(
+        received as f64 <= bound,
+        "received {} bytes in {}s, limit {} bytes/s",
+        received,
+        elapsed,
+        bytes_per_sec
+    );
+
+    Ok(())
+}
+
+/*
+    Tests the shutdown handle: a task awaiting next() on another thread must complete with None
+    once shutdown is triggered from the main thread.
+*/
+pub fn test_shutdown_handle<R>(net_addr: String) -> Result<()>
+where
+    R: TransportReceiver<Id, Data, Error, TestPeerList<Id>> + Send + 'static,
+{
+    let mut receiver = R::new(net_addr)?;
+    let handle = receiver
+        .shutdown_handle()
+        .expect("receiver does not support shutdown handles");
+
+    let task = thread::spawn(move || block_on(async { receiver.next().await }));
+    thread::sleep(time::Duration::from_millis(200));
+    handle.trigger();
+    assert_eq!(None, task.join().expect("receiving thread panicked"));
+
+    Ok(())
+}
+
+/*
+    Tests reset_metrics: after some traffic the global and per-peer counters are non-zero, and
+    after reset_metrics they are all back to zero while the connection keeps working. Skipped for
+    transports which don't count traffic, i.e. whose metrics stay zero.
+*/
+pub fn test_reset_metrics<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let (mut receiver, mut sender) = ready_pair::<T>(&net_addrs)?;
+
+    for i in 0..3 {
+        let d: Data = Data(i);
+        sender.send(net_addrs[0].clone(), d.clone())?;
+        expect_next(&mut receiver, &d);
+    }
+    let sent = sender.metrics();
+    if sent == Metrics::default() {
+        return skip("no metrics");
+    }
+    assert_eq!(3, sent.messages_sent);
+    assert!(sent.bytes_sent > 0);
+    assert_eq!(3, receiver.metrics().messages_received);
+    assert_ne!(Some(Metrics::default()), sender.peer_metrics(&net_addrs[0]));
+
+    sender.reset_metrics();
+    receiver.reset_metrics();
+    assert_eq!(Metrics::default(), sender.metrics());
+    assert_eq!(Metrics::default(), receiver.metrics());
+    if let Some(m) = sender.peer_metrics(&net_addrs[0]) {
+        assert_eq!(Metrics::default(), m);
+    }
+
+    let d: Data = Data(3);
+    sender.send(net_addrs[0].clone(), d.clone())?;
+    expect_next(&mut receiver, &d);
+
+    Ok(())
+}
+
+/*
+    Tests stats: after some traffic the snapshot must report plausible non-zero values. Skipped
+    for transports which don't count traffic, i.e. whose metrics stay zero.
+*/
+pub fn test_stats<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let (mut receiver, mut sender) = ready_pair::<T>(&net_addrs)?;
+
+    for i in 0..3 {
+        let d: Data = Data(i);
+        sender.send(net_addrs[0].clone(), d.clone())?;
+        expect_next(&mut receiver, &d);
+    }
+
+    let stats = sender.stats();
+    if stats.total == Metrics::default() {
+        return skip("no stats");
+    }
+    assert!(stats.uptime > time::Duration::from_secs(0));
+    assert!(stats.peer_count >= 1);
+    assert_eq!(3, stats.total.messages_sent);
+    assert!(stats.total.bytes_sent > 0);
+    let peer = stats
+        .per_peer
+        .iter()
+        .find(|c| c.address == net_addrs[0])
+        .expect("no connection info for the receiver");
+    assert_eq!(3, peer.metrics.messages_sent);
+
+    Ok(())
+}
+
+// Returns a "127.0.0.1:port" address with a currently free ephemeral port, found by briefly
+// binding a listener to port 0.
+pub fn free_local_addr() -> Result<String> {
+    let listener = TcpListener::bind("127.0.0.1:0").map_err(Error::from)?;
+    let addr = listener.local_addr().map_err(Error::from)?;
+    Ok(addr.to_string())
+}
+
+// Returns `n` unused Unix domain socket paths in the temporary directory, so `common_test` and
+// the other generic tests can be run over Unix sockets.
+pub fn free_unix_addrs(n: usize) -> Vec<String> {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    (0..n)
+        .map(|_| {
+            let name = format!(
+                "libtransport-{}-{}.sock",
+                std::process::id(),
+                NEXT.fetch_add(1, Ordering::SeqCst)
+            );
+            let path = std::env::temp_dir().join(name);
+            let _ = std::fs::remove_file(&path);
+            path.to_string_lossy().into_owned()
+        })
+        .collect()
+}
+
+// Binds two transports on ephemeral local ports and waits until both are ready. Returns both
+// transports and their addresses, ready to send to each other.
+pub fn loopback_pair<T>() -> Result<(T, T, String, String)>
+where
+    T: Transport<Id, Data, Error, TestPeerList<Id>>,
+{
+    let addr_a = free_local_addr()?;
+    let addr_b = free_local_addr()?;
+    let a = T::new(addr_a.clone())?;
+    let b = T::new(addr_b.clone())?;
+    wait_ready(vec![&a, &b])?;
+    Ok((a, b, addr_a, addr_b))
+}
+
+/*
+    Tests loopback_pair by round-tripping one Data value in each direction.
+*/
+pub fn loopback_pair_test<T: Transport<Id, Data, Error, TestPeerList<Id>>>() -> Result<()> {
+    let (mut a, mut b, addr_a, addr_b) = loopback_pair::<T>()?;
+
+    let d: Data = Data(1);
+    a.send(addr_b, d.clone())?;
+    expect_next(&mut b, &d);
+    let u: Data = Data(2);
+    b.send(addr_a, u.clone())?;
+    expect_next(&mut a, &u);
+
+    Ok(())
+}
+
+/*
+    Tests the raw bytes escape hatch by round-tripping vec![1, 2, 3, 4] between two transports.
+*/
+pub fn test_send_raw<T: Transport<Id, Data, Error, TestPeerList<Id>>>() -> Result<()> {
+    let (mut a, mut b, _, addr_b) = loopback_pair::<T>()?;
+
+    let raw: Vec<u8> = vec![1, 2, 3, 4];
+    a.send_raw(addr_b, raw.clone())?;
+    let mut s = b.raw_stream()?;
+    expect_next(&mut s, &raw);
+
+    Ok(())
+}
+
+// How long test_try_broadcast broadcasts to fill the buffers of a stalled peer.
+const TRY_BROADCAST_TIMEOUT: time::Duration = time::Duration::from_secs(10);
+
+/*
+    Tests try_broadcast with one stalled peer: a plain listener which accepts connections but never
+    reads. Broadcasting repeatedly must report the stalled peer as skipped (its send buffer is
+    full) without blocking within TRY_BROADCAST_TIMEOUT, while the live peer is never skipped.
+*/
+pub fn test_try_broadcast<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let mut sender = T::new(net_addrs[0].clone())?;
+    let mut live = T::new(net_addrs[1].clone())?;
+    let stalled = TcpListener::bind("127.0.0.1:0").map_err(Error::from)?;
+    let stalled_addr = stalled.local_addr().map_err(Error::from)?.to_string();
+    let mut pl: TestPeerList<Id> = TestPeerList::new();
+    pl.add(TestPeer::new(1.into(), net_addrs[1].clone()))?;
+    pl.add(TestPeer::new(2.into(), stalled_addr))?;
+    wait_ready(vec![&sender, &live])?;
+
+    // Broadcast until the stalled peer's buffers are full, giving up after TRY_BROADCAST_TIMEOUT.
+    let deadline = time::Instant::now() + TRY_BROADCAST_TIMEOUT;
+    let mut sent: u32 = 0;
+    let skipped = loop {
+        let skipped = sender.try_broadcast(&mut pl, Data(sent))?;
+        sent += 1;
+        if !skipped.is_empty() {
+            break skipped;
+        }
+        assert!(
+            time::Instant::now() < deadline,
+            "stalled peer not skipped after {} broadcasts",
+            sent
+        );
+    };
+    assert_eq!(vec![Id(2)], skipped);
+    expect_next(&mut live, &Data(0));
+
+    Ok(())
+}
+
+/*
+    Tests the pluggable logger: with a CapturingLogger installed, common_test must log its progress
+    messages to it.
+*/
+pub fn test_logging<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let logger = Arc::new(CapturingLogger::default());
+    set_test_logger(Some(logger.clone()));
+    let result = common_test::<T>(net_addrs);
+    set_test_logger(None);
+    result?;
+
+    let lines = logger.lines();
+    assert_eq!(Some("Broadcast test"), lines.first().map(|l| l.as_str()));
+    assert!(lines.iter().any(|l| l == "receiving from peer 0"));
+    assert_eq!(Some("Unicast test"), lines.last().map(|l| l.as_str()));
+
+    Ok(())
+}
+
+/*
+    Tests reconnect: after connecting and then forcing a reconnect, a message must still be
+    delivered over the new connection.
+*/
+pub fn test_reconnect<T: Transport<Id, Data, Error, TestPeerList<Id>>>() -> Result<()> {
+    let (mut a, mut b, _, addr_b) = loopback_pair::<T>()?;
+
+    a.connect(addr_b.clone())?;
+    a.reconnect(addr_b.clone())?;
+    let d: Data = Data(7);
+    a.send(addr_b, d.clone())?;
+    expect_next(&mut b, &d);
+
+    Ok(())
+}
+
+/*
+    Tests close_all: after connecting to two peers and closing all connections no peer may remain
+    connected, while sending again must reconnect and deliver.
+*/
+pub fn test_close_all<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 3, "three net addresses required");
+    let mut trns: Vec<T> = Vec::with_capacity(net_addrs.len());
+    for addr in net_addrs.iter().take(3) {
+        trns.push(T::new(addr.clone())?);
+    }
+    wait_ready(&trns)?;
+
+    let (sender, receivers) = trns.split_first_mut().unwrap();
+    sender.connect(net_addrs[1].clone())?;
+    sender.connect(net_addrs[2].clone())?;
+    sender.close_all()?;
+    assert_eq!(0, sender.peer_count());
+
+    let d: Data = Data(13);
+    for (addr, receiver) in net_addrs[1..].iter().zip(receivers.iter_mut()) {
+        sender.send(addr.clone(), d.clone())?;
+        expect_next(receiver, &d);
+    }
+
+    Ok(())
+}
+
+/*
+    Tests is_connected_to: false before any send, true after a successful send and false again
+    after disconnect. Skipped for connectionless transports, i.e. which aren't connected after the
+    send.
+*/
+pub fn test_is_connected_to<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let (mut receiver, mut sender) = ready_pair::<T>(&net_addrs)?;
+    assert!(!sender.is_connected_to(&net_addrs[0]));
+
+    let d: Data = Data(27);
+    sender.send(net_addrs[0].clone(), d.clone())?;
+    expect_next(&mut receiver, &d);
+    if !sender.is_connected_to(&net_addrs[0]) {
+        return skip("connectionless");
+    }
+
+    sender.disconnect(net_addrs[0].clone())?;
+    assert!(!sender.is_connected_to(&net_addrs[0]));
+
+    Ok(())
+}
+
+/*
+    Tests sending to an address nobody listens on: the send must fail instead of silently dropping
+    the message.
+*/
+pub fn test_send_unknown_peer<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addr: String,
+    unknown_addr: String,
+) -> Result<()> {
+    let mut trn = T::new(net_addr)?;
+    wait_ready(vec![&trn])?;
+
+    assert!(
+        trn.send(unknown_addr, Data(3)).is_err(),
+        "send to an unknown peer succeeded"
+    );
+
+    Ok(())
+}
+
+/*
+    Tests message ordering: many messages sent from one transport to another must be received in
+    the order they were sent.
+*/
+pub fn test_ordering<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let (mut receiver, mut sender) = ready_pair::<T>(&net_addrs)?;
+
+    for i in 0..100 {
+        sender.send(net_addrs[0].clone(), Data(i))?;
+    }
+    for i in 0..100 {
+        expect_next(&mut receiver, &Data(i));
+    }
+
+    Ok(())
+}
+
+/*
+    Tests that dropping a transport releases its address: a new transport must be able to bind
+    the same address right away and receive on it.
+*/
+pub fn test_drop_rebinds<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let first = T::new(net_addrs[0].clone())?;
+    wait_ready(vec![&first])?;
+    drop(first);
+
+    let mut receiver = T::new(net_addrs[0].clone())?;
+    let mut sender = T::new(net_addrs[1].clone())?;
+    wait_ready(vec![&receiver, &sender])?;
+
+    let d: Data = Data(17);
+    sender.send(net_addrs[0].clone(), d.clone())?;
+    expect_next(&mut receiver, &d);
+
+    Ok(())
+}
+
+/*
+    Tests send_peer: sending to a &TestPeer must deliver to the peer's base address.
+*/
+pub fn test_send_peer<T: Transport<Id, Data, Error, TestPeerList<Id>>>() -> Result<()> {
+    let (mut a, mut b, _, addr_b) = loopback_pair::<T>()?;
+    let peer = TestPeer::new(1.into(), addr_b);
+
+    let d: Data = Data(9);
+    a.send_peer(&peer, d.clone())?;
+    expect_next(&mut b, &d);
+
+    Ok(())
+}
+
+/*
+    Tests broadcast_recording: after a broadcast every peer reached must have the address the
+    transport reports it reached it at (see negotiated_addr) as its first net_addr, while the last
+    peer, which has no transport, must have no net_addr recorded and make the broadcast fail.
+*/
+pub fn test_broadcast_recording<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let mut pl: TestPeerList<Id> = TestPeerList::new();
+    for (i, net_addr) in net_addrs.iter().enumerate() {
+        pl.add(TestPeer::new(i.into(), net_addr.clone()))?;
+    }
+    let unreachable = net_addrs.len() - 1;
+    let mut trns: Vec<T> = Vec::with_capacity(unreachable);
+    for net_addr in net_addrs[..unreachable].iter() {
+        trns.push(T::new(net_addr.clone())?);
+    }
+    wait_ready(&trns)?;
+    assert_eq!(None, trns[0].negotiated_addr(&net_addrs[1]));
+
+    let d: Data = Data(4);
+    assert!(trns[0].broadcast_recording(&mut pl, 0, d.clone()).is_err());
+    let negotiated: Vec<Option<String>> = net_addrs
+        .iter()
+        .map(|addr| trns[0].negotiated_addr(addr))
+        .collect();
+    if negotiated[0].is_none() {
+        return skip("negotiated address not recorded");
+    }
+    for (i, trn) in trns.iter_mut().enumerate() {
+        assert_eq!(negotiated[i], Some(pl[i].get_net_addr(0)));
+        expect_next(trn, &d);
+    }
+    assert_eq!(None, negotiated[unreachable]);
+    assert!(pl[unreachable].net_addr.is_empty());
+
+    Ok(())
+}
+
+/*
+    Tests TransportSender::broadcast_abortable: a broadcast polled once makes exactly one send,
+    and once aborted resolves to Error::Aborted without sending to anyone else.
+*/
+pub fn test_broadcast_abortable<S, R>(net_addrs: Vec<String>) -> Result<()>
+where
+    S: TransportSender<Id, Data, Error, TestPeerList<Id>> + Send,
+    R: TransportReceiver<Id, Data, Error, TestPeerList<Id>>,
+{
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let mut pl: TestPeerList<Id> = TestPeerList::new();
+    let mut receivers: Vec<R> = Vec::with_capacity(net_addrs.len());
+    for (i, net_addr) in net_addrs.iter().enumerate() {
+        pl.add(TestPeer::new(i.into(), net_addr.clone()))?;
+        receivers.push(R::new(net_addr.clone())?);
+    }
+    let mut sender = S::new()?;
+
+    let (mut broadcast, handle) = sender.broadcast_abortable(&mut pl, Data(7));
+    let mut cx = Context::from_waker(noop_waker_ref());
+    assert!(broadcast.as_mut().poll(&mut cx).is_pending());
+    handle.abort();
+    match block_on(broadcast) {
+        Err(e) => match e.downcast_ref::<Error>() {
+            Some(Error::Aborted) => {}
+            _ => panic!("unexpected error: {}", e),
+        },
+        Ok(()) => panic!("aborted broadcast completed"),
+    }
+
+    // Give any sends that slipped through time to arrive.
+    thread::sleep(time::Duration::from_millis(500));
+    let mut reached = 0;
+    for receiver in receivers.iter_mut() {
+        if try_next(receiver).is_some() {
+            reached += 1;
+        }
+    }
+    assert_eq!(
+        1, reached,
+        "aborted broadcast reached {} receivers",
+        reached
+    );
+
+    Ok(())
+}
+
+/*
+    Tests on_peers_changed: a peer added to the PeerList at runtime must be reached by the next
+    broadcast once on_peers_changed has been called.
+*/
+pub fn test_on_peers_changed<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 3, "three net addresses required");
+    let mut trns: Vec<T> = Vec::with_capacity(3);
+    for net_addr in net_addrs.iter().take(3) {
+        trns.push(T::new(net_addr.clone())?);
+    }
+    let mut pl: TestPeerList<Id> = TestPeerList::new();
+    pl.add(TestPeer::new(1.into(), net_addrs[1].clone()))?;
+
+    wait_ready(&trns)?;
+    trns[0].on_peers_changed(&pl)?;
+
+    pl.add(TestPeer::new(2.into(), net_addrs[2].clone()))?;
+    trns[0].on_peers_changed(&pl)?;
+
+    let d: Data = Data(14);
+    trns[0].broadcast(&mut pl, d.clone())?;
+    for trn in trns.iter_mut().skip(1) {
+        expect_next(trn, &d);
+    }
+
+    Ok(())
+}
+
+/*
+    Tests broadcast_safe, which only compiles for BroadcastSafe data such as the small Data type;
+    every peer must receive the broadcast.
+*/
+pub fn test_broadcast_safe<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let mut pl: TestPeerList<Id> = TestPeerList::new();
+    let mut trns: Vec<T> = Vec::with_capacity(net_addrs.len());
+    for (i, net_addr) in net_addrs.iter().enumerate() {
+        pl.add(TestPeer::new(i.into(), net_addr.clone()))?;
+        trns.push(T::new(net_addr.clone())?);
+    }
+    wait_ready(&trns)?;
+
+    let d: Data = Data(15);
+    trns[0].broadcast_safe(&mut pl, d.clone())?;
+    for trn in trns.iter_mut() {
+        expect_next(trn, &d);
+    }
+
+    Ok(())
+}
+
+/*
+    Tests merge_receivers: with two receivers bound to different addresses merged into one stream,
+    the merged stream must yield the messages sent to either of them. The receivers are
+    Transports, so that the test can wait for them to be ready.
+*/
+pub fn test_merge_receivers<S, R>(net_addrs: Vec<String>) -> Result<()>
+where
+    S: TransportSender<Id, Data, Error, TestPeerList<Id>>,
+    R: Transport<Id, Data, Error, TestPeerList<Id>>,
+{
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let receivers: Vec<R> = vec![R::new(net_addrs[0].clone())?, R::new(net_addrs[1].clone())?];
+    wait_ready(&receivers)?;
+    let mut merged = merge_receivers(receivers);
+    let mut sender = S::new()?;
+
+    sender.send(net_addrs[0].clone(), Data(16))?;
+    sender.send(net_addrs[1].clone(), Data(17))?;
+
+    let mut received: HashSet<Data> = HashSet::new();
+    block_on(async {
+        for _ in 0..2 {
+            match merged.next().await {
+                Some(t) => {
+                    received.insert(t);
+                }
+                None => panic!("unexpected None"),
+            }
+        }
+    });
+    let expected: HashSet<Data> = vec![Data(16), Data(17)].into_iter().collect();
+    assert_eq!(expected, received);
+
+    Ok(())
+}
+
+/*
+    Tests late binding: a transport created with new_unbound must fail to send with
+    Error::NotReady, and send normally once bound. Skipped for transports whose new_unbound
+    returns Error::Unsupported.
+*/
+pub fn test_bind<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let mut receiver = T::new(net_addrs[0].clone())?;
+    let mut sender = match T::new_unbound() {
+        Ok(sender) => sender,
+        Err(e) => {
+            return match e.downcast_ref::<Error>() {
+                Some(Error::Unsupported(_)) => skip("new_unbound unsupported"),
+                _ => Err(e),
+            };
+        }
+    };
+    assert!(!sender.is_ready());
+
+    let d: Data = Data(18);
+    match sender.send(net_addrs[0].clone(), d.clone()) {
+        Err(e) => match e.downcast_ref::<Error>() {
+            Some(Error::NotReady) => {}
+            _ => panic!("unexpected error: {:?}", e),
+        },
+        Ok(()) => panic!("unbound transport sent"),
+    }
+
+    sender.bind(net_addrs[1].clone())?;
+    wait_ready(vec![&receiver, &sender])?;
+    sender.send(net_addrs[0].clone(), d.clone())?;
+    expect_next(&mut receiver, &d);
+
+    Ok(())
+}
+
+/*
+    Tests broadcast_filtered with a predicate selecting the peers with an even Id: only those must
+    receive the message.
+*/
+pub fn test_broadcast_filtered<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let mut pl: TestPeerList<Id> = TestPeerList::new();
+    let mut trns: Vec<T> = Vec::with_capacity(net_addrs.len());
+    for (i, net_addr) in net_addrs.iter().enumerate() {
+        pl.add(TestPeer::new(i.into(), net_addr.clone()))?;
+        trns.push(T::new(net_addr.clone())?);
+    }
+    wait_ready(&trns)?;
+
+    let d: Data = Data(19);
+    trns[0].broadcast_filtered(&mut pl, d.clone(), |peer| peer.get_id().0 % 2 == 0)?;
+    for (i, trn) in trns.iter_mut().enumerate() {
+        if i % 2 == 0 {
+            expect_next(trn, &d);
+        }
+    }
+    // Give stray messages time to arrive before checking the odd peers got nothing.
+    thread::sleep(time::Duration::from_millis(500));
+    for (i, trn) in trns.iter_mut().enumerate() {
+        if i % 2 == 1 {
+            assert_eq!(
+                None,
+                try_next(trn),
+                "peer {} received a filtered message",
+                i
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// A Transport whose peers never read: every message sent stays queued, as recorded by its
+// DropAudit.
+#[cfg(feature = "debug")]
+pub struct StalledDummy {
+    audit: DropAudit,
+}
+
+#[cfg(feature = "debug")]
+impl Drop for StalledDummy {
+    fn drop(&mut self) {}
+}
+
+#[cfg(feature = "debug")]
+impl Stream for StalledDummy {
+    type Item = Data;
+    fn poll_next(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Data>> {
+        Poll::Pending
+    }
+}
+
+#[cfg(feature = "debug")]
+impl FusedStream for StalledDummy {
+    fn is_terminated(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(feature = "debug")]
+impl Transport<Id, Data, Error, TestPeerList<Id>> for StalledDummy {
+    fn new<C: TransportConfiguration>(_cfg: C) -> Result<Self> {
+        Ok(StalledDummy {
+            audit: DropAudit::new(),
+        })
+    }
+    fn is_ready(&self) -> bool {
+        true
+    }
+    fn send(&mut self, peer_address: String, data: Data) -> Result<()> {
+        let bytes = bincode_encode(&data)?;
+        self.audit.queued(&peer_address, bytes.len() as u64);
+        Ok(())
+    }
+    fn broadcast(&mut self, peers: &mut TestPeerList<Id>, data: Data) -> Result<()> {
+        for peer in peers.iter() {
+            self.send(peer.get_base_addr(), data.clone())?;
+        }
+        Ok(())
+    }
+    fn broadcast_n(&mut self, peers: &mut TestPeerList<Id>, _n: usize, data: Data) -> Result<()> {
+        self.broadcast(peers, data)
+    }
+    fn get_quit_tx(&self) -> Option<Sender<()>> {
+        None
+    }
+}
+
+/*
+    Tests send_ref and broadcast_ref: the message is passed by reference, so the original value
+    is still usable afterwards and compared against what every peer received.
+*/
+pub fn test_broadcast_ref<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let mut pl: TestPeerList<Id> = TestPeerList::new();
+    let mut trns: Vec<T> = Vec::with_capacity(net_addrs.len());
+    for (i, net_addr) in net_addrs.iter().enumerate() {
+        pl.add(TestPeer::new(i.into(), net_addr.clone()))?;
+        trns.push(T::new(net_addr.clone())?);
+    }
+    wait_ready(&trns)?;
+
+    let d: Data = Data(20);
+    trns[0].broadcast_ref(&mut pl, &d)?;
+    trns[0].send_ref(net_addrs[0].clone(), &d)?;
+    let expected = d;
+    for (i, trn) in trns.iter_mut().enumerate() {
+        let count = if i == 0 { 2 } else { 1 };
+        for _ in 0..count {
+            expect_next(trn, &expected);
+        }
+    }
+
+    Ok(())
+}
+
+/*
+    Tests set_on_connect: the sender's callback must fire with the receiver's address when it
+    connects, and the receiver's callback must fire for the inbound connection. The callbacks aren't
+    checked for connectionless transports, i.e. which aren't connected after the send.
+*/
+pub fn test_on_connect<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let mut receiver = T::new(net_addrs[0].clone())?;
+    let mut sender = T::new(net_addrs[1].clone())?;
+    let outbound: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+    let inbound: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+    let sink = outbound.clone();
+    sender.set_on_connect(Box::new(move |addr| {
+        if let Ok(mut addrs) = sink.lock() {
+            addrs.push(addr.to_string());
+        }
+    }));
+    let sink = inbound.clone();
+    receiver.set_on_connect(Box::new(move |addr| {
+        if let Ok(mut addrs) = sink.lock() {
+            addrs.push(addr.to_string());
+        }
+    }));
+    wait_ready(vec![&receiver, &sender])?;
+
+    let d: Data = Data(21);
+    sender.connect(net_addrs[0].clone())?;
+    sender.send(net_addrs[0].clone(), d.clone())?;
+    expect_next(&mut receiver, &d);
+    if !sender.is_connected_to(&net_addrs[0]) {
+        return skip("connectionless");
+    }
+
+    let expected = normalize_address(&net_addrs[0])?;
+    let outbound = outbound.lock().map_err(Error::from)?.clone();
+    assert!(
+        outbound
+            .iter()
+            .any(|addr| normalize_address(addr).ok().as_ref() == Some(&expected)),
+        "outbound callback not invoked with {}: {:?}",
+        expected,
+        outbound
+    );
+    assert!(
+        !inbound.lock().map_err(Error::from)?.is_empty(),
+        "inbound callback not invoked"
+    );
+
+    Ok(())
+}
+
+/*
+    Tests set_accept_backlog: with a backlog configured before binding, a burst of connections up
+    to the backlog size must all be accepted by the OS rather than refused.
+*/
+pub fn test_accept_backlog<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addr: String,
+) -> Result<()> {
+    let backlog: u32 = 32;
+    let mut trn = T::new_unbound()?;
+    trn.set_accept_backlog(backlog)?;
+    trn.bind(net_addr.clone())?;
+    wait_ready(vec![&trn])?;
+
+    let mut streams: Vec<TcpStream> = Vec::with_capacity(backlog as usize);
+    for i in 0..backlog {
+        match TcpStream::connect(&net_addr) {
+            Ok(stream) => streams.push(stream),
+            Err(e) => panic!("connection {} of {} refused: {}", i + 1, backlog, e),
+        }
+    }
+
+    Ok(())
+}
+
+/*
+    Tests the rpc layer: a request to a serving peer must resolve with the handler's response,
+    while a request to a peer which never answers must fail with Error::Timeout. A request
+    received while waiting for a response must still be served afterwards. The serving threads
+    are stopped at the end through shutdown_handle where the transport supports it.
+*/
+pub fn test_rpc<T>(net_addrs: Vec<String>) -> Result<()>
+where
+    T: Transport<Id, RpcMessage<Data>, Error, TestPeerList<Id>> + Send + 'static,
+{
+    assert!(net_addrs.len() >= 3, "three net addresses required");
+    let server_addr = net_addrs[0].clone();
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+    let server = thread::spawn(move || -> Result<()> {
+        let trn = T::new(server_addr.clone())?;
+        wait_ready(vec![&trn])?;
+        let _ = ready_tx.send(trn.shutdown_handle());
+        let mut server: RpcNode<T, Data> = RpcNode::new(trn, server_addr);
+        server.serve(|Data(x)| Data(x * 2))
+    });
+    let server_shutdown = ready_rx
+        .recv_timeout(READY_TIMEOUT)
+        .map_err(|_| Error::NotReady)?;
+
+    let mut silent = T::new(net_addrs[2].clone())?;
+    let trn = T::new(net_addrs[1].clone())?;
+    wait_ready(vec![&silent, &trn])?;
+    let client_shutdown = trn.shutdown_handle();
+    let mut client: RpcNode<T, Data> = RpcNode::new(trn, net_addrs[1].clone());
+
+    let response =
+        block_on(client.request(net_addrs[0].clone(), Data(21), time::Duration::from_secs(5)))?;
+    assert_eq!(Data(42), response);
+
+    silent.send(
+        net_addrs[1].clone(),
+        RpcMessage::Request {
+            id: 9,
+            reply_to: net_addrs[2].clone(),
+            body: Data(5),
+        },
+    )?;
+    let timeout = time::Duration::from_millis(200);
+    match block_on(client.request(net_addrs[2].clone(), Data(1), timeout)) {
+        Err(e) => match e.downcast_ref::<Error>() {
+            Some(Error::Timeout(d)) => assert_eq!(timeout, *d),
+            _ => panic!("unexpected error: {}", e),
+        },
+        Ok(d) => panic!("unexpected response {:?}", d),
+    }
+
+    let client = thread::spawn(move || client.serve(|Data(x)| Data(x + 1)));
+    block_on(async {
+        loop {
+            match silent.next().await {
+                Some(RpcMessage::Response { id, body }) => {
+                    assert_eq!(9, id);
+                    assert_eq!(Data(6), body);
+                    break;
+                }
+                Some(RpcMessage::Request { .. }) => {}
+                None => panic!("unexpected None"),
+            }
+        }
+    });
+
+    // Stop the serving threads; those of transports without a shutdown handle are left running.
+    for (shutdown, handle) in vec![(server_shutdown, server), (client_shutdown, client)] {
+        if let Some(shutdown) = shutdown {
+            shutdown.trigger();
+            handle.join().expect("serving thread panicked")?;
+        }
+    }
+
+    Ok(())
+}
+
+/*
+    Tests max_frame_size negotiation between two transports advertising different sizes: after a
+    message was exchanged both must report the smaller size for each other. Skipped for transports
+    which don't negotiate, i.e. whose negotiated_max_frame_size stays None.
+*/
+pub fn test_negotiated_max_frame_size<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let mut receiver = T::new(net_addrs[0].clone())?;
+    let mut sender = T::new(net_addrs[1].clone())?;
+    receiver.set_max_frame_size(4096);
+    sender.set_max_frame_size(1024);
+    wait_ready(vec![&receiver, &sender])?;
+
+    let d: Data = Data(22);
+    sender.send(net_addrs[0].clone(), d.clone())?;
+    expect_next(&mut receiver, &d);
+    if sender.negotiated_max_frame_size(&net_addrs[0]).is_none() {
+        return skip("no negotiated frame size");
+    }
+
+    assert_eq!(Some(1024), sender.negotiated_max_frame_size(&net_addrs[0]));
+    let remote = receiver
+        .last_remote_addr()
+        .expect("receiver did not record the sender's address");
+    assert_eq!(Some(1024), receiver.negotiated_max_frame_size(&remote));
+
+    Ok(())
+}
+
+/*
+    Tests feature negotiation between two transports advertising overlapping features: after a
+    message was exchanged both must report the intersection for each other. Skipped for transports
+    which don't negotiate, i.e. whose peer_features stays None.
+*/
+pub fn test_peer_features<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let mut receiver = T::new(net_addrs[0].clone())?;
+    let mut sender = T::new(net_addrs[1].clone())?;
+    receiver.set_features(FEATURE_COMPRESSION | FEATURE_ACK);
+    sender.set_features(FEATURE_ACK | FEATURE_CHANNELS);
+    wait_ready(vec![&receiver, &sender])?;
+    assert_eq!(None, sender.peer_features(&net_addrs[0]));
+
+    let d: Data = Data(23);
+    sender.send(net_addrs[0].clone(), d.clone())?;
+    expect_next(&mut receiver, &d);
+    if sender.peer_features(&net_addrs[0]).is_none() {
+        return skip("no peer features");
+    }
+
+    assert_eq!(Some(FEATURE_ACK), sender.peer_features(&net_addrs[0]));
+    let remote = receiver
+        .last_remote_addr()
+        .expect("receiver did not record the sender's address");
+    assert_eq!(Some(FEATURE_ACK), receiver.peer_features(&remote));
+
+    Ok(())
+}
+
+/*
+    Tests rotate_keys on an encrypting transport: messages sent both before and after rekeying
+    mid-session must be received intact. Skipped for transports which don't encrypt, i.e. whose
+    rotate_keys returns Error::Unsupported.
+*/
+pub fn test_rotate_keys<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let (mut receiver, mut sender) = ready_pair::<T>(&net_addrs)?;
+
+    let before: Data = Data(24);
+    let after: Data = Data(25);
+    sender.send(net_addrs[0].clone(), before.clone())?;
+    if let Err(e) = sender.rotate_keys(net_addrs[0].clone()) {
+        return match e.downcast_ref::<Error>() {
+            Some(Error::Unsupported(_)) => skip("rotate_keys unsupported"),
+            _ => Err(e),
+        };
+    }
+    sender.send(net_addrs[0].clone(), after.clone())?;
+    for d in [before, after].iter() {
+        expect_next(&mut receiver, d);
+    }
+
+    Ok(())
+}
+
+/*
+    Tests send_fut: awaiting the returned future must deliver the message.
+*/
+pub fn test_send_fut<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let (mut receiver, mut sender) = ready_pair::<T>(&net_addrs)?;
+
+    let d: Data = Data(23);
+    block_on(sender.send_fut(net_addrs[0].clone(), d.clone()))?;
+    expect_next(&mut receiver, &d);
+
+    Ok(())
+}
+
+/*
+    Tests send_fut interleaved with receives: sends awaited inside the same block_on as the
+    receives must not block it, and every message must arrive in the order it was sent.
+*/
+pub fn test_send_fut_interleaved<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let (mut receiver, mut sender) = ready_pair::<T>(&net_addrs)?;
+
+    block_on(async {
+        for i in 0..5u32 {
+            sender.send_fut(net_addrs[0].clone(), Data(i)).await?;
+            match receiver.next().await {
+                Some(t) => assert_eq!(Data(i), t),
+                None => panic!("unexpected None"),
+            }
+        }
+        Result::<()>::Ok(())
+    })
+}
+
+/*
+    Tests recv_timeout: a receiver nobody sends to must return Ok(None) once the timeout elapsed
+    instead of blocking, and a message sent afterwards must be returned.
+*/
+pub fn test_recv_timeout<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let mut receiver = T::new(net_addrs[0].clone())?;
+    wait_ready(vec![&receiver])?;
+
+    let timeout = time::Duration::from_millis(200);
+    let start = time::Instant::now();
+    assert_eq!(None, block_on(receiver.recv_timeout(timeout))?);
+    assert!(start.elapsed() >= timeout);
+
+    let mut sender = T::new(net_addrs[1].clone())?;
+    wait_ready(vec![&sender])?;
+    sender.send(net_addrs[0].clone(), Data(24))?;
+    assert_eq!(
+        Some(Data(24)),
+        block_on(receiver.recv_timeout(time::Duration::from_secs(5)))?
+    );
+
+    Ok(())
+}
+
+/*
+    Tests broadcast_unique_addr: with two peer ids sharing one base address, the receiver behind
+    it must get the message exactly once.
+*/
+pub fn test_broadcast_unique_addr<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let mut receiver = T::new(net_addrs[0].clone())?;
+    let mut sender = T::new(net_addrs[1].clone())?;
+    let mut pl: TestPeerList<Id> = TestPeerList::new();
+    pl.add(TestPeer::new(0.into(), net_addrs[0].clone()))?;
+    pl.add(TestPeer::new(1.into(), net_addrs[0].clone()))?;
+    wait_ready(vec![&receiver, &sender])?;
+
+    let d: Data = Data(24);
+    sender.broadcast_unique_addr(&mut pl, d.clone())?;
+    expect_next(&mut receiver, &d);
+    // Give a duplicate time to arrive.
+    thread::sleep(time::Duration::from_millis(500));
+    assert_eq!(None, try_next(&mut receiver));
+
+    Ok(())
+}
+
+/*
+    Tests broadcast_batch with a batch mixing serializable and failing items: only the good items
+    must be delivered, in order, and counted.
+*/
+pub fn test_broadcast_batch<T: Transport<Id, MixedData, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let mut pl: TestPeerList<Id> = TestPeerList::new();
+    let mut trns: Vec<T> = Vec::with_capacity(net_addrs.len());
+    for (i, net_addr) in net_addrs.iter().enumerate() {
+        pl.add(TestPeer::new(i.into(), net_addr.clone()))?;
+        trns.push(T::new(net_addr.clone())?);
+    }
+    wait_ready(&trns)?;
+
+    let items = vec![
+        MixedData::Good(1),
+        MixedData::Bad(FailingData),
+        MixedData::Good(2),
+        MixedData::Bad(FailingData),
+    ];
+    assert_eq!(2, trns[0].broadcast_batch(&mut pl, items)?);
+    for trn in trns.iter_mut() {
+        for expected in [MixedData::Good(1), MixedData::Good(2)].iter() {
+            expect_next(trn, expected);
+        }
+    }
+    thread::sleep(time::Duration::from_millis(500));
+    for trn in trns.iter_mut() {
+        assert_eq!(None, try_next(trn));
+    }
+
+    Ok(())
+}
+
+/*
+    Tests advertised_addr for a transport bound to the wildcard address: the advertised address
+    must be a parseable socket address with a concrete IP and the bound port.
+*/
+pub fn test_advertised_addr<T: Transport<Id, Data, Error, TestPeerList<Id>>>() -> Result<()> {
+    let trn = T::new(String::from("0.0.0.0:0"))?;
+    wait_ready(vec![&trn])?;
+
+    let local: SocketAddr = trn
+        .local_addr()
+        .expect("local address unknown")
+        .parse()
+        .map_err(|_| Error::AddrParse(String::from("local address")))?;
+    let advertised = trn.advertised_addr()?;
+    let parsed: SocketAddr = advertised
+        .parse()
+        .map_err(|_| Error::AddrParse(advertised.clone()))?;
+    assert!(!parsed.ip().is_unspecified(), "advertised {}", advertised);
+    assert_eq!(local.port(), parsed.port());
+
+    // Concrete addresses are advertised as they are.
+    assert_eq!("127.0.0.1:8000", advertised_address("127.0.0.1:8000")?);
+
+    Ok(())
+}
+
+/*
+    Tests set_circuit_breaker: after failing to send to a dead peer the configured number of times
+    further sends must fail with Error::CircuitOpen, and once the peer is up and the cooldown has
+    elapsed sends must go through again.
+*/
+pub fn test_circuit_breaker<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let failures: u32 = 3;
+    let cooldown = time::Duration::from_millis(500);
+    let mut sender = T::new(net_addrs[0].clone())?;
+    sender.set_circuit_breaker(failures, cooldown);
+    wait_ready(vec![&sender])?;
+
+    let d: Data = Data(25);
+    for _ in 0..failures {
+        match sender.send(net_addrs[1].clone(), d.clone()) {
+            Err(e) => {
+                if let Some(Error::CircuitOpen(_)) = e.downcast_ref::<Error>() {
+                    panic!("breaker opened too early");
+                }
+            }
+            Ok(()) => panic!("send to a dead peer succeeded"),
+        }
+    }
+    let start = time::Instant::now();
+    match sender.send(net_addrs[1].clone(), d.clone()) {
+        Err(e) => match e.downcast_ref::<Error>() {
+            Some(Error::CircuitOpen(_)) => {}
+            _ => panic!("unexpected error: {}", e),
+        },
+        Ok(()) => panic!("send through an open breaker succeeded"),
+    }
+    assert!(start.elapsed() < time::Duration::from_millis(100));
+
+    let mut receiver = T::new(net_addrs[1].clone())?;
+    wait_ready(vec![&receiver])?;
+    thread::sleep(cooldown);
+    sender.send(net_addrs[1].clone(), d.clone())?;
+    expect_next(&mut receiver, &d);
+
+    Ok(())
+}
+
+/*
+    Tests set_coalesce_window: 100 small messages sent within the window must still arrive as 100
+    distinct messages, in order.
+*/
+pub fn test_coalesce_window<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let mut receiver = T::new(net_addrs[0].clone())?;
+    let mut sender = T::new(net_addrs[1].clone())?;
+    sender.set_coalesce_window(Some(time::Duration::from_millis(50)));
+    wait_ready(vec![&receiver, &sender])?;
+
+    for i in 0..100 {
+        sender.send(net_addrs[0].clone(), Data(i))?;
+    }
+    for i in 0..100 {
+        expect_next(&mut receiver, &Data(i));
+    }
+
+    Ok(())
+}
+
+/*
+    Tests connection reuse: ten sends to one peer must go over a single pooled connection. Skipped
+    for connectionless transports, i.e. which pool nothing.
+*/
+pub fn test_pooled_connections<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let (mut receiver, mut sender) = ready_pair::<T>(&net_addrs)?;
+
+    for i in 0..10 {
+        sender.send(net_addrs[0].clone(), Data(i))?;
+        if i == 0 && sender.pooled_connection_count() == 0 {
+            return skip("no connection pool");
+        }
+        assert_eq!(1, sender.pooled_connection_count());
+    }
+    for i in 0..10 {
+        expect_next(&mut receiver, &Data(i));
+    }
+
+    Ok(())
+}
+
+/*
+    Tests observe: with both ends observed on one channel, sending and receiving a message must
+    report it once as Sent and once as Received, without affecting delivery.
+*/
+pub fn test_observe<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let (receiver, sender) = ready_pair::<T>(&net_addrs)?;
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut receiver = receiver.observe(tx.clone());
+    let mut sender = sender.observe(tx);
+
+    let d: Data = Data(26);
+    sender.send(net_addrs[0].clone(), d.clone())?;
+    expect_next(&mut receiver, &d);
+
+    assert_eq!(
+        (Direction::Sent, d.clone()),
+        rx.try_recv().map_err(|_| Error::Incomplete)?
+    );
+    assert_eq!(
+        (Direction::Received, d),
+        rx.try_recv().map_err(|_| Error::Incomplete)?
+    );
+    assert!(rx.try_recv().is_err());
+
+    Ok(())
+}
+
+/*
+    Tests sending a message which serializes to zero bytes: exactly one message must be received,
+    and the connection must stay open for a following message.
+*/
+pub fn test_zero_length<T: Transport<Id, EmptyData, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let mut receiver = T::new(net_addrs[0].clone())?;
+    let mut sender = T::new(net_addrs[1].clone())?;
+    wait_ready(vec![&receiver, &sender])?;
+
+    sender.send(net_addrs[0].clone(), EmptyData)?;
+    expect_next(&mut receiver, &EmptyData);
+    thread::sleep(time::Duration::from_millis(500));
+    assert_eq!(None, try_next(&mut receiver));
+    assert!(!receiver.is_terminated());
+
+    sender.send(net_addrs[0].clone(), EmptyData)?;
+    expect_next(&mut receiver, &EmptyData);
+
+    Ok(())
+}
+
+/*
+    Tests broadcast_to with a plain Vec of three addresses: all three transports must receive the
+    message.
+*/
+pub fn test_broadcast_to<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 3, "three net addresses required");
+    let mut trns: Vec<T> = Vec::with_capacity(3);
+    for net_addr in net_addrs.iter().take(3) {
+        trns.push(T::new(net_addr.clone())?);
+    }
+    wait_ready(&trns)?;
+
+    let d: Data = Data(28);
+    let addrs: Vec<String> = net_addrs.iter().take(3).cloned().collect();
+    trns[0].broadcast_to(addrs, d.clone())?;
+    for trn in trns.iter_mut() {
+        expect_next(trn, &d);
+    }
+
+    Ok(())
+}
+
+/*
+    Tests set_write_timeout with a stalled peer: a plain listener which accepts connections but
+    never reads. Once its receive window is full, sending must fail with Error::Timeout instead of
+    blocking forever.
+*/
+pub fn test_write_timeout<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addr: String,
+) -> Result<()> {
+    let timeout = time::Duration::from_millis(200);
+    let mut sender = T::new(net_addr)?;
+    sender.set_write_timeout(Some(timeout));
+    let stalled = TcpListener::bind("127.0.0.1:0").map_err(Error::from)?;
+    let stalled_addr = stalled.local_addr().map_err(Error::from)?.to_string();
+    wait_ready(vec![&sender])?;
+
+    let deadline = time::Instant::now() + time::Duration::from_secs(30);
+    let mut sent: u32 = 0;
+    loop {
+        let start = time::Instant::now();
+        match sender.send(stalled_addr.clone(), Data(sent)) {
+            Ok(()) => sent += 1,
+            Err(e) => {
+                match e.downcast_ref::<Error>() {
+                    Some(Error::Timeout(_)) => {}
+                    _ => panic!("unexpected error: {}", e),
+                }
+                assert!(start.elapsed() < timeout * 10, "write blocked too long");
+                break;
+            }
+        }
+        assert!(time::Instant::now() < deadline, "write never timed out");
+    }
+
+    Ok(())
+}
+
+/*
+    Tests send_failover with a peer whose base address is dead but whose second and last net
+    address is live: the message must be delivered via the live address, and a peer whose
+    addresses are all dead must make send_failover fail once its net addresses run out.
+*/
+pub fn test_send_failover<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+    dead_addr: String,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let mut receiver = T::new(net_addrs[0].clone())?;
+    let mut sender = T::new(net_addrs[1].clone())?;
+    let mut peer = TestPeer::new(0.into(), dead_addr.clone());
+    peer.set_net_addr(0, dead_addr)?;
+    peer.set_net_addr(1, net_addrs[0].clone())?;
+    wait_ready(vec![&receiver, &sender])?;
+
+    let d: Data = Data(29);
+    sender.send_failover(&peer, d.clone())?;
+    expect_next(&mut receiver, &d);
+
+    peer.net_addr.truncate(1);
+    assert!(sender.send_failover(&peer, d).is_err());
+
+    Ok(())
+}
+
+/*
+    Tests set_poll_interval: messages must still arrive with a custom poll interval. Only
+    correctness is checked; the CPU impact can be measured by running a receiver idle with
+    different intervals and comparing its CPU time, e.g. with `time` or `perf stat`.
+*/
+pub fn test_poll_interval<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let mut receiver = T::new(net_addrs[0].clone())?;
+    let mut sender = T::new(net_addrs[1].clone())?;
+    receiver.set_poll_interval(time::Duration::from_millis(50));
+    wait_ready(vec![&receiver, &sender])?;
+
+    for i in 0..3 {
+        let d: Data = Data(i);
+        sender.send(net_addrs[0].clone(), d.clone())?;
+        expect_next(&mut receiver, &d);
+    }
+
+    Ok(())
+}
+
+/*
+    Tests stream: messages read through the borrowed stream must arrive in order, and the
+    transport must be able to send once the stream was dropped.
+*/
+pub fn test_stream<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let (mut a, mut b) = ready_pair::<T>(&net_addrs)?;
+
+    for i in 0..3 {
+        b.send(net_addrs[0].clone(), Data(i))?;
+    }
+    block_on(async {
+        let mut stream = a.stream();
+        for i in 0..3 {
+            match stream.next().await {
+                Some(t) => assert_eq!(Data(i), t),
+                None => panic!("unexpected None"),
+            }
+        }
+    });
+
+    let d: Data = Data(3);
+    a.send(net_addrs[1].clone(), d.clone())?;
+    expect_next(&mut b, &d);
+
+    Ok(())
+}
+
+/*
+    Tests broadcast_tree with a small topology: with every receiver forwarding what it gets, each
+    node must receive the message exactly once, and the forwarding must die out. Skipped for
+    transports whose broadcast_tree returns Error::Unsupported.
+*/
+pub fn test_broadcast_tree<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 5, "five net addresses required");
+    let fanout = 2;
+    let mut pl: TestPeerList<Id> = TestPeerList::new();
+    let mut trns: Vec<T> = Vec::with_capacity(net_addrs.len());
+    for (i, net_addr) in net_addrs.iter().enumerate() {
+        pl.add(TestPeer::new(i.into(), net_addr.clone()))?;
+        trns.push(T::new(net_addr.clone())?);
+    }
+    wait_ready(&trns)?;
+
+    let d: Data = Data(26);
+    if let Err(e) = trns[0].broadcast_tree(&mut pl, d.clone(), fanout) {
+        return match e.downcast_ref::<Error>() {
+            Some(Error::Unsupported(_)) => skip("broadcast_tree unsupported"),
+            _ => Err(e),
+        };
+    }
+
+    let mut received = vec![0; trns.len()];
+    let deadline = time::Instant::now() + time::Duration::from_secs(10);
+    let mut quiet_since = time::Instant::now();
+    while quiet_since.elapsed() < time::Duration::from_millis(500) {
+        assert!(
+            time::Instant::now() < deadline,
+            "forwarding did not die out"
+        );
+        for (i, trn) in trns.iter_mut().enumerate() {
+            let next = try_next(&mut trn.headers_stream());
+            if let Some((headers, t)) = next {
+                assert_eq!(d, t);
+                received[i] += 1;
+                trn.forward_tree(&mut pl, &headers, t, fanout)?;
+                quiet_since = time::Instant::now();
+            }
+        }
+        thread::sleep(time::Duration::from_millis(10));
+    }
+    assert_eq!(0, received[0]);
+    assert!(received[1..].iter().all(|&n| n == 1), "{:?}", received);
+
+    Ok(())
+}
+
+/*
+    Tests set_stream_compression: two compression-enabled peers must round-trip several redundant
+    messages, putting fewer bytes on the wire than an uncompressed pair sending the same ones.
+    Requires four net addresses.
+*/
+pub fn test_stream_compression<T: Transport<Id, BulkData, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 4, "four net addresses required");
+    let mut wire_bytes = Vec::new();
+    for (pair, compression) in [Compression::None, Compression::Deflate].iter().enumerate() {
+        let mut receiver = T::new(net_addrs[2 * pair].clone())?;
+        let mut sender = T::new(net_addrs[2 * pair + 1].clone())?;
+        receiver.set_stream_compression(*compression);
+        sender.set_stream_compression(*compression);
+        wait_ready(vec![&receiver, &sender])?;
+
+        for i in 0..5 {
+            let d = BulkData(vec![i; 4096]);
+            sender.send(net_addrs[2 * pair].clone(), d.clone())?;
+            expect_next(&mut receiver, &d);
+        }
+        wire_bytes.push(sender.metrics().bytes_sent);
+    }
+    assert!(
+        wire_bytes[1] < wire_bytes[0],
+        "compressed {} bytes, uncompressed {} bytes",
+        wire_bytes[1],
+        wire_bytes[0]
+    );
+
+    Ok(())
+}
+
+// Number of peers `test_broadcast_fanout` is meant to be run with; CI can pass more addresses to
+// scale it up.
+pub const BROADCAST_FANOUT_PEERS: usize = 12;
+
+/*
+    Tests broadcast integrity under fan-out: a moderately large payload broadcast to every peer
+    (one per address in `addrs`, at least BROADCAST_FANOUT_PEERS) must reach each receiver
+    byte-identical. Catches per-peer buffer aliasing or corruption in broadcast implementations.
+*/
+pub fn test_broadcast_fanout<T: Transport<Id, BulkData, Error, TestPeerList<Id>>>(
+    addrs: Vec<String>,
+) -> Result<()> {
+    assert!(
+        addrs.len() >= BROADCAST_FANOUT_PEERS,
+        "{} net addresses required",
+        BROADCAST_FANOUT_PEERS
+    );
+    let mut pl: TestPeerList<Id> = TestPeerList::new();
+    let mut trns: Vec<T> = Vec::with_capacity(addrs.len());
+    for (i, addr) in addrs.iter().enumerate() {
+        pl.add(TestPeer::new(i.into(), addr.clone()))?;
+        trns.push(T::new(addr.clone())?);
+    }
+    wait_ready(&trns)?;
+
+    // A non-repeating pattern, so misplaced or mixed-up chunks are detected.
+    let d = BulkData((0..64 * 1024).map(|i| (i % 251) as u8).collect());
+    let expected = bincode_encode(&d)?;
+    trns[0].broadcast(&mut pl, d.clone())?;
+    for (i, trn) in trns.iter_mut().enumerate() {
+        block_on(async {
+            match trn.next().await {
+                Some(t) => {
+                    assert_eq!(expected, bincode_encode(&t).unwrap(), "peer {}", i);
+                    assert_eq!(d, t);
+                }
+                None => panic!("unexpected None"),
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/*
+    Tests pause and resume: a message sent while the receiver is paused must not be delivered
+    until the receiver resumes. Skipped for transports whose pause returns Error::Unsupported.
+*/
+pub fn test_pause_resume<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let (mut receiver, mut sender) = ready_pair::<T>(&net_addrs)?;
+
+    if let Err(e) = receiver.pause() {
+        return match e.downcast_ref::<Error>() {
+            Some(Error::Unsupported(_)) => skip("pause unsupported"),
+            _ => Err(e),
+        };
+    }
+    let d: Data = Data(28);
+    sender.send(net_addrs[0].clone(), d.clone())?;
+    thread::sleep(time::Duration::from_millis(200));
+    assert_eq!(None, try_next(&mut receiver));
+
+    receiver.resume()?;
+    expect_next(&mut receiver, &d);
+
+    Ok(())
+}
+
+/*
+    Tests broadcast during a reconnect: after a connected peer is killed and restarted, a message
+    broadcast before it is back must be queued and delivered once the connection is re-established.
+    Skipped for connectionless transports, i.e. which aren't connected after the first broadcast.
+*/
+pub fn test_broadcast_during_reconnect<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let mut pl: TestPeerList<Id> = TestPeerList::new();
+    pl.add(TestPeer::new(0.into(), net_addrs[0].clone()))?;
+    let mut receiver = T::new(net_addrs[0].clone())?;
+    let mut sender = T::new(net_addrs[1].clone())?;
+    wait_ready(vec![&receiver, &sender])?;
+
+    let d: Data = Data(29);
+    sender.broadcast(&mut pl, d.clone())?;
+    expect_next(&mut receiver, &d);
+    if !sender.is_connected_to(&net_addrs[0]) {
+        return skip("connectionless");
+    }
+
+    // Kill the peer and broadcast while it is away.
+    drop(receiver);
+    let u: Data = Data(30);
+    sender.broadcast(&mut pl, u.clone())?;
+
+    let mut receiver = T::new(net_addrs[0].clone())?;
+    wait_ready(vec![&receiver])?;
+    expect_eventually(&mut receiver, &u, &[]);
+
+    Ok(())
+}
+
+/*
+    Tests set_sequence_tracking over an ordered transport: normal delivery must produce no gap
+    errors on result_stream. A generic test can't drop frames on the wire; the dropped frame case
+    is tested over a scheduled MemoryNetwork (see the scheduler module).
+*/
+pub fn test_sequence_tracking<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    assert!(net_addrs.len() >= 2, "two net addresses required");
+    let mut receiver = T::new(net_addrs[0].clone())?;
+    let mut sender = T::new(net_addrs[1].clone())?;
+    receiver.set_sequence_tracking(true);
+    sender.set_sequence_tracking(true);
+    wait_ready(vec![&receiver, &sender])?;
+
+    for i in 0..5 {
+        sender.send(net_addrs[0].clone(), Data(i))?;
+    }
+    block_on(async {
+        let mut results = receiver.result_stream();
+        for i in 0..5 {
+            match results.next().await {
+                Some(Ok(t)) => assert_eq!(Data(i), t),
+                Some(Err(e)) => panic!("unexpected error: {}", e),
+                None => panic!("unexpected None"),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/*
+    Tests run_receive_loop: the handler must observe three sent messages, and the loop thread
+    must exit once the transport is closed. Skipped for transports which can't be closed from
+    another thread, i.e. with neither a shutdown_handle nor a quit channel.
+*/
+pub fn test_run_receive_loop<T>(net_addrs: Vec<String>) -> Result<()>
+where
+    T: ThreadSafeTransport<Id, Data, Error, TestPeerList<Id>> + 'static,
+{
+    let (receiver, mut sender) = ready_pair::<T>(&net_addrs)?;
+
+    let shutdown = receiver.shutdown_handle();
+    let quit_tx = receiver.get_quit_tx();
+    if shutdown.is_none() && quit_tx.is_none() {
+        return skip("no shutdown handle or quit channel");
+    }
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = receiver.run_receive_loop(move |data| {
+        let _ = tx.send(data);
+    });
+
+    for i in 0..3 {
+        sender.send(net_addrs[0].clone(), Data(i))?;
+    }
+    for i in 0..3 {
+        match rx.recv_timeout(READY_TIMEOUT) {
+            Ok(t) => assert_eq!(Data(i), t),
+            Err(e) => panic!("handler did not observe message {}: {:?}", i, e),
+        }
+    }
+
+    match (shutdown, quit_tx) {
+        (Some(shutdown), _) => shutdown.trigger(),
+        (None, Some(quit_tx)) => {
+            let _ = quit_tx.send(());
+        }
+        (None, None) => unreachable!(),
+    }
+    handle.join().expect("receive loop panicked");
+
+    Ok(())
+}
+
+/*
+    Tests a hot reload: a connection established by one instance is exported, imported into a new
+    instance bound to the same address, and used to continue sending. Skipped for transports whose
+    export_connections returns Error::Unsupported.
+*/
+pub fn test_connection_handoff<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let (mut receiver, mut old) = ready_pair::<T>(&net_addrs)?;
+
+    let d: Data = Data(33);
+    old.send(net_addrs[0].clone(), d.clone())?;
+    expect_next(&mut receiver, &d);
+
+    let state = match old.export_connections() {
+        Ok(state) => state,
+        Err(e) => {
+            return match e.downcast_ref::<Error>() {
+                Some(Error::Unsupported(_)) => skip("export_connections unsupported"),
+                _ => Err(e),
+            };
+        }
+    };
+    assert_eq!(1, state.len());
+    let mut new = T::new(net_addrs[1].clone())?;
+    wait_ready(vec![&new])?;
+    new.import_connections(state)?;
+    assert!(new.is_connected_to(&net_addrs[0]));
+
+    let u: Data = Data(34);
+    new.send(net_addrs[0].clone(), u.clone())?;
+    expect_next(&mut receiver, &u);
+
+    Ok(())
+}
+
+/*
+    Tests set_codec: with JSON set as the codec on both sides, messages must still be delivered
+    and decoded.
+*/
+#[cfg(feature = "json")]
+pub fn test_set_codec<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+    net_addrs: Vec<String>,
+) -> Result<()> {
+    let (mut receiver, mut sender) = ready_pair::<T>(&net_addrs)?;
+    receiver.set_codec(SerializationFormat::Json.codec())?;
+    sender.set_codec(SerializationFormat::Json.codec())?;
+
+    let d: Data = Data(26);
+    sender.send(net_addrs[0].clone(), d.clone())?;
+    expect_next(&mut receiver, &d);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{MemoryNetwork, MemoryTransport};
+
+    // A TransportSender whose send futures yield once before completing, counting the sends made
+    // and the most in flight at once. Clones share the counters.
+    #[derive(Clone, Default)]
+    struct AsyncSender {
+        sent: Arc<AtomicUsize>,
+        in_flight: Arc<AtomicUsize>,
+        max_in_flight: Arc<AtomicUsize>,
+    }
+
+    impl TransportSender<Id, Data, Error, TestPeerList<Id>> for AsyncSender {
+        fn new() -> Result<Self> {
+            Ok(AsyncSender::default())
+        }
+        fn send(&mut self, _peer_address: String, _data: Data) -> Result<()> {
+            self.sent.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+        fn send_fut(&mut self, _peer_address: String, _data: Data) -> crate::SendFut<'_> {
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+            let mut yielded = false;
+            Box::pin(futures::future::poll_fn(move |cx| {
+                if yielded {
+                    self.in_flight.fetch_sub(1, Ordering::SeqCst);
+                    self.sent.fetch_add(1, Ordering::SeqCst);
+                    Poll::Ready(Ok(()))
+                } else {
+                    yielded = true;
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+            }))
+        }
+        fn broadcast(&mut self, peers: &mut TestPeerList<Id>, data: Data) -> Result<()> {
+            for peer in peers.iter() {
+                self.send(peer.get_base_addr(), data.clone())?;
+            }
+            Ok(())
+        }
+        fn broadcast_n(
+            &mut self,
+            peers: &mut TestPeerList<Id>,
+            _n: usize,
+            data: Data,
+        ) -> Result<()> {
+            self.broadcast(peers, data)
+        }
+        fn broadcast_concurrency(&self) -> usize {
+            4
+        }
+    }
+
+    /*
+        Tests broadcast_parallel with asynchronous sends: every peer must be sent to, with sends
+        overlapping but never more than broadcast_concurrency of them in flight at once.
+    */
+    #[test]
+    fn broadcast_parallel_concurrency_test() {
+        let mut pl: TestPeerList<Id> = TestPeerList::new();
+        for i in 0..10usize {
+            pl.add(TestPeer::new(i.into(), format!("peer-{}", i)))
+                .unwrap();
+        }
+        let mut sender = AsyncSender::default();
+        block_on(sender.broadcast_parallel(&mut pl, Data(31))).unwrap();
+
+        assert_eq!(10, sender.sent.load(Ordering::SeqCst));
+        let max = sender.max_in_flight.load(Ordering::SeqCst);
+        assert!(max > 1 && max <= 4, "{} sends in flight at once", max);
+    }
+
+    /*
+        Compile test for the `wasm` feature: LocalDummy is `!Send` (it holds an `Rc`), yet still
+        satisfies LocalTransport. Also reads a message sent only after the reader went pending, to
+        check the waker is registered.
+    */
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn local_transport_compile_test() {
+        use crate::local::LocalTransport;
+        fn assert_local<T: LocalTransport<Id, Data, Error, TestPeerList<Id>>>() {}
+        assert_local::<LocalDummy>();
+
+        let mut t = LocalDummy::new(String::from("local")).unwrap();
+        let mut cx = Context::from_waker(noop_waker_ref());
+        assert_eq!(Poll::Pending, Pin::new(&mut t).poll_next(&mut cx));
+        assert!(t.inbox.borrow().waker.is_some());
+        t.send(String::from("local"), Data(7)).unwrap();
+        assert!(t.inbox.borrow().waker.is_none());
+        block_on(async {
+            match t.next().await {
+                Some(d) => assert_eq!(Data(7), d),
+                None => panic!("unexpected None"),
+            }
+        });
+    }
+
+    /*
+        Tests the raw and by-reference methods are unsupported by default: LoopbackDummy doesn't
+        override them.
+    */
+    #[test]
+    fn raw_and_ref_defaults_unsupported_test() {
+        let mut trn = <LoopbackDummy as Transport<Id, Data, Error, TestPeerList<Id>>>::new(
+            String::from("dummy"),
+        )
+        .unwrap();
+        match trn.send_raw(String::from("dummy"), vec![1, 2, 3]) {
+            Err(e) => match e.downcast_ref::<Error>() {
+                Some(Error::Unsupported(_)) => {}
+                _ => panic!("unexpected error: {:?}", e),
+            },
+            Ok(()) => panic!("expected Error::Unsupported"),
+        }
+        assert!(trn.raw_stream().is_err());
+        let mut pl: TestPeerList<Id> = TestPeerList::new();
+        pl.add(TestPeer::new(0.into(), String::from("dummy")))
+            .unwrap();
+        assert!(trn.send_ref(String::from("dummy"), &Data(1)).is_err());
+        assert!(trn.broadcast_ref(&mut pl, &Data(1)).is_err());
+        assert!(trn.sent_to.is_empty());
+    }
+
+    // A Data type which deliberately doesn't implement Clone.
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct NonCloneData(u32);
+
+    /*
+        Compile test for non-Clone payloads: MemoryTransport<NonCloneData> must implement the base
+        Transport, and its clone-free methods must be usable.
+    */
+    #[test]
+    fn non_clone_data_test() {
+        fn assert_transport<T: Transport<Id, NonCloneData, Error, TestPeerList<Id>>>() {}
+        assert_transport::<MemoryTransport<NonCloneData>>();
+
+        let network = MemoryNetwork::new();
+        let mut a: MemoryTransport<NonCloneData> = network.transport(String::from("a")).unwrap();
+        let mut b: MemoryTransport<NonCloneData> = network.transport(String::from("b")).unwrap();
+        let mut pl: TestPeerList<Id> = TestPeerList::new();
+        pl.add(TestPeer::new(0.into(), String::from("a"))).unwrap();
+
+        Transport::<Id, NonCloneData, Error, TestPeerList<Id>>::send(
+            &mut b,
+            String::from("a"),
+            NonCloneData(1),
+        )
+        .unwrap();
+        Transport::<Id, NonCloneData, Error, TestPeerList<Id>>::broadcast(
+            &mut b,
+            &mut pl,
+            NonCloneData(2),
+        )
+        .unwrap();
+        block_on(async {
+            for i in 1..3 {
+                match a.next().await {
+                    Some(t) => assert_eq!(NonCloneData(i), t),
+                    None => panic!("unexpected None"),
+                }
+            }
+        });
+    }
+
+    /*
+        Tests that a `!Unpin` transport boxed in BoxedTransport can be driven through the Transport
+        API: a broadcast and a direct send are both received back from the stream.
+    */
+    #[test]
+    fn boxed_transport_test() -> Result<()> {
+        loopback_test::<BoxedTransport<PinnedDummy>>(String::from("boxed"))
+    }
+
+    // Broadcasts to a single-peer list containing `net_addr` and sends to it directly, expecting
+    // both messages to arrive on the same transport. Used with loopback dummies.
+    fn loopback_test<T: Transport<Id, Data, Error, TestPeerList<Id>>>(
+        net_addr: String,
+    ) -> Result<()> {
+        let mut pl: TestPeerList<Id> = TestPeerList::new();
+        pl.add(TestPeer::new(0.into(), net_addr.clone()))?;
+        let mut trn = T::new(net_addr)?;
+
+        let d: Data = Data(55);
+        trn.broadcast(&mut pl, d.clone())?;
+        let u: Data = Data(0xaa);
+        trn.send(pl[0].base_addr.clone(), u.clone())?;
+        for expected in [d, u].iter() {
+            expect_next(&mut trn, expected);
+        }
+
+        Ok(())
+    }
+
+    // A pending read of LoopbackDummy must be woken by the next send and by close.
+    #[test]
+    fn loopback_wake_test() -> Result<()> {
+        let mut trn: LoopbackDummy =
+            Transport::<Id, Data, Error, TestPeerList<Id>>::new(String::from("a"))?;
+        let woken = Arc::new(WakeCounter::default());
+        let waker = futures::task::waker(woken.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        assert_eq!(Poll::Pending, Pin::new(&mut trn).poll_next(&mut cx));
+        Transport::<Id, Data, Error, TestPeerList<Id>>::send(&mut trn, "a".into(), Data(1))?;
+        assert_eq!(1, woken.0.load(Ordering::SeqCst));
+        assert_eq!(
+            Poll::Ready(Some(Data(1))),
+            Pin::new(&mut trn).poll_next(&mut cx)
+        );
+
+        assert_eq!(Poll::Pending, Pin::new(&mut trn).poll_next(&mut cx));
+        Transport::<Id, Data, Error, TestPeerList<Id>>::close(&mut trn)?;
+        assert_eq!(2, woken.0.load(Ordering::SeqCst));
+        assert_eq!(Poll::Ready(None), Pin::new(&mut trn).poll_next(&mut cx));
+
+        Ok(())
+    }
+
+    // Waker counting how many times it was woken.
+    #[derive(Default)]
+    struct WakeCounter(AtomicUsize);
+
+    impl futures::task::ArcWake for WakeCounter {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /*
+        Tests that two different transports can be stored behind `Box<dyn DynTransport>` in one vec
+        and that sending through each of them works.
+    */
+    #[test]
+    fn dyn_transport_test() -> Result<()> {
+        use crate::dynamic::DynTransport;
+        let mut trns: Vec<Box<dyn DynTransport<Id, Data, Error, TestPeerList<Id>>>> = vec![
+            Box::new(<LoopbackDummy as Transport<
+                Id,
+                Data,
+                Error,
+                TestPeerList<Id>,
+            >>::new(String::from("loopback"))?),
+            Box::new(<BoxedTransport<PinnedDummy> as Transport<
+                Id,
+                Data,
+                Error,
+                TestPeerList<Id>,
+            >>::new(String::from("boxed"))?),
+        ];
+        for (i, trn) in trns.iter_mut().enumerate() {
+            let d: Data = i.into();
+            trn.send(String::from("self"), d.clone())?;
+            expect_next(trn, &d);
+        }
+
+        Ok(())
+    }
+
+    /*
+        Tests that broadcast_reporting returns the serialization error up front, sending nothing,
+        also for transports without Transport::encode, as MapData.
+    */
+    #[test]
+    fn broadcast_reporting_unserializable_test() -> Result<()> {
+        let network = MemoryNetwork::new();
+        let inner: MemoryTransport<FailingData> = network.transport(String::from("a"))?;
+        let mut receiver: MemoryTransport<FailingData> = network.transport(String::from("b"))?;
+        let mut trn = Transport::<Id, FailingData, Error, TestPeerList<Id>>::map_data(
+            inner,
+            |d: FailingData| d,
+            |d: FailingData| d,
+        );
+        let mut pl: TestPeerList<Id> = TestPeerList::new();
+        pl.add(TestPeer::new(1.into(), String::from("b")))?;
+
+        match Transport::<Id, FailingData, Error, TestPeerList<Id>>::broadcast_reporting(
+            &mut trn,
+            &mut pl,
+            FailingData,
+        ) {
+            Ok(outcome) => panic!("expected a Bincode error, got {:?}", outcome),
+            Err(e) => match e.downcast_ref::<Error>() {
+                Some(Error::Bincode(_)) => {}
+                _ => panic!("unexpected error: {:?}", e),
+            },
+        }
+        assert_eq!(None, try_next(&mut receiver));
+
+        Ok(())
+    }
+
+    /*
+        Tests map_data by presenting a Data(u32) transport as a String transport: "hi" is packed
+        into the u32 as its bytes on the way out and unpacked on the way in. The adapter can't
+        replace the codec, which serializes the inner Data, and must hand the inner transport back.
+    */
+    #[test]
+    fn map_data_test() -> Result<()> {
+        let inner = <LoopbackDummy as Transport<Id, Data, Error, TestPeerList<Id>>>::new(
+            String::from("loopback"),
+        )?;
+        let to = |s: String| Data(s.bytes().fold(0, |acc, b| (acc << 8) | u32::from(b)));
+        let from = |d: Data| {
+            let bytes: Vec<u8> =
+                d.0.to_be_bytes()
+                    .iter()
+                    .cloned()
+                    .skip_while(|b| *b == 0)
+                    .collect();
+            String::from_utf8_lossy(&bytes).into_owned()
+        };
+        let mut trn = Transport::<Id, Data, Error, TestPeerList<Id>>::map_data(inner, to, from);
+
+        Transport::<Id, String, Error, TestPeerList<Id>>::send(
+            &mut trn,
+            String::from("loopback"),
+            String::from("hi"),
+        )?;
+        expect_next(&mut trn, &String::from("hi"));
+
+        match Transport::<Id, String, Error, TestPeerList<Id>>::set_codec(
+            &mut trn,
+            Box::new(BincodeCodec::new()),
+        ) {
+            Err(e) => match e.downcast_ref::<Error>() {
+                Some(Error::Unsupported(_)) => {}
+                _ => panic!("unexpected error {}", e),
+            },
+            Ok(()) => panic!("set_codec must be unsupported on MapData"),
+        }
+        let _inner: LoopbackDummy = trn.into_inner();
+
+        Ok(())
+    }
+
+    /*
+        Tests broadcast_ordered: peers inserted out of order must be sent to in ascending id order.
+    */
+    #[test]
+    fn broadcast_ordered_test() -> Result<()> {
+        let mut pl: TestPeerList<Id> = TestPeerList::new();
+        for i in [2usize, 0, 1].iter() {
+            pl.add(TestPeer::new((*i).into(), format!("peer{}", i)))?;
+        }
+        let mut trn = <LoopbackDummy as Transport<Id, Data, Error, TestPeerList<Id>>>::new(
+            String::from("loopback"),
+        )?;
+
+        trn.broadcast_ordered(&mut pl, Data(1))?;
+        assert_eq!(vec!["peer0", "peer1", "peer2"], trn.sent_to);
+
+        Ok(())
+    }
+
+    /*
+        Tests DropAudit: dropping a transport with messages queued for a stalled peer must report
+        the unflushed messages and bytes of that peer, and nothing for a peer whose messages were
+        flushed.
+    */
+    #[cfg(feature = "debug")]
+    #[test]
+    fn drop_audit_test() -> Result<()> {
+        let reports: Arc<Mutex<Vec<(String, Unflushed)>>> = Arc::new(Mutex::new(Vec::new()));
+        let sink = reports.clone();
+        let mut trn = <StalledDummy as Transport<Id, Data, Error, TestPeerList<Id>>>::new(
+            String::from("stalled"),
+        )?;
+        trn.audit = DropAudit::with_reporter(move |peer, unflushed| {
+            if let Ok(mut reports) = sink.lock() {
+                reports.push((peer.to_string(), unflushed));
+            }
+        });
+
+        let size = bincode_encode(&Data(0))?.len() as u64;
+        for i in 0..3 {
+            trn.send(String::from("stalled:1"), Data(i))?;
+        }
+        trn.send(String::from("live:1"), Data(3))?;
+        trn.audit.flushed("live:1", size);
+        drop(trn);
+
+        let guard = reports.lock().map_err(Error::from)?;
+        assert_eq!(
+            vec![(
+                String::from("stalled:1"),
+                Unflushed {
+                    messages: 3,
+                    bytes: 3 * size
+                }
+            )],
+            *guard
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+/// # Fantom Libtransport/handoff
+///
+/// This file defines `ConnectionState`, the live connections and per-peer queues handed from an
+/// old Transport instance to a freshly configured one by `Transport::export_connections` and
+/// `Transport::import_connections`, for zero-downtime configuration reloads.
+///
+/// Exporting consumes the old instance and closes its listener; the new instance then binds the
+/// same address and imports the established connections, which continue without reconnecting.
+/// Frames queued for connecting peers (see `pending::PendingQueue`) move along.
+///
+/// Settings which only apply to new connections or to the send path can change during the
+/// transfer: timeouts, rate limits, coalescing, priority weights, the pending capacity and the
+/// settings advertised in new handshakes (frame size, features, compression). The state of each
+/// migrated connection cannot change: its negotiated handshake settings and session keys are
+/// kept, and both instances must use the same `Data` type and serialization format.
+use crate::pending::PendingQueue;
+use std::any::Any;
+use std::collections::HashMap;
+
+/// Connections and queued frames exported from a Transport.
+#[derive(Default)]
+pub struct ConnectionState {
+    /// Established connections by peer address, as the implementation's own socket type (e.g. a
+    /// `TcpStream`) together with any per-connection state it keeps.
+    pub connections: HashMap<String, Box<dyn Any + Send>>,
+    /// Frames waiting for peers whose connection was being established.
+    pub pending: PendingQueue,
+}
+
+impl ConnectionState {
+    /// Creates an empty state.
+    pub fn new() -> ConnectionState {
+        ConnectionState::default()
+    }
+
+    /// Adds the connection to `peer_address`.
+    pub fn insert<C: Any + Send>(&mut self, peer_address: String, connection: C) {
+        self.connections.insert(peer_address, Box::new(connection));
+    }
+
+    /// Removes the connection to `peer_address` if it is of type `C`.
+    pub fn take<C: Any + Send>(&mut self, peer_address: &str) -> Option<C> {
+        match self.connections.remove(peer_address)?.downcast::<C>() {
+            Ok(connection) => Some(*connection),
+            Err(connection) => {
+                self.connections
+                    .insert(peer_address.to_string(), connection);
+                None
+            }
+        }
+    }
+
+    /// Returns the number of connections.
+    pub fn len(&self) -> usize {
+        self.connections.len()
+    }
+
+    /// Returns true if there is no connection and no queued frame.
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty() && self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /*
+        Tests ConnectionState: connections are taken back only as the type they were stored as.
+    */
+    #[test]
+    fn connection_state_test() {
+        let mut state = ConnectionState::new();
+        assert!(state.is_empty());
+        state.insert(String::from("peer"), 7u32);
+        assert_eq!(1, state.len());
+        assert_eq!(None, state.take::<String>("peer"));
+        assert_eq!(Some(7u32), state.take::<u32>("peer"));
+        assert!(state.is_empty());
+    }
+}
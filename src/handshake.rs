@@ -0,0 +1,227 @@
+/// # Fantom Libtransport/handshake
+///
+/// This file defines the connection handshake shared by transport implementations. Right after a
+/// connection is established, before any data frame, each side sends a `HandshakeFrame`
+/// advertising its frame version and limits, and reads the remote one. Both sides then run
+/// `HandshakeFrame::negotiate` and, since negotiation is symmetric, agree on the same settings:
+///
+/// - the frame versions must match, else the connection fails with
+///   `Error::UnsupportedFrameVersion`;
+/// - the maximum frame size is the smaller of the two advertised sizes. Frames exceeding it are
+///   rejected with `Error::MessageTooLarge` (see `Negotiated::check_frame_size`);
+/// - the features are the intersection of the two advertised `FEATURE_*` bitmasks, so an optional
+///   feature is only used if both sides support it.
+///
+/// Accept loops run inbound handshakes through a `HandshakeLimiter`, which caps how many are
+/// processed at once (see `Transport::set_max_concurrent_handshakes`) and records the peak.
+use crate::codec::{bincode_decode, bincode_encode, DEFAULT_MAX_MESSAGE_SIZE};
+use crate::errors::{Error, Result};
+use crate::framing::FRAME_VERSION;
+use crate::semaphore::{Semaphore, SemaphoreGuard};
+use crate::DEFAULT_MAX_CONCURRENT_HANDSHAKES;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Maximum frame size advertised by default.
+pub const DEFAULT_MAX_FRAME_SIZE: u32 = DEFAULT_MAX_MESSAGE_SIZE as u32;
+
+/// Feature bit: the sender accepts compressed frames.
+pub const FEATURE_COMPRESSION: u32 = 1;
+/// Feature bit: the sender acknowledges received frames.
+pub const FEATURE_ACK: u32 = 1 << 1;
+/// Feature bit: the sender multiplexes channels over a connection.
+pub const FEATURE_CHANNELS: u32 = 1 << 2;
+
+/// The frame each side sends when a connection is established.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct HandshakeFrame {
+    /// Frame format version spoken by the sender.
+    pub version: u8,
+    /// Largest frame, in bytes, the sender accepts.
+    pub max_frame_size: u32,
+    /// Bitmask of the optional `FEATURE_*` features the sender supports.
+    pub features: u32,
+}
+
+/// The settings both sides of a connection agreed on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Negotiated {
+    pub max_frame_size: u32,
+    pub features: u32,
+}
+
+impl Default for HandshakeFrame {
+    fn default() -> HandshakeFrame {
+        HandshakeFrame::new(DEFAULT_MAX_FRAME_SIZE)
+    }
+}
+
+impl HandshakeFrame {
+    /// Creates a handshake frame for this crate's frame version advertising `max_frame_size` and
+    /// no optional features.
+    pub fn new(max_frame_size: u32) -> HandshakeFrame {
+        HandshakeFrame {
+            version: FRAME_VERSION,
+            max_frame_size,
+            features: 0,
+        }
+    }
+
+    /// Sets the advertised feature bitmask.
+    pub fn with_features(mut self, features: u32) -> HandshakeFrame {
+        self.features = features;
+        self
+    }
+
+    /// Serializes the frame.
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        bincode_encode(self)
+    }
+
+    /// Deserializes a frame.
+    pub fn decode(bytes: &[u8]) -> Result<HandshakeFrame> {
+        bincode_decode(bytes)
+    }
+
+    /// Negotiates the connection settings from the local frame and the `remote` one.
+    pub fn negotiate(&self, remote: &HandshakeFrame) -> Result<Negotiated> {
+        if remote.version != self.version {
+            return Err(Error::UnsupportedFrameVersion(remote.version).into());
+        }
+        Ok(Negotiated {
+            max_frame_size: self.max_frame_size.min(remote.max_frame_size),
+            features: self.features & remote.features,
+        })
+    }
+}
+
+impl Negotiated {
+    /// Fails with `Error::MessageTooLarge` if a frame of `len` bytes exceeds the negotiated size.
+    pub fn check_frame_size(&self, len: usize) -> Result<()> {
+        if len as u64 > u64::from(self.max_frame_size) {
+            return Err(Error::MessageTooLarge(u64::from(self.max_frame_size)).into());
+        }
+        Ok(())
+    }
+}
+
+/// Caps the number of handshakes processed at once, recording the most seen at once.
+pub struct HandshakeLimiter {
+    semaphore: Semaphore,
+    active: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+/// A permit to process one handshake, released on drop.
+pub struct HandshakePermit<'a> {
+    _guard: SemaphoreGuard<'a>,
+    active: &'a AtomicUsize,
+}
+
+impl HandshakeLimiter {
+    /// Creates a limiter processing at most `max` handshakes at once (at least one).
+    pub fn new(max: usize) -> HandshakeLimiter {
+        HandshakeLimiter {
+            semaphore: Semaphore::new(max),
+            active: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        }
+    }
+
+    /// Blocks until fewer than the maximum handshakes are being processed and takes a permit.
+    pub fn acquire(&self) -> Result<HandshakePermit<'_>> {
+        let guard = self.semaphore.acquire()?;
+        let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(active, Ordering::SeqCst);
+        Ok(HandshakePermit {
+            _guard: guard,
+            active: &self.active,
+        })
+    }
+
+    /// Returns the most handshakes processed at once so far.
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::SeqCst)
+    }
+}
+
+impl Default for HandshakeLimiter {
+    fn default() -> HandshakeLimiter {
+        HandshakeLimiter::new(DEFAULT_MAX_CONCURRENT_HANDSHAKES)
+    }
+}
+
+impl<'a> Drop for HandshakePermit<'a> {
+    fn drop(&mut self) {
+        self.active.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    // With the limit set to 2, a flood of ten handshakes must never have more than 2 processed
+    // at once.
+    #[test]
+    fn handshake_limiter_test() {
+        let limiter = Arc::new(HandshakeLimiter::new(2));
+        let handshakes: Vec<_> = (0..10)
+            .map(|_| {
+                let limiter = limiter.clone();
+                thread::spawn(move || {
+                    let _permit = limiter.acquire().unwrap();
+                    thread::sleep(Duration::from_millis(20));
+                })
+            })
+            .collect();
+        for handshake in handshakes {
+            handshake.join().unwrap();
+        }
+        let peak = limiter.peak();
+        assert!(peak >= 1 && peak <= 2, "{} handshakes at once", peak);
+    }
+
+    /*
+        Tests the handshake negotiation: two sides advertising different maximum frame sizes must
+        both adopt the smaller one, and frames exceeding it must be rejected with
+        Error::MessageTooLarge.
+    */
+    #[test]
+    fn handshake_test() -> Result<()> {
+        let small = HandshakeFrame::new(1024);
+        let large = HandshakeFrame::new(4096);
+        assert_eq!(large, HandshakeFrame::decode(&large.encode()?)?);
+
+        let a = small.negotiate(&HandshakeFrame::decode(&large.encode()?)?)?;
+        let b = large.negotiate(&HandshakeFrame::decode(&small.encode()?)?)?;
+        assert_eq!(1024, a.max_frame_size);
+        assert_eq!(a, b);
+
+        a.check_frame_size(1024)?;
+        match a.check_frame_size(1025) {
+            Err(e) => match e.downcast_ref::<Error>() {
+                Some(Error::MessageTooLarge(1024)) => {}
+                _ => panic!("unexpected error: {}", e),
+            },
+            Ok(()) => panic!("oversized frame accepted"),
+        }
+
+        let future = HandshakeFrame {
+            version: small.version + 1,
+            max_frame_size: 1024,
+            features: 0,
+        };
+        assert!(small.negotiate(&future).is_err());
+
+        let a = HandshakeFrame::default().with_features(FEATURE_COMPRESSION | FEATURE_ACK);
+        let b = HandshakeFrame::default().with_features(FEATURE_ACK | FEATURE_CHANNELS);
+        assert_eq!(FEATURE_ACK, a.negotiate(&b)?.features);
+        assert_eq!(FEATURE_ACK, b.negotiate(&a)?.features);
+
+        Ok(())
+    }
+}
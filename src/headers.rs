@@ -0,0 +1,107 @@
+/// # Fantom Libtransport/headers
+///
+/// This file defines per-message metadata headers: a small map of out-of-band values (such as a
+/// sender epoch or a message type tag) sent alongside a `Data` payload without being part of it.
+///
+/// On the wire the headers are serialized as a prefix block ahead of the payload:
+///
+/// [ headers length: u32, big-endian ][ bincode(headers) ][ bincode(data) ]
+///
+/// Both parts are decoded with `codec::bincode_decode`, so the decode size limit applies to the
+/// headers block as well as to the payload.
+use crate::codec::{bincode_decode, bincode_encode};
+use crate::errors::{Error, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+
+/// Per-message metadata headers, keyed by name.
+pub type Headers = BTreeMap<String, Vec<u8>>;
+
+/// Size in bytes of the headers length prefix.
+const HEADERS_LEN_SIZE: usize = 4;
+
+/// Serializes `headers` as a length-prefixed block followed by the serialized `data`.
+pub fn encode<Data: Serialize>(headers: &Headers, data: &Data) -> Result<Vec<u8>> {
+    let header_bytes = bincode_encode(headers)?;
+    let data_bytes = bincode_encode(data)?;
+    let header_len: u32 = header_bytes
+        .len()
+        .try_into()
+        .map_err(|_| Error::AtMaxVecCapacity)?;
+    let mut out = Vec::with_capacity(HEADERS_LEN_SIZE + header_bytes.len() + data_bytes.len());
+    out.extend_from_slice(&header_len.to_be_bytes());
+    out.extend_from_slice(&header_bytes);
+    out.extend_from_slice(&data_bytes);
+    Ok(out)
+}
+
+/// Splits a buffer produced by `encode` back into its headers and `Data`.
+pub fn decode<Data: DeserializeOwned>(bytes: &[u8]) -> Result<(Headers, Data)> {
+    if bytes.len() < HEADERS_LEN_SIZE {
+        return Err(Error::Incomplete.into());
+    }
+    let mut len_bytes = [0u8; HEADERS_LEN_SIZE];
+    len_bytes.copy_from_slice(&bytes[..HEADERS_LEN_SIZE]);
+    let header_len = u32::from_be_bytes(len_bytes) as usize;
+    let rest = &bytes[HEADERS_LEN_SIZE..];
+    if rest.len() < header_len {
+        return Err(Error::Incomplete.into());
+    }
+    let headers: Headers = bincode_decode(&rest[..header_len])?;
+    let data: Data = bincode_decode(&rest[header_len..])?;
+    Ok((headers, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::DEFAULT_MAX_MESSAGE_SIZE;
+    use crate::generic_test::Data;
+
+    // A headers block holding one header whose value declares a length of `len` bytes.
+    fn oversized_headers(len: u64) -> Vec<u8> {
+        let mut block = Vec::new();
+        block.extend_from_slice(&1u64.to_le_bytes());
+        block.extend_from_slice(&1u64.to_le_bytes());
+        block.push(b'a');
+        block.extend_from_slice(&len.to_le_bytes());
+        let mut bytes = (block.len() as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(&block);
+        bytes
+    }
+
+    /*
+        Tests that the headers block is size limited: a header value declaring a huge length must
+        fail with Error::MessageTooLarge instead of allocating.
+    */
+    #[test]
+    fn decode_limit_test() {
+        match decode::<u32>(&oversized_headers(1 << 40)) {
+            Err(e) => match e.downcast_ref::<Error>() {
+                Some(Error::MessageTooLarge(limit)) => assert_eq!(DEFAULT_MAX_MESSAGE_SIZE, *limit),
+                _ => panic!("unexpected error: {}", e),
+            },
+            Ok(_) => panic!("unexpected Ok"),
+        }
+    }
+
+    /*
+        Tests that per-message headers survive the encode/decode round trip alongside a Data value.
+    */
+    #[test]
+    fn headers_round_trip_test() -> Result<()> {
+        let mut headers = Headers::new();
+        headers.insert(String::from("epoch"), vec![0, 0, 0, 7]);
+        let d: Data = Data(55);
+
+        let bytes = encode(&headers, &d)?;
+        let (h, t): (Headers, Data) = decode(&bytes)?;
+        assert_eq!(headers, h);
+        assert_eq!(Some(&vec![0, 0, 0, 7]), h.get("epoch"));
+        assert_eq!(d, t);
+
+        Ok(())
+    }
+}
@@ -37,21 +37,224 @@
 ///
 #[macro_use]
 extern crate failure;
-use crate::errors::Result;
-use futures::stream::Stream;
-use libcommon_rs::peer::{PeerId, PeerList};
+use crate::addr::BindAddr;
+use crate::channel::DEFAULT_CHANNEL;
+use crate::codec::Codec;
+use crate::compression::Compression;
+use crate::concurrency::SendLimiter;
+use crate::errors::{Error, Result};
+use crate::events::TransportEvent;
+use crate::format::SerializationFormat;
+use crate::handoff::ConnectionState;
+use crate::headers::Headers;
+use crate::map::MapData;
+use crate::metrics::{ConnectionInfo, Metrics, TransportStats};
+use crate::observe::{Direction, Observed};
+use crate::priority::Priority;
+use crate::safe::BroadcastSafe;
+use crate::shutdown::ShutdownHandle;
+use crate::timer::{self, RecvTimeout};
+use core::fmt;
+use core::pin::Pin;
+use core::str::FromStr;
+use core::task::Poll;
+use failure::Fail;
+use futures::executor::block_on;
+use futures::future::{self, AbortHandle, Abortable, Future};
+use futures::stream::{self, FusedStream, Stream, StreamExt};
+use libcommon_rs::peer::{Peer, PeerId, PeerList};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use std::collections::HashSet;
 use std::marker::Unpin;
-use std::sync::mpsc::Sender;
+use std::sync::mpsc::{Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Default number of sends run at once by `TransportSender::broadcast_parallel`.
+pub const DEFAULT_BROADCAST_CONCURRENCY: usize = 16;
+
+/// Default accept backlog of a Transport's listener, see `Transport::set_accept_backlog`.
+pub const DEFAULT_ACCEPT_BACKLOG: u32 = 128;
+
+/// Default number of inbound handshakes processed at once, see
+/// `Transport::set_max_concurrent_handshakes`.
+pub const DEFAULT_MAX_CONCURRENT_HANDSHAKES: usize = 64;
 
 /// An enum for identifying various Transport types. So far only the TCP variant has been identified
 /// and implemented.
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum TransportType {
     Unknown,
     TCP,
+    /// WebSocket transport, intended for browser/WASM peers. WebSockets provide their own message
+    /// framing, so an implementation maps each `Data` to exactly one binary WS message; no extra
+    /// length prefix is needed.
+    WebSocket,
+    /// Unix domain socket transport, for co-located processes on the same host. The bind address
+    /// is a filesystem path (e.g. "/tmp/node.sock") rather than host:port. A socket file left
+    /// behind by a previous process must be removed before rebinding, see
+    /// `addr::remove_stale_socket`.
+    UnixSocket,
+}
+
+/// Allows a TransportType to be parsed from a configuration string (case insensitive).
+impl FromStr for TransportType {
+    type Err = Error;
+    fn from_str(s: &str) -> std::result::Result<TransportType, Error> {
+        match s.to_lowercase().as_str() {
+            "unknown" => Ok(TransportType::Unknown),
+            "tcp" => Ok(TransportType::TCP),
+            "websocket" | "ws" => Ok(TransportType::WebSocket),
+            "unix" => Ok(TransportType::UnixSocket),
+            _ => Err(Error::UnknownTransportType(s.to_string())),
+        }
+    }
+}
+
+/// Displays a TransportType in the same form accepted by `FromStr`.
+impl fmt::Display for TransportType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            TransportType::Unknown => "unknown",
+            TransportType::TCP => "tcp",
+            TransportType::WebSocket => "websocket",
+            TransportType::UnixSocket => "unix",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Maps a peer `Id` to the address it can be reached at, see `Transport::set_resolver`.
+pub type Resolver<Id> = Box<dyn Fn(&Id) -> Option<String> + Send + Sync>;
+
+/// A boxed send future, keeping the async forms of the Transport operations object-safe and
+/// usable on stable Rust without `async fn` in traits.
+pub type SendFut<'a> = Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
+
+/// Controls how a broadcast reacts to a failed send to one of the peers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BroadcastPolicy {
+    /// Stop at the first failed send and return its error; later peers are not sent to.
+    FailFast,
+    /// Attempt every peer and return `Error::MultipleErrors` listing all failed sends, if any.
+    BestEffort,
+}
+
+/// Counts of the sends performed by `Transport::broadcast_reporting`, telling a broadcast to zero
+/// peers (`attempted == 0`) apart from one that reached nobody (`succeeded == 0`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BroadcastOutcome {
+    pub attempted: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
+/// What a Transport supports, as reported by `Transport::capabilities`, e.g. so orchestration
+/// can detect feature mismatches between nodes during a rolling upgrade before connecting them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The wire frame format version (see `framing::FRAME_VERSION`).
+    pub version: u8,
+    pub supports_compression: bool,
+    pub supports_ack: bool,
+    /// Largest message accepted, in bytes.
+    pub max_message_size: u64,
+    pub supported_formats: Vec<SerializationFormat>,
+}
+
+/// The configuration a Transport is created from with `Transport::new`. Implementations needing
+/// more than a bind address read the further settings through the provided methods, whose
+/// defaults are this crate's defaults, so a configuration type only overrides what it sets.
+///
+/// `DefaultConfiguration` holds every setting. `String` is a configuration too, holding only the
+/// bind address, so `Transport::new(addr)` calls written before configurations existed keep
+/// working while implementations migrate.
+///
+/// The trait has no `Data` parameter, as no setting depends on the payload type: one
+/// configuration can create transports of several `Data` types.
+pub trait TransportConfiguration {
+    /// Creates a configuration binding to `bind_addr`, with every other setting at its default.
+    fn new(bind_addr: String) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Returns the net address the listener is bound to.
+    fn bind_addr(&self) -> &str;
+
+    /// Sets the net address the listener is bound to.
+    fn set_bind_addr(&mut self, addr: String);
+
+    /// Returns the accept backlog of the listener, see `Transport::set_accept_backlog`.
+    fn accept_backlog(&self) -> u32 {
+        DEFAULT_ACCEPT_BACKLOG
+    }
+
+    /// Returns the socket send buffer size, or None to keep the OS default.
+    fn send_buffer_size(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns the socket receive buffer size, or None to keep the OS default.
+    fn recv_buffer_size(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// A configuration holding every setting of `TransportConfiguration`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DefaultConfiguration {
+    pub bind_addr: String,
+    pub accept_backlog: u32,
+    pub send_buffer_size: Option<usize>,
+    pub recv_buffer_size: Option<usize>,
+}
+
+impl TransportConfiguration for DefaultConfiguration {
+    fn new(bind_addr: String) -> Result<Self> {
+        Ok(DefaultConfiguration {
+            bind_addr,
+            accept_backlog: DEFAULT_ACCEPT_BACKLOG,
+            send_buffer_size: None,
+            recv_buffer_size: None,
+        })
+    }
+
+    fn bind_addr(&self) -> &str {
+        &self.bind_addr
+    }
+
+    fn set_bind_addr(&mut self, addr: String) {
+        self.bind_addr = addr;
+    }
+
+    fn accept_backlog(&self) -> u32 {
+        self.accept_backlog
+    }
+
+    fn send_buffer_size(&self) -> Option<usize> {
+        self.send_buffer_size
+    }
+
+    fn recv_buffer_size(&self) -> Option<usize> {
+        self.recv_buffer_size
+    }
+}
+
+/// A bare bind address as a configuration, with every other setting at its default.
+impl TransportConfiguration for String {
+    fn new(bind_addr: String) -> Result<Self> {
+        Ok(bind_addr)
+    }
+
+    fn bind_addr(&self) -> &str {
+        self
+    }
+
+    fn set_bind_addr(&mut self, addr: String) {
+        *self = addr;
+    }
 }
 
 /// Transport trait allows us to create multiple message sending/receiving services which share
@@ -65,25 +268,182 @@ pub enum TransportType {
 ///
 /// NOTE: Transport must implement Stream trait from async/.await framework.
 ///
+/// The stream yields `None` only once the Transport has been closed (see `close`) or is being
+/// dropped; until then it stays pending while no data is available. After yielding `None` the
+/// stream must report `is_terminated() == true` through `FusedStream`, so combinators such as
+/// `select!` know it is finished and never poll it again. Requiring `FusedStream` is a breaking
+/// change for implementations written against 0.0.4, see the README for how to migrate.
+///
+/// A frame which fails to deserialize must not end the stream either: it is skipped and polling
+/// continues with the next frame. `decode::DecodeStream` implements this contract on top of a
+/// stream of received frames.
+///
+/// `Data` is only required to be `Serialize + DeserializeOwned`. Helpers which clone it, e.g. the
+/// broadcast variants sending a copy per peer or `observe`, carry their own `where Data: Clone`
+/// bound, so a non-`Clone` payload can still use the rest of the trait.
+///
 /// For an example of how this trait can be implemented, please look at the libtransport-tcp
 /// repository: https://github.com/Fantom-foundation/libtransport-tcp
 
-pub trait Transport<Id, Data, Error, Pl>: Stream<Item = Data> + Drop + Unpin
+pub trait Transport<Id, Data, Error, Pl>: FusedStream<Item = Data> + Drop + Unpin
 where
     Id: PeerId,
     Pl: PeerList<Id, Error>,
     Data: Serialize + DeserializeOwned,
 {
-    /// Creates a new Transport type. Takes in a net address to be bound to a listener
-    fn new(set_bind_net_addr: String) -> Result<Self>
+    /// Creates a new Transport type from a configuration, binding a listener to its
+    /// `bind_addr`. A plain `String` bind address is a valid configuration.
+    fn new<C>(cfg: C) -> Result<Self>
     where
+        C: TransportConfiguration,
         Self: Sized;
 
+    /// Creates a new Transport type which runs its background work (e.g. accept/read loops) as
+    /// tasks handed to `spawner` instead of on threads of its own, so applications control where
+    /// that work runs. `new` is equivalent to passing a spawner calling `std::thread::spawn`. The
+    /// default implementation ignores `spawner` and calls `new`.
+    fn new_on_executor<C>(
+        cfg: C,
+        _spawner: impl Fn(Box<dyn FnOnce() + Send>) + Send + Sync + 'static,
+    ) -> Result<Self>
+    where
+        C: TransportConfiguration,
+        Self: Sized,
+    {
+        Self::new(cfg)
+    }
+
+    /// Creates a new Transport type bound to an address validated up front with
+    /// `addr::BindAddr`. The default implementation passes the address to `new`.
+    fn from_bind_addr(bind_addr: BindAddr) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        Self::new(String::from(bind_addr))
+    }
+
+    /// Creates a new Transport type which is not bound to any address yet, e.g. to construct it
+    /// early for dependency injection and bind once configuration is loaded. Until `bind` is
+    /// called `send`, `broadcast` and the stream fail with `Error::NotReady` and `is_ready`
+    /// returns false. The default implementation returns `Error::Unsupported`.
+    fn new_unbound() -> Result<Self>
+    where
+        Self: Sized,
+    {
+        Err(errors::Error::Unsupported(String::from("new_unbound")).into())
+    }
+
+    /// Binds a Transport created with `new_unbound` to the specified net address. The default
+    /// implementation returns `Error::Unsupported`.
+    fn bind(&mut self, _addr: String) -> Result<()> {
+        Err(errors::Error::Unsupported(String::from("bind")).into())
+    }
+
+    /// Sets the accept backlog of the listener, i.e. the number of pending connections the OS
+    /// queues before refusing new ones, mapping to `listen(backlog)`. It applies when binding, so
+    /// it must be set on a Transport created with `new_unbound` before calling `bind`. The default
+    /// backlog is `DEFAULT_ACCEPT_BACKLOG`; the OS may clamp the value (e.g. to `somaxconn` on
+    /// Linux). The default implementation ignores it, which suits connectionless transports.
+    fn set_accept_backlog(&mut self, _backlog: u32) -> Result<()> {
+        Ok(())
+    }
+
+    /// Caps the number of inbound handshakes the accept loop processes at once at `max` (at least
+    /// 1), e.g. with a `handshake::HandshakeLimiter`, so a flood of connections can't exhaust the node
+    /// with handshake work (especially with authentication or encryption). Further connections
+    /// wait in the accept queue, or are rejected once it is full. Defaults to
+    /// `DEFAULT_MAX_CONCURRENT_HANDSHAKES`; the default implementation ignores the setting, which
+    /// suits transports without a handshake.
+    fn set_max_concurrent_handshakes(&mut self, _max: usize) {}
+
+    /// Returns the most inbound handshakes processed at once so far (see
+    /// `handshake::HandshakeLimiter::peak`), or None if the implementation doesn't track it (the
+    /// default).
+    fn peak_concurrent_handshakes(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns true once the Transport has finished binding/listening. Implementations which
+    /// bind asynchronously must make `send` and `broadcast` fail with `Error::NotReady` (or block
+    /// until ready) before then, never silently drop. There is no default: an implementation
+    /// binding in `new` returns true, one binding in the background must report when its listener
+    /// is up, so that callers waiting for readiness (e.g. `generic_test::wait_ready`) never race
+    /// the bind.
+    fn is_ready(&self) -> bool;
+
+    /// Returns true if every message sent is delivered, unless an error is returned, as with
+    /// stream transports such as TCP. Datagram transports (e.g. UDP) may lose or duplicate
+    /// messages and return false; test them with `generic_test::unreliable_common_test` rather
+    /// than `generic_test::common_test`. The default reports reliable.
+    fn is_reliable(&self) -> bool {
+        true
+    }
+
+    /// Returns true if messages from one sender to one peer are received in the order they were
+    /// sent. The default reports order preserving.
+    fn preserves_order(&self) -> bool {
+        true
+    }
+
     /// Sends a message of type 'Data' to the specified peer (as specified by `peer_address`)
     fn send(&mut self, peer_address: String, data: Data) -> Result<()>;
 
+    /// Sends a message of type 'Data' to the given peer, at the address returned by its
+    /// `get_base_addr`.
+    fn send_peer(&mut self, peer: &Pl::P, data: Data) -> Result<()> {
+        self.send(peer.get_base_addr(), data)
+    }
+
+    /// Sends a message of type 'Data' to the specified peer, returning a future which resolves
+    /// once the message was sent. The default implementation performs the synchronous `send` and
+    /// returns its result as a ready future; implementations on non-blocking sockets should
+    /// override it so the send can be awaited alongside receives without blocking the executor.
+    fn send_fut(&mut self, peer_address: String, data: Data) -> SendFut<'_> {
+        Box::pin(future::ready(self.send(peer_address, data)))
+    }
+
+    /// Sends a message of type 'Data' to a PeerList peer reachable at several endpoints: its
+    /// `base` address is tried first, then its `net_addr` addresses in turn (n = 0, 1, ...), until
+    /// a send succeeds. The first empty `net_addr` ends the list, so the peer's `get_net_addr`
+    /// must return an empty address for any n past its last address rather than panic. Returns
+    /// the last error if every address fails.
+    fn send_failover(&mut self, peer: &Pl::P, data: Data) -> Result<()>
+    where
+        Data: Clone,
+    {
+        let mut last = match self.send(peer.get_base_addr(), data.clone()) {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        for n in 0.. {
+            let addr = peer.get_net_addr(n);
+            if addr.is_empty() {
+                break;
+            }
+            match self.send(addr, data.clone()) {
+                Ok(()) => return Ok(()),
+                Err(e) => last = e,
+            }
+        }
+        Err(last)
+    }
+
+    /// Sends a message of type 'Data' to the specified peer without blocking: if the peer's send
+    /// buffer is full the message is skipped and `Ok(false)` is returned. The default
+    /// implementation performs a regular `send` and returns `Ok(true)`.
+    fn try_send(&mut self, peer_address: String, data: Data) -> Result<bool> {
+        self.send(peer_address, data)?;
+        Ok(true)
+    }
+
     /// Broadcasts a message of type 'Data' to all peers on the network using `base` address.
-    /// Requires a struct which implements PeerList.
+    /// Requires a struct which implements PeerList. Broadcasting to an empty PeerList must return
+    /// `Ok(())` without sending anything. Implementations must serialize `data` once, before any
+    /// peer is contacted, so a serialization failure is returned without opening connections.
+    /// A previously connected peer whose connection is being re-established must not lose the
+    /// message: it is queued and flushed once connected, up to the per-peer capacity (see
+    /// `pending::PendingQueue`). A peer which can't be reached and isn't reconnecting still makes
+    /// the broadcast fail.
     fn broadcast(&mut self, peers: &mut Pl, data: Data) -> Result<()>;
 
     /// Broadcasts a message of type 'Data' to all peers on the network using nth address in `net_addr`.
@@ -92,6 +452,849 @@ where
 
     /// returns quit send channel
     fn get_quit_tx(&self) -> Option<Sender<()>>;
+
+    /// Establishes a connection to the specified peer ahead of the first `send`. The default
+    /// implementation does nothing, which suits connectionless transports.
+    fn connect(&mut self, _peer_address: String) -> Result<()> {
+        Ok(())
+    }
+
+    /// Closes the connection to the specified peer, if any, reported as a
+    /// `TransportEvent::Disconnected` with `DisconnectReason::Graceful`. Later sends connect again
+    /// as needed. The default implementation does nothing, which suits connectionless transports.
+    fn disconnect(&mut self, _peer_address: String) -> Result<()> {
+        Ok(())
+    }
+
+    /// Closes every inbound and outbound connection and empties the connection pool, e.g. for a
+    /// fast topology reset. Unlike `close` the Transport keeps listening, and later sends connect
+    /// again as needed. The default implementation does nothing, which suits connectionless
+    /// transports.
+    fn close_all(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Returns the number of peers with an active inbound or outbound connection. The default
+    /// implementation reports 0, which suits connectionless transports.
+    fn peer_count(&self) -> usize {
+        0
+    }
+
+    /// Hands the established connections and per-peer queues over for a hot reload (see the
+    /// `handoff` module), consuming the Transport and closing its listener. The default
+    /// implementation returns `Error::Unsupported`.
+    fn export_connections(self) -> Result<ConnectionState>
+    where
+        Self: Sized,
+    {
+        Err(errors::Error::Unsupported(String::from("export_connections")).into())
+    }
+
+    /// Takes over connections exported by `export_connections` of another instance, which then
+    /// continue without reconnecting, subject to the `handoff` module's rules on which settings
+    /// may differ. The default implementation accepts an empty state only and returns
+    /// `Error::Unsupported` otherwise.
+    fn import_connections(&mut self, state: ConnectionState) -> Result<()> {
+        if state.is_empty() {
+            return Ok(());
+        }
+        Err(errors::Error::Unsupported(String::from("import_connections")).into())
+    }
+
+    /// Returns true only if there is a live pooled connection to the specified peer, without
+    /// triggering a lazy connect, e.g. to send only to already connected peers. The default
+    /// implementation returns false, which suits connectionless transports.
+    fn is_connected_to(&self, _peer_address: &str) -> bool {
+        false
+    }
+
+    /// Forces a fresh connection to the specified peer: any existing connection is torn down and
+    /// a new one established immediately, returning connect errors. Unlike `disconnect` this
+    /// reconnects, and unlike reconnect-on-send it does so eagerly. The default implementation
+    /// calls `disconnect` followed by `connect`.
+    fn reconnect(&mut self, peer_address: String) -> Result<()> {
+        self.disconnect(peer_address.clone())?;
+        self.connect(peer_address)
+    }
+
+    /// Registers `cb` to be invoked with the remote address on every new inbound or outbound
+    /// connection, e.g. to send a hello or register the peer as soon as a connection is
+    /// established. A lower-level alternative to `subscribe_events`. The default implementation
+    /// never invokes it, which suits connectionless transports.
+    fn set_on_connect(&mut self, _cb: Box<dyn Fn(&str) + Send + Sync>) {}
+
+    /// Returns the wire frame format version spoken by the Transport.
+    fn version(&self) -> u8 {
+        framing::FRAME_VERSION
+    }
+
+    /// Reports what the Transport supports. The default implementation reports this crate's
+    /// frame version, bincode with the `codec` size limit, and no optional features.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            version: self.version(),
+            supports_compression: false,
+            supports_ack: false,
+            max_message_size: codec::DEFAULT_MAX_MESSAGE_SIZE,
+            supported_formats: vec![SerializationFormat::Bincode],
+        }
+    }
+
+    /// Sets the maximum frame size, in bytes, advertised in the handshake of new connections (see
+    /// the `handshake` module). The default implementation ignores it.
+    fn set_max_frame_size(&mut self, _max_frame_size: u32) {}
+
+    /// Returns the maximum frame size negotiated with the specified peer during the handshake,
+    /// i.e. the smaller of both advertised sizes. Frames exceeding it are rejected with
+    /// `Error::MessageTooLarge`. None if not connected or unsupported (the default).
+    fn negotiated_max_frame_size(&self, _peer_address: &str) -> Option<u32> {
+        None
+    }
+
+    /// Sets the bitmask of optional features (`handshake::FEATURE_*`) advertised in the handshake
+    /// of new connections. The default implementation ignores it.
+    fn set_features(&mut self, _features: u32) {}
+
+    /// Returns the feature bitmask negotiated with the specified peer during the handshake, i.e.
+    /// the features both sides advertised, so callers can adapt to what the peer supports. None
+    /// if not connected or unsupported (the default).
+    fn peer_features(&self, _peer_address: &str) -> Option<u32> {
+        None
+    }
+
+    /// Enables connection-level compression with `algo` for new connections (see the
+    /// `compression` module): the whole byte stream of a connection is wrapped in a streaming
+    /// (de)compressor if both peers support it, and left uncompressed otherwise. Wire bytes are
+    /// what `metrics` counts. The default implementation ignores the setting.
+    fn set_stream_compression(&mut self, _algo: Compression) {}
+
+    /// Replaces the codec serializing the messages sent and deserializing the messages received
+    /// (bincode, see `codec::BincodeCodec`, unless changed), e.g. with
+    /// `codec::JsonCodec` (`json` feature). Every peer must use the same codec. Returns
+    /// `Error::Unsupported` if the serialization is fixed (the default).
+    fn set_codec(&mut self, _codec: Box<dyn Codec<Data> + Send + Sync>) -> Result<()> {
+        Err(errors::Error::Unsupported(String::from("set_codec")).into())
+    }
+
+    /// Rotates the session keys of the encrypted connection to the specified peer without
+    /// dropping it, so traffic continues uninterrupted under the new key. Encrypting
+    /// implementations rekey in-band: the initiator sends a rekey frame
+    ///
+    /// [ REKEY ][ epoch: u64, big-endian ][ ephemeral public key ]
+    ///
+    /// encrypted under the current key, the peer answers with its own, and both derive the key
+    /// of the new epoch. Every data frame carries the epoch it was encrypted under, and the
+    /// previous key is kept until the peer's first frame of the new epoch arrives, so frames in
+    /// flight during the rotation still decrypt. The default implementation returns
+    /// `Error::Unsupported`, which suits unencrypted transports.
+    fn rotate_keys(&mut self, _peer_address: String) -> Result<()> {
+        Err(errors::Error::Unsupported(String::from("rotate_keys")).into())
+    }
+
+    /// Connects to every peer in the list using `base` address, e.g. before a consensus round to
+    /// avoid first-message latency spikes. The returned future resolves to the connect result for
+    /// each peer id. Implementations with asynchronous connects can dial the peers concurrently;
+    /// the default implementation calls `connect` for one peer after the other when polled.
+    fn preconnect_all<'a>(
+        &'a mut self,
+        peers: &mut Pl,
+    ) -> Pin<Box<dyn Future<Output = Vec<(Id, Result<()>)>> + 'a>>
+    where
+        Id: 'a,
+    {
+        let targets: Vec<(Id, String)> = peers
+            .iter()
+            .map(|peer| (peer.get_id(), peer.get_base_addr()))
+            .collect();
+        Box::pin(async move {
+            targets
+                .into_iter()
+                .map(|(id, addr)| (id, self.connect(addr)))
+                .collect()
+        })
+    }
+
+    /// Reconciles the connection set with a live PeerList after peers were added or removed at
+    /// runtime: connections to newly-added peers are opened and connections to peers no longer in
+    /// `peers` are closed. Implementors keeping connections must track which peers they are
+    /// connected to; the default implementation just calls `connect` for every peer in the list.
+    fn on_peers_changed(&mut self, peers: &Pl) -> Result<()> {
+        for peer in peers.iter() {
+            self.connect(peer.get_base_addr())?;
+        }
+        Ok(())
+    }
+
+    /// Half-closes the connection to the specified peer: the write half is shut down, signalling
+    /// that no more data will be sent, while data from the peer can still be received. Subsequent
+    /// sends to the peer fail. Datagram transports have no such notion and return
+    /// `Error::Incomplete`, as does the default implementation.
+    fn shutdown_send(&mut self, _peer_address: String) -> Result<()> {
+        Err(errors::Error::Incomplete.into())
+    }
+
+    /// Returns the local address the Transport's listener is bound to, e.g. to learn the port
+    /// picked when binding to port 0. None if not bound or unknown (the default).
+    fn local_addr(&self) -> Option<String> {
+        None
+    }
+
+    /// Returns the address peers should use to reach this Transport. When bound to a wildcard
+    /// address such as "0.0.0.0:0", which peers can't dial, a routable local IP is advertised
+    /// instead (see `addr::advertised_address` for the heuristic). Fails with `Error::NotReady`
+    /// if `local_addr` is unknown.
+    fn advertised_addr(&self) -> Result<String> {
+        match self.local_addr() {
+            Some(local) => addr::advertised_address(&local),
+            None => Err(errors::Error::NotReady.into()),
+        }
+    }
+
+    /// Returns the observed remote socket address of the peer the most recently received message
+    /// came from, or None if unknown (the default). Behind NAT this differs from the peer's
+    /// configured `base_addr` and should be used for reply routing; it is unrelated to the peer id.
+    fn last_remote_addr(&self) -> Option<String> {
+        None
+    }
+
+    /// Returns the number of connections cached for reuse by `send` (see `pool::ConnectionPool`).
+    /// Repeated sends to one address reuse a single connection. The default implementation
+    /// reports 0, which suits connectionless transports.
+    fn pooled_connection_count(&self) -> usize {
+        0
+    }
+
+    /// Returns the traffic counters summed over all connections. The default implementation
+    /// doesn't count and returns zeroes.
+    fn metrics(&self) -> Metrics {
+        Metrics::default()
+    }
+
+    /// Returns the traffic counters of the connection to the specified peer, or None if unknown
+    /// (the default).
+    fn peer_metrics(&self, _peer_address: &str) -> Option<Metrics> {
+        None
+    }
+
+    /// Returns the time since the Transport was created. The default implementation doesn't
+    /// track it and returns zero.
+    fn uptime(&self) -> Duration {
+        Duration::from_secs(0)
+    }
+
+    /// Returns the live connections with their traffic counters. The default implementation
+    /// returns none, which suits connectionless transports.
+    fn connections(&self) -> Vec<ConnectionInfo> {
+        Vec::new()
+    }
+
+    /// Returns a serializable snapshot of `uptime`, `peer_count`, `metrics` and `connections`,
+    /// e.g. to expose at a monitoring endpoint.
+    fn stats(&self) -> TransportStats {
+        TransportStats {
+            uptime: self.uptime(),
+            peer_count: self.peer_count(),
+            total: self.metrics(),
+            per_peer: self.connections(),
+        }
+    }
+
+    /// Zeroes all traffic counters, global and per connection, e.g. for rolling metrics windows.
+    /// Only the counters are affected; active connections are left untouched.
+    fn reset_metrics(&mut self) {}
+
+    /// Returns a channel on which connection events are reported, or None if the implementation
+    /// doesn't report events (the default).
+    fn subscribe_events(&mut self) -> Option<Receiver<TransportEvent>> {
+        None
+    }
+
+    /// Sets the idle timeout: connections without any traffic for `timeout` are closed
+    /// automatically, emitting a `TransportEvent::Disconnected` with `DisconnectReason::Timeout`,
+    /// and re-established on the next send. `None` disables reaping. This complements keepalive,
+    /// which keeps connections alive; this reaps the truly idle ones. The default implementation
+    /// ignores the setting.
+    fn set_idle_timeout(&mut self, _timeout: Option<Duration>) {}
+
+    /// Caps the simultaneous in-flight sends of this Transport with `limiter` (see the
+    /// `concurrency` module); sharing one limiter between transports caps their sends together.
+    /// The default implementation ignores the limiter.
+    fn set_send_limiter(&mut self, _limiter: SendLimiter) {}
+
+    /// Enables a per-peer circuit breaker (see the `breaker` module): after `failures`
+    /// consecutive failed sends to a peer, sends to it fail immediately with
+    /// `Error::CircuitOpen` for `cooldown`, after which one send is attempted again. The default
+    /// implementation ignores it.
+    fn set_circuit_breaker(&mut self, _failures: u32, _cooldown: Duration) {}
+
+    /// Batches outbound messages to the same peer sent within `window` into one write, each
+    /// still individually framed so the receiver splits them back into separate messages. This
+    /// trades up to `window` of extra latency for throughput when many small messages are sent in
+    /// bursts. None disables coalescing. The default implementation ignores the setting.
+    fn set_coalesce_window(&mut self, _window: Option<Duration>) {}
+
+    /// Sets the timeout of a single write on a connection: a write which makes no progress for
+    /// `timeout`, e.g. because the receiver stopped reading and its window is full, aborts with
+    /// `Error::Timeout`, freeing the send path. Unlike a timeout for the whole send it doesn't
+    /// cover connecting. None disables the timeout. The default implementation ignores it.
+    fn set_write_timeout(&mut self, _timeout: Option<Duration>) {}
+
+    /// Sets how often a polling receive loop, e.g. a fallback on platforms without epoll, wakes
+    /// when no events are ready. A shorter interval lowers latency at the cost of CPU time.
+    /// Event-driven implementations can ignore it, as does the default implementation.
+    fn set_poll_interval(&mut self, _interval: Duration) {}
+
+    /// Sends a message of type 'Data' to the specified peer with the given `priority`: queued
+    /// outbound messages of higher priority are sent first, subject to the weights set with
+    /// `set_priority_weights`. The default implementation ignores the priority and calls `send`.
+    fn send_with_priority(
+        &mut self,
+        peer_address: String,
+        data: Data,
+        _priority: Priority,
+    ) -> Result<()> {
+        self.send(peer_address, data)
+    }
+
+    /// Sets the weights of the weighted round-robin scheduling of queued outbound messages (see
+    /// `priority::WeightedQueue`): per round up to `high` High, `normal` Normal and `low` Low
+    /// messages are sent, so lower priorities keep a guaranteed share of the send bandwidth
+    /// under a flood of High traffic. Weights below 1 count as 1. Defaults to
+    /// `priority::DEFAULT_PRIORITY_WEIGHTS`; the default implementation ignores the setting.
+    fn set_priority_weights(&mut self, _high: u32, _normal: u32, _low: u32) {}
+
+    /// Enables sequence numbers on outbound frames and gap detection on inbound ones (see the
+    /// `sequence` module), for protocols needing to know about lost frames. A gap is yielded by
+    /// `result_stream` as `Error::SequenceGap` instead of being skipped silently. Only meaningful
+    /// on ordered transports (see `preserves_order`). The default implementation ignores it.
+    fn set_sequence_tracking(&mut self, _enabled: bool) {}
+
+    /// Sets how many frames are queued per peer while its connection is being (re)established
+    /// (see `pending::PendingQueue`); sends beyond it fail with `Error::QueueFull`. Defaults to
+    /// `pending::DEFAULT_PENDING_CAPACITY`; the default implementation ignores the setting.
+    fn set_pending_capacity(&mut self, _capacity: usize) {}
+
+    /// Stops reading from connections, e.g. during state transfer, so the stream delivers no new
+    /// messages while connections stay open. Unread data is buffered at the OS/TCP level, not in
+    /// memory: once the socket buffers fill, TCP flow control makes the senders block or, with
+    /// `try_send`, report full buffers. Not supported by default.
+    fn pause(&mut self) -> Result<()> {
+        Err(errors::Error::Unsupported(String::from("pause")).into())
+    }
+
+    /// Resumes reading after `pause`; messages buffered meanwhile are delivered in order. Not
+    /// supported by default.
+    fn resume(&mut self) -> Result<()> {
+        Err(errors::Error::Unsupported(String::from("resume")).into())
+    }
+
+    /// Limits how fast the receive loop reads from sockets to `bytes_per_sec`, e.g. using
+    /// `rate::RateLimiter`. Once the budget is exceeded reading pauses, applying backpressure to
+    /// the senders, so a single peer cannot saturate ingress. The default implementation ignores
+    /// the setting.
+    fn set_inbound_rate_limit(&mut self, _bytes_per_sec: u64) {}
+
+    /// Chooses what `Drop` does with sends still buffered: with `flush` set, `Drop` blocks
+    /// briefly to flush them to their peers; otherwise they are discarded for a fast exit. The
+    /// default is to discard, so `Drop` never blocks unless asked to. The default implementation
+    /// ignores the setting, suiting transports which don't buffer sends.
+    fn set_flush_on_drop(&mut self, _flush: bool) {}
+
+    /// Presents this Transport as a Transport of another data type `D2`: outbound payloads are
+    /// converted with `to`, inbound payloads with `from`. Lets one transport instance serve typed
+    /// sub-protocols.
+    fn map_data<D2, F, G>(self, to: F, from: G) -> MapData<Self, Data, D2, F, G>
+    where
+        Self: Sized,
+        F: Fn(D2) -> Data,
+        G: Fn(Data) -> D2,
+    {
+        MapData::new(self, to, from)
+    }
+
+    /// Wraps this Transport so that every message sent, broadcast or received is also cloned to
+    /// `tx`, tagged with its `Direction`, e.g. for a network monitor. Delivery is unaffected.
+    fn observe(self, tx: Sender<(Direction, Data)>) -> Observed<Self, Data>
+    where
+        Self: Sized,
+        Data: Clone,
+    {
+        Observed::new(self, tx)
+    }
+
+    /// Returns a handle whose `trigger` makes the stream yield `None`, waking every pending
+    /// `next()`, for clean task teardown. None if unsupported (the default).
+    fn shutdown_handle(&self) -> Option<ShutdownHandle> {
+        None
+    }
+
+    /// Closes the Transport. The default implementation signals the quit channel returned by
+    /// `get_quit_tx`, if any.
+    fn close(&mut self) -> Result<()> {
+        if let Some(tx) = self.get_quit_tx() {
+            // A disconnected receiver means the transport has already quit.
+            let _ = tx.send(());
+        }
+        Ok(())
+    }
+
+    /// Sets the resolver used to map peer ids to addresses for `send_by_id`. Implementations
+    /// supporting id based sending store it and consult it in `resolve`; the default discards it.
+    fn set_resolver(&mut self, _resolver: Resolver<Id>) {}
+
+    /// Resolves a peer id into an address using the resolver set by `set_resolver`.
+    fn resolve(&self, _id: &Id) -> Option<String> {
+        None
+    }
+
+    /// Sends a message of type 'Data' to the peer with the given id, resolving its address via
+    /// `resolve`. Returns `Error::PeerNotFound` when the id does not resolve.
+    fn send_by_id(&mut self, id: &Id, data: Data) -> Result<()> {
+        match self.resolve(id) {
+            Some(peer_address) => self.send(peer_address, data),
+            None => Err(errors::Error::PeerNotFound(id.to_string()).into()),
+        }
+    }
+
+    /// Sends a message of type 'Data' to the specified peer on a logical `channel`; the channel id
+    /// is sent as a frame prefix (see the `channel` module). The default implementation only
+    /// supports `DEFAULT_CHANNEL`, which it sends as a plain `send`.
+    fn send_on_channel(&mut self, peer_address: String, channel: u16, data: Data) -> Result<()> {
+        if channel == DEFAULT_CHANNEL {
+            return self.send(peer_address, data);
+        }
+        Err(errors::Error::Unsupported(String::from("send_on_channel")).into())
+    }
+
+    /// Returns a stream of the messages received on `channel` only. Implementations buffer
+    /// messages of other channels until a stream for their channel is polled. The default
+    /// implementation returns the whole Transport stream for `DEFAULT_CHANNEL` and an empty
+    /// stream for any other channel.
+    fn stream_for_channel(&mut self, channel: u16) -> Pin<Box<dyn Stream<Item = Data> + '_>> {
+        if channel == DEFAULT_CHANNEL {
+            Box::pin(self)
+        } else {
+            Box::pin(stream::empty())
+        }
+    }
+
+    /// Sends already serialized bytes to the specified peer, bypassing the `Serialize` step and
+    /// using only the transport's framing. Mixing raw and typed messages on one connection
+    /// requires both sides to share the frame format. Not supported by default.
+    fn send_raw(&mut self, _peer_address: String, _bytes: Vec<u8>) -> Result<()> {
+        Err(errors::Error::Unsupported(String::from("send_raw")).into())
+    }
+
+    /// Serializes `data` into the bytes `send_raw` sends as that message, with the transport's
+    /// codec. The broadcast helpers use it to serialize a message once for all peers, falling back
+    /// to a `send` per peer where it is unsupported. Not supported by default.
+    fn encode(&self, _data: &Data) -> Result<Vec<u8>> {
+        Err(errors::Error::Unsupported(String::from("encode")).into())
+    }
+
+    /// Sends a message of type 'Data' to the specified peer, serializing it from a borrow so the
+    /// caller keeps the value without cloning it. Not supported by default.
+    fn send_ref(&mut self, _peer_address: String, _data: &Data) -> Result<()> {
+        Err(errors::Error::Unsupported(String::from("send_ref")).into())
+    }
+
+    /// Broadcasts a message of type 'Data' to all peers using `base` address, serializing it once
+    /// from a borrow so the caller keeps the value without cloning it. Not supported by default.
+    fn broadcast_ref(&mut self, _peers: &mut Pl, _data: &Data) -> Result<()> {
+        Err(errors::Error::Unsupported(String::from("broadcast_ref")).into())
+    }
+
+    /// Returns a stream of the raw bytes of received messages, bypassing the `Deserialize` step.
+    /// Not supported by default.
+    fn raw_stream(&mut self) -> Result<Pin<Box<dyn Stream<Item = Vec<u8>> + '_>>> {
+        Err(errors::Error::Unsupported(String::from("raw_stream")).into())
+    }
+
+    /// Sends a message of type 'Data' to the specified peer, tagged with a message id unique to
+    /// this Transport instance (see the `tracking` module), and returns that id. Ids increase
+    /// monotonically and concurrent sends never share one. Not supported by default.
+    fn send_tracked(&mut self, _peer_address: String, _data: Data) -> Result<u64> {
+        Err(errors::Error::Unsupported(String::from("send_tracked")).into())
+    }
+
+    /// Returns a stream of the tracked messages received, together with their message ids. The
+    /// default implementation returns an empty stream.
+    fn tracked_stream(&mut self) -> Pin<Box<dyn Stream<Item = (u64, Data)> + '_>> {
+        Box::pin(stream::empty())
+    }
+
+    /// Broadcasts a message of type 'Data' to all peers using `base` address, like `broadcast`,
+    /// but only for `Data` types which opted into `BroadcastSafe`, ruling out oversized messages
+    /// at compile time (see the `safe` module).
+    fn broadcast_safe(&mut self, peers: &mut Pl, data: Data) -> Result<()>
+    where
+        Data: BroadcastSafe,
+    {
+        self.broadcast(peers, data)
+    }
+
+    /// Broadcasts a message of type 'Data' to all peers using `base` address without blocking on
+    /// any of them, as `try_send` does: peers whose send buffers are full are skipped and their
+    /// ids returned, so a slow peer cannot stall a real-time loop. The default implementation
+    /// delegates to `broadcast` and skips nobody.
+    fn try_broadcast(&mut self, peers: &mut Pl, data: Data) -> Result<Vec<Id>> {
+        self.broadcast(peers, data)?;
+        Ok(Vec::new())
+    }
+
+    /// Broadcasts a message of type 'Data' to all peers using `base` address, handling failed
+    /// sends according to `policy`. `FailFast` behaves like `broadcast`. With `BestEffort` a
+    /// message which fails to serialize is returned as an error before anything is sent.
+    fn broadcast_with_policy(
+        &mut self,
+        peers: &mut Pl,
+        data: Data,
+        policy: BroadcastPolicy,
+    ) -> Result<()>
+    where
+        Data: Clone,
+    {
+        if policy == BroadcastPolicy::FailFast {
+            return self.broadcast(peers, data);
+        }
+        let encoded = encode_once(&*self, &data)?;
+        let mut failures: Vec<String> = Vec::new();
+        for peer in peers.iter() {
+            let addr = peer.get_base_addr();
+            if let Err(e) = send_encoded(self, addr.clone(), &encoded, &data) {
+                failures.push(format!("{}: {}", addr, e));
+            }
+        }
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(errors::Error::MultipleErrors(failures).into())
+        }
+    }
+
+    /// Broadcasts a message of type 'Data' to all peers using `base` address, attempting every
+    /// peer, and returns how many sends were attempted, succeeded and failed, e.g. so callers can
+    /// require `succeeded > 0`. Only a message which fails to serialize is returned as an error,
+    /// before anything is sent.
+    fn broadcast_reporting(&mut self, peers: &mut Pl, data: Data) -> Result<BroadcastOutcome>
+    where
+        Data: Clone,
+    {
+        let encoded = encode_once(&*self, &data)?;
+        if encoded.is_none() {
+            // Check up front that the message serializes, as the sends below count any error.
+            codec::serialized_size(&data)?;
+        }
+        let mut outcome = BroadcastOutcome::default();
+        for peer in peers.iter() {
+            outcome.attempted += 1;
+            match send_encoded(self, peer.get_base_addr(), &encoded, &data) {
+                Ok(()) => outcome.succeeded += 1,
+                Err(_) => outcome.failed += 1,
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Broadcasts a message of type 'Data' to each of `addrs`, for addresses which don't come
+    /// from a PeerList. Stops at, and returns, the first send error.
+    fn broadcast_to<I>(&mut self, addrs: I, data: Data) -> Result<()>
+    where
+        I: IntoIterator<Item = String>,
+        Data: Clone,
+    {
+        let encoded = encode_once(&*self, &data)?;
+        for addr in addrs {
+            send_encoded(self, addr, &encoded, &data)?;
+        }
+        Ok(())
+    }
+
+    /// Broadcasts each of `items` to all peers using `base` address, in order. Items which fail
+    /// to serialize are skipped rather than aborting the batch; returns the number of items
+    /// broadcast. Send failures still abort with their error.
+    fn broadcast_batch(&mut self, peers: &mut Pl, items: Vec<Data>) -> Result<usize> {
+        let mut sent = 0;
+        for item in items {
+            match self.broadcast(peers, item) {
+                Ok(()) => sent += 1,
+                Err(e) => match e.downcast_ref::<errors::Error>() {
+                    Some(errors::Error::Bincode(_)) => {}
+                    #[cfg(feature = "json")]
+                    Some(errors::Error::Json(_)) => {}
+                    _ => return Err(e),
+                },
+            }
+        }
+        Ok(sent)
+    }
+
+    /// Broadcasts a message of type 'Data' to all peers using `base` address, sending only one
+    /// copy to each endpoint: peers whose addresses normalize to the same endpoint (see
+    /// `addr::normalize_address`), e.g. several ids hosted by one node, are sent to once.
+    /// Addresses which don't resolve are compared as written.
+    fn broadcast_unique_addr(&mut self, peers: &mut Pl, data: Data) -> Result<()>
+    where
+        Data: Clone,
+    {
+        let encoded = encode_once(&*self, &data)?;
+        let mut seen: HashSet<String> = HashSet::new();
+        for peer in peers.iter() {
+            let addr = peer.get_base_addr();
+            if seen.insert(addr::normalize_address(&addr).unwrap_or_else(|_| addr.clone())) {
+                send_encoded(self, addr, &encoded, &data)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Broadcasts a message of type 'Data' using `base` address to the peers for which `pred`
+    /// returns true, e.g. for region-scoped or role-scoped broadcasts.
+    fn broadcast_filtered(
+        &mut self,
+        peers: &mut Pl,
+        data: Data,
+        pred: impl Fn(&Pl::P) -> bool,
+    ) -> Result<()>
+    where
+        Data: Clone,
+    {
+        let encoded = encode_once(&*self, &data)?;
+        for peer in peers.iter().filter(|peer| pred(peer)) {
+            send_encoded(self, peer.get_base_addr(), &encoded, &data)?;
+        }
+        Ok(())
+    }
+
+    /// Broadcasts a message of type 'Data' to all peers using `base` address, sending to the peers
+    /// in ascending order of their ids rather than in PeerList order, e.g. for reproducible test
+    /// snapshots.
+    fn broadcast_ordered(&mut self, peers: &mut Pl, data: Data) -> Result<()>
+    where
+        Id: Ord,
+        Data: Clone,
+    {
+        let mut targets: Vec<(Id, String)> = peers
+            .iter()
+            .map(|peer| (peer.get_id(), peer.get_base_addr()))
+            .collect();
+        targets.sort_by(|a, b| a.0.cmp(&b.0));
+        let encoded = encode_once(&*self, &data)?;
+        for (_, addr) in targets {
+            send_encoded(self, addr, &encoded, &data)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the address the last connection to the specified peer actually reached, e.g. the
+    /// resolved socket address, as recorded by the send path once a send succeeded. Returns None
+    /// if no send to the peer has succeeded yet. The default implementation records nothing and
+    /// always returns None.
+    fn negotiated_addr(&self, _peer_address: &str) -> Option<String> {
+        None
+    }
+
+    /// Broadcasts a message of type 'Data' to all peers using `base` address, then records the
+    /// address each peer was reached at (see `negotiated_addr`) as the peer's nth `net_addr` via
+    /// `set_net_addr`, keeping the PeerList accurate for multi-homed peers. Peers which were never
+    /// reached keep their nth `net_addr`. Addresses are recorded even if the broadcast fails, in
+    /// which case its error is returned afterwards.
+    fn broadcast_recording(&mut self, peers: &mut Pl, n: usize, data: Data) -> Result<()>
+    where
+        Error: Fail,
+    {
+        let result = self.broadcast(peers, data);
+        for peer in peers.iter_mut() {
+            if let Some(addr) = self.negotiated_addr(&peer.get_base_addr()) {
+                peer.set_net_addr(n, addr)?;
+            }
+        }
+        result
+    }
+
+    /// Sends a message of type 'Data' together with per-message `headers` to the specified peer.
+    /// Headers are serialized as a prefix block ahead of the payload (see the `headers` module).
+    /// The default implementation only supports empty headers, which it sends as a plain `send`.
+    fn send_with_headers(
+        &mut self,
+        peer_address: String,
+        headers: Headers,
+        data: Data,
+    ) -> Result<()> {
+        if headers.is_empty() {
+            return self.send(peer_address, data);
+        }
+        Err(errors::Error::Unsupported(String::from("send_with_headers")).into())
+    }
+
+    /// Broadcasts a message of type 'Data' to all peers using `base` address over a spanning tree
+    /// (see the `tree` module): it is sent to at most `fanout` peers only, tagged with forwarding
+    /// headers, and every receiver passes it on with `forward_tree`. This bounds the per-node
+    /// fan-out in large clusters, at the cost of extra hops and of delivery depending on the
+    /// forwarding nodes. All nodes must share the same peer list. Requires `local_addr` and
+    /// `send_with_headers`; fails with `Error::Unsupported` otherwise.
+    fn broadcast_tree(&mut self, peers: &mut Pl, data: Data, fanout: usize) -> Result<()>
+    where
+        Data: Clone,
+    {
+        let origin = match self.local_addr() {
+            Some(origin) => origin,
+            None => return Err(errors::Error::Unsupported(String::from("broadcast_tree")).into()),
+        };
+        let members = tree::members(peers.iter().map(|peer| peer.get_base_addr()), &origin);
+        let ttl = members.len() as u32;
+        for addr in tree::children(&members, None, fanout.max(1)) {
+            self.send_with_headers(addr.clone(), tree::headers(&origin, ttl), data.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Forwards a message received from `headers_stream` with the given `headers` to this node's
+    /// children in the spanning tree of `broadcast_tree`, with the hop count decremented. Does
+    /// nothing if the message carries no forwarding headers, has run out of hops or this node
+    /// isn't part of the tree. `fanout` must match the one used by the origin.
+    fn forward_tree(
+        &mut self,
+        peers: &mut Pl,
+        headers: &Headers,
+        data: Data,
+        fanout: usize,
+    ) -> Result<()>
+    where
+        Data: Clone,
+    {
+        let (origin, ttl) = match tree::parse(headers) {
+            Some((_, 0)) | None => return Ok(()),
+            Some(forwarding) => forwarding,
+        };
+        let local = match self.local_addr() {
+            Some(local) => local,
+            None => return Err(errors::Error::Unsupported(String::from("forward_tree")).into()),
+        };
+        let members = tree::members(peers.iter().map(|peer| peer.get_base_addr()), &origin);
+        let index = match members.iter().position(|addr| *addr == local) {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+        for addr in tree::children(&members, Some(index), fanout.max(1)) {
+            self.send_with_headers(addr.clone(), tree::headers(&origin, ttl - 1), data.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Moves the Transport to a new thread which drives its stream to completion, calling
+    /// `handler` for every received message, and returns once the stream ends, i.e. once the
+    /// Transport is closed (e.g. through `shutdown_handle` or the quit channel of
+    /// `get_quit_tx`, obtained before calling this). Runtime-agnostic: the stream is driven with
+    /// `block_on` on a plain std thread.
+    fn run_receive_loop<F>(self, mut handler: F) -> JoinHandle<()>
+    where
+        Self: Sized + Send + 'static,
+        F: FnMut(Data) + Send + 'static,
+    {
+        let mut trn = self;
+        thread::spawn(move || {
+            block_on(async {
+                while let Some(data) = trn.next().await {
+                    handler(data);
+                }
+            })
+        })
+    }
+
+    /// Returns a future waiting up to `dur` for the next message (see `timer::recv_timeout`). It
+    /// resolves to `Ok(None)` if none arrived in time, so a message lost on the wire can't hang
+    /// the caller as awaiting `next()` would; a timeout is not an error here. It resolves to
+    /// `Error::Incomplete` if the stream ended before a message.
+    fn recv_timeout(&mut self, dur: Duration) -> RecvTimeout<'_, Self>
+    where
+        Self: Sized,
+    {
+        timer::recv_timeout(self, dur)
+    }
+
+    /// Returns a stream of received messages borrowing the Transport, so the receive half can be
+    /// handed to a consumer without moving the Transport. The stream holds a mutable borrow: it
+    /// can be awaited as long as needed, but the Transport can't send while the stream is alive.
+    /// Once the stream is dropped (e.g. at the end of the consumer's scope) the Transport is
+    /// usable again, and messages not read through the stream remain queued on it.
+    fn stream(&mut self) -> Pin<Box<dyn Stream<Item = Data> + '_>> {
+        Box::pin(self)
+    }
+
+    /// Returns a stream of received messages which also yields receive errors (e.g. decode or
+    /// I/O errors) inline, instead of skipping the frame or ending the stream, so protocols can
+    /// decide whether an error is fatal. Implementations can build it on `decode::decode_results`.
+    /// The default implementation yields every message of the Transport stream as `Ok`.
+    fn result_stream(&mut self) -> Pin<Box<dyn Stream<Item = Result<Data>> + '_>> {
+        Box::pin(self.map(Ok))
+    }
+
+    /// Returns a stream of received messages together with their headers. The default
+    /// implementation yields every message from the Transport stream with empty headers.
+    fn headers_stream(&mut self) -> Pin<Box<dyn Stream<Item = (Headers, Data)> + '_>> {
+        Box::pin(self.map(|data| (Headers::new(), data)))
+    }
+}
+
+// Serializes `data` once with `Transport::encode` for sending it to several peers; None if the
+// transport doesn't support `encode`.
+fn encode_once<T, Id, Data, Error, Pl>(trn: &T, data: &Data) -> Result<Option<Vec<u8>>>
+where
+    T: Transport<Id, Data, Error, Pl> + ?Sized,
+    Id: PeerId,
+    Pl: PeerList<Id, Error>,
+    Data: Serialize + DeserializeOwned,
+{
+    match trn.encode(data) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) => match e.downcast_ref::<errors::Error>() {
+            Some(errors::Error::Unsupported(_)) => Ok(None),
+            _ => Err(e),
+        },
+    }
+}
+
+// Sends a message to `peer_address`: the bytes serialized by `encode_once` with `send_raw` if
+// there are any, `data` with `send` otherwise.
+fn send_encoded<T, Id, Data, Error, Pl>(
+    trn: &mut T,
+    peer_address: String,
+    encoded: &Option<Vec<u8>>,
+    data: &Data,
+) -> Result<()>
+where
+    T: Transport<Id, Data, Error, Pl> + ?Sized,
+    Id: PeerId,
+    Pl: PeerList<Id, Error>,
+    Data: Serialize + DeserializeOwned + Clone,
+{
+    match encoded {
+        Some(bytes) => trn.send_raw(peer_address, bytes.clone()),
+        None => trn.send(peer_address, data.clone()),
+    }
+}
+
+/// ThreadSafeTransport marks a `Transport` which may be moved to and shared between threads, for
+/// example behind an `Arc<Mutex<_>>`. `Transport` itself makes no such promise; this sub-trait is
+/// implemented automatically for every `Transport` that is also `Send + Sync`, so generic code can
+/// state the concurrency contract it relies on.
+pub trait ThreadSafeTransport<Id, Data, Error, Pl>:
+    Transport<Id, Data, Error, Pl> + Send + Sync
+where
+    Id: PeerId,
+    Pl: PeerList<Id, Error>,
+    Data: Serialize + DeserializeOwned,
+{
+}
+
+impl<Id, Data, Error, Pl, T> ThreadSafeTransport<Id, Data, Error, Pl> for T
+where
+    Id: PeerId,
+    Pl: PeerList<Id, Error>,
+    Data: Serialize + DeserializeOwned,
+    T: Transport<Id, Data, Error, Pl> + Send + Sync,
+{
 }
 
 /// Transport sender trait allows us to create multiple `Data` sending only services.
@@ -110,6 +1313,13 @@ where
     /// Sends a message of type 'Data' to the specified peer (as specified by `peer_address`)
     fn send(&mut self, peer_address: String, data: Data) -> Result<()>;
 
+    /// Sends a message of type 'Data' to the specified peer, returning a future which resolves
+    /// once the message was sent, as `Transport::send_fut` does. The default implementation
+    /// performs the synchronous `send` and returns its result as a ready future.
+    fn send_fut(&mut self, peer_address: String, data: Data) -> SendFut<'_> {
+        Box::pin(future::ready(self.send(peer_address, data)))
+    }
+
     /// Broadcasts a message of type 'Data' to all peers on the network using `base` address.
     /// Requires a struct which implements PeerList.
     fn broadcast(&mut self, peers: &mut Pl, data: Data) -> Result<()>;
@@ -117,27 +1327,220 @@ where
     /// Broadcasts a message of type 'Data' to all peers on the network using nth address in `net_addr`.
     /// Requires a struct which implements PeerList.
     fn broadcast_n(&mut self, peers: &mut Pl, n: usize, data: Data) -> Result<()>;
+
+    /// Maximum number of concurrent sends performed by `broadcast_parallel`.
+    fn broadcast_concurrency(&self) -> usize {
+        DEFAULT_BROADCAST_CONCURRENCY
+    }
+
+    /// Broadcasts a message of type 'Data' to all peers using `base` address, sending to the peers
+    /// concurrently: a send future is created per peer with `send_fut` on a clone of this sender,
+    /// and at most `broadcast_concurrency` of them run at once, so large peer lists don't open
+    /// thousands of simultaneous connections. Clones must share the sender's connections and
+    /// settings. Sends only overlap if `send_fut` is truly asynchronous; with the default
+    /// `send_fut` they complete one after the other. The returned future resolves once every send
+    /// finished, with `Error::MultipleErrors` listing the failed ones, if any.
+    fn broadcast_parallel(
+        &mut self,
+        peers: &mut Pl,
+        data: Data,
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send>>
+    where
+        Self: Clone + Send + 'static,
+        Data: Clone + Send + 'static,
+    {
+        let sends: Vec<_> = peers
+            .iter()
+            .map(|peer| {
+                let addr = peer.get_base_addr();
+                let mut sender = self.clone();
+                let data = data.clone();
+                async move {
+                    let result = sender.send_fut(addr.clone(), data).await;
+                    result.map_err(|e| format!("{}: {}", addr, e))
+                }
+            })
+            .collect();
+        let concurrency = self.broadcast_concurrency().max(1);
+        Box::pin(async move {
+            let failures: Vec<String> = stream::iter(sends)
+                .buffer_unordered(concurrency)
+                .filter_map(|result| future::ready(result.err()))
+                .collect()
+                .await;
+            if failures.is_empty() {
+                Ok(())
+            } else {
+                Err(errors::Error::MultipleErrors(failures).into())
+            }
+        })
+    }
+
+    /// Broadcasts a message of type 'Data' to all peers using `base` address, returning the
+    /// broadcast future together with an `AbortHandle` cancelling it mid-flight, e.g. when a new
+    /// round supersedes it. Peers are sent to one at a time through this sender, yielding between
+    /// sends, so aborting stops any further sends from being initiated; sends already made can't
+    /// be undone. An aborted broadcast resolves to `Error::Aborted`.
+    fn broadcast_abortable<'a>(
+        &'a mut self,
+        peers: &mut Pl,
+        data: Data,
+    ) -> (
+        Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>,
+        AbortHandle,
+    )
+    where
+        Self: Sized + Send,
+        Data: Clone + Send + 'a,
+    {
+        let (handle, registration) = AbortHandle::new_pair();
+        let addrs: Vec<String> = peers.iter().map(|peer| peer.get_base_addr()).collect();
+        let broadcast = async move {
+            for addr in addrs {
+                self.send(addr, data.clone())?;
+                // Yield, giving the Abortable wrapper a chance to stop us before the next send.
+                let mut yielded = false;
+                future::poll_fn(|cx| {
+                    if yielded {
+                        Poll::Ready(())
+                    } else {
+                        yielded = true;
+                        cx.waker().wake_by_ref();
+                        Poll::Pending
+                    }
+                })
+                .await;
+            }
+            Ok(())
+        };
+        let abortable = Abortable::new(broadcast, registration);
+        let future = async move {
+            match abortable.await {
+                Ok(result) => result,
+                Err(_) => Err(errors::Error::Aborted.into()),
+            }
+        };
+        (Box::pin(future), handle)
+    }
 }
 
 /// Transport receiver trait allows us to create multiple `Data` receiving only services.
 /// `TransportReceiver` trait requires the same 4 parameter types as `Transport` trait above.
 ///
-/// NOTE: `TransportReceiver` must implement Stream trait from async/.await framework.
-pub trait TransportReceiver<Id, Data, Error, Pl>: Stream<Item = Data> + Drop + Unpin
+/// NOTE: `TransportReceiver` must implement Stream trait from async/.await framework, following
+/// the same termination contract as `Transport`.
+pub trait TransportReceiver<Id, Data, Error, Pl>: FusedStream<Item = Data> + Drop + Unpin
 where
     Id: PeerId,
     Pl: PeerList<Id, Error>,
     Data: DeserializeOwned,
 {
     /// Creates a new Transport type using a preset configuration type.
-    fn new(set_bind_net_addr: String) -> Result<Self>
+    fn new<C>(cfg: C) -> Result<Self>
     where
+        C: TransportConfiguration,
         Self: Sized;
 
     /// returns quit send channel
     fn get_quit_tx(&self) -> Option<Sender<()>>;
+
+    /// Returns a handle whose `trigger` makes the stream yield `None`, waking every pending
+    /// `next()`, for clean task teardown. None if unsupported (the default).
+    fn shutdown_handle(&self) -> Option<ShutdownHandle> {
+        None
+    }
+
+    /// Returns a future waiting up to `dur` for the next message (see `timer::recv_timeout`). It
+    /// resolves to `Ok(None)` if none arrived in time, so a message lost on the wire can't hang
+    /// the caller as awaiting `next()` would; a timeout is not an error here. It resolves to
+    /// `Error::Incomplete` if the stream ended before a message.
+    fn recv_timeout(&mut self, dur: Duration) -> RecvTimeout<'_, Self>
+    where
+        Self: Sized,
+    {
+        timer::recv_timeout(self, dur)
+    }
 }
 
 // Imports
+pub mod addr;
+#[cfg(feature = "debug")]
+pub mod audit;
+pub mod boxed;
+pub mod breaker;
+pub mod bridge;
+pub mod channel;
+pub mod codec;
+pub mod compression;
+pub mod concurrency;
+pub mod decode;
+pub mod dynamic;
 pub mod errors;
+pub mod events;
+pub mod format;
+pub mod framing;
 pub mod generic_test;
+pub mod handoff;
+pub mod handshake;
+pub mod headers;
+#[cfg(feature = "wasm")]
+pub mod local;
+pub mod map;
+pub mod memory;
+pub mod merge;
+pub mod metrics;
+pub mod null;
+pub mod observe;
+pub mod pending;
+pub mod pool;
+pub mod priority;
+pub mod rate;
+pub mod retry;
+pub mod rpc;
+pub mod safe;
+pub mod scheduler;
+pub mod semaphore;
+pub mod sequence;
+pub mod shutdown;
+pub mod suite;
+pub mod timer;
+pub mod tracking;
+pub mod tree;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generic_test::{Data, Id, TestPeerList};
+    use crate::memory::{MemoryNetwork, MemoryTransport};
+
+    /*
+        Tests the transport configurations: DefaultConfiguration must start from the crate defaults
+        and keep the settings made to it, and a plain String must act as a bind address only
+        configuration.
+    */
+    #[test]
+    fn configuration_test() -> Result<()> {
+        let mut cfg = DefaultConfiguration::new(String::from("127.0.0.1:9000"))?;
+        assert_eq!("127.0.0.1:9000", cfg.bind_addr());
+        assert_eq!(crate::DEFAULT_ACCEPT_BACKLOG, cfg.accept_backlog());
+        cfg.set_bind_addr(String::from("127.0.0.1:9001"));
+        cfg.send_buffer_size = Some(4096);
+        assert_eq!("127.0.0.1:9001", cfg.bind_addr);
+        assert_eq!(Some(4096), cfg.send_buffer_size());
+
+        let addr = <String as TransportConfiguration>::new(String::from("127.0.0.1:9002"))?;
+        assert_eq!("127.0.0.1:9002", addr.bind_addr());
+        assert_eq!(None, addr.recv_buffer_size());
+
+        // Transports accept either configuration.
+        let _network = MemoryNetwork::new().enter();
+        let t: MemoryTransport<Data> =
+            Transport::<Id, Data, Error, TestPeerList<Id>>::new(cfg.clone())?;
+        assert_eq!(cfg.bind_addr, t.addr());
+        drop(t);
+        let t: MemoryTransport<Data> = Transport::<Id, Data, Error, TestPeerList<Id>>::new(addr)?;
+        assert_eq!("127.0.0.1:9002", t.addr());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+/// # Fantom Libtransport/local
+///
+/// This file defines the `LocalTransport` trait, a single-threaded counterpart of the `Transport`
+/// trait intended for targets such as `wasm32-unknown-unknown` where OS threads are unavailable.
+/// It mirrors the required methods of `Transport` (`new`, `send`, `broadcast`, `broadcast_n` and
+/// `get_quit_tx`) but places no `Send`/`Sync` requirements on the implementor, so it can hold
+/// `Rc`/`RefCell` state and `?Send` futures. The provided helpers of `Transport` are not part of
+/// it.
+///
+/// Only available with the `wasm` feature enabled.
+use crate::errors::Result;
+use crate::TransportConfiguration;
+use futures::stream::Stream;
+use libcommon_rs::peer::{PeerId, PeerList};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::Unpin;
+use std::sync::mpsc::Sender;
+
+/// LocalTransport trait has the required methods of `Transport` but may be implemented by `!Send`
+/// types.
+///
+/// The LocalTransport trait requires 4 parameter types to work:
+/// Id: The peer's ID type.
+/// Data: The data being transmitted.
+/// Error: An error returned by the PeerList trait
+/// Pl: A list of Peers (PeerList trait struct)
+///
+/// NOTE: LocalTransport must implement Stream trait from async/.await framework.
+pub trait LocalTransport<Id, Data, Error, Pl>: Stream<Item = Data> + Drop + Unpin
+where
+    Id: PeerId,
+    Pl: PeerList<Id, Error>,
+    Data: Serialize + DeserializeOwned,
+{
+    /// Creates a new LocalTransport type from a configuration, binding a listener to its
+    /// `bind_addr`, as `Transport::new`.
+    fn new<C>(cfg: C) -> Result<Self>
+    where
+        C: TransportConfiguration,
+        Self: Sized;
+
+    /// Sends a message of type 'Data' to the specified peer (as specified by `peer_address`)
+    fn send(&mut self, peer_address: String, data: Data) -> Result<()>;
+
+    /// Broadcasts a message of type 'Data' to all peers on the network using `base` address.
+    /// Requires a struct which implements PeerList.
+    fn broadcast(&mut self, peers: &mut Pl, data: Data) -> Result<()>;
+
+    /// Broadcasts a message of type 'Data' to all peers on the network using nth address in `net_addr`.
+    /// Requires a struct which implements PeerList.
+    fn broadcast_n(&mut self, peers: &mut Pl, n: usize, data: Data) -> Result<()>;
+
+    /// returns quit send channel
+    fn get_quit_tx(&self) -> Option<Sender<()>>;
+}
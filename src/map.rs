@@ -0,0 +1,477 @@
+/// # Fantom Libtransport/map
+///
+/// This file defines `MapData`, an adapter returned by `Transport::map_data` which presents a
+/// Transport of one `Data` type as a Transport of another. Outbound payloads are converted with
+/// the `to` function before sending and inbound payloads with the `from` function after
+/// receiving, so one underlying transport instance can serve a typed sub-protocol.
+///
+/// Every Transport method is forwarded to the inner transport, converting the payloads it takes
+/// or yields. Methods which only borrow their payload (`send_ref`, `broadcast_ref`) or require
+/// `Data: Clone` can't convert it and fall back to the default implementations, which send
+/// through `send` and `broadcast`. `set_codec` is unsupported: a codec would have to serialize
+/// `D2`, while the inner transport serializes its own `Data`.
+use crate::codec::Codec;
+use crate::compression::Compression;
+use crate::concurrency::SendLimiter;
+use crate::errors::{Error as TransportError, Result};
+use crate::events::TransportEvent;
+use crate::handoff::ConnectionState;
+use crate::headers::Headers;
+use crate::metrics::{ConnectionInfo, Metrics, TransportStats};
+use crate::priority::Priority;
+use crate::shutdown::ShutdownHandle;
+use crate::{Capabilities, Resolver, SendFut, Transport, TransportConfiguration};
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
+use core::pin::Pin;
+use core::ptr;
+use core::task::{Context, Poll};
+use failure::Fail;
+use futures::future::Future;
+use futures::stream::{FusedStream, Stream, StreamExt};
+use libcommon_rs::peer::{PeerId, PeerList};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::sync::mpsc::{Receiver, Sender};
+use std::time::Duration;
+
+/// A Transport adapter converting between the inner transport's `Data` and `D2`.
+pub struct MapData<T, Data, D2, F, G> {
+    inner: T,
+    to: F,
+    from: G,
+    _data: PhantomData<fn(Data) -> D2>,
+}
+
+impl<T, Data, D2, F, G> MapData<T, Data, D2, F, G> {
+    /// Wraps `inner`, converting outbound payloads with `to` and inbound payloads with `from`.
+    pub fn new(inner: T, to: F, from: G) -> MapData<T, Data, D2, F, G> {
+        MapData {
+            inner,
+            to,
+            from,
+            _data: PhantomData,
+        }
+    }
+
+    /// Unwraps the inner transport.
+    pub fn into_inner(self) -> T {
+        // MapData implements Drop, so its fields can't be moved out of it directly.
+        let this = ManuallyDrop::new(self);
+        // Safety: `this` is never dropped, so every field is read out exactly once.
+        unsafe {
+            let inner = ptr::read(&this.inner);
+            drop(ptr::read(&this.to));
+            drop(ptr::read(&this.from));
+            inner
+        }
+    }
+}
+
+// The fields are never pinned: the inner transport is polled through `Pin::new`, and the
+// conversion functions are only called.
+impl<T: Unpin, Data, D2, F, G> Unpin for MapData<T, Data, D2, F, G> {}
+
+impl<T, Data, D2, F, G> Drop for MapData<T, Data, D2, F, G> {
+    fn drop(&mut self) {}
+}
+
+impl<T, Data, D2, F, G> Stream for MapData<T, Data, D2, F, G>
+where
+    T: Stream<Item = Data> + Unpin,
+    G: Fn(Data) -> D2,
+{
+    type Item = D2;
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<D2>> {
+        let this = &mut *self;
+        match Pin::new(&mut this.inner).poll_next(cx) {
+            Poll::Ready(Some(data)) => Poll::Ready(Some((this.from)(data))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<T, Data, D2, F, G> FusedStream for MapData<T, Data, D2, F, G>
+where
+    T: FusedStream<Item = Data> + Unpin,
+    G: Fn(Data) -> D2,
+{
+    fn is_terminated(&self) -> bool {
+        self.inner.is_terminated()
+    }
+}
+
+impl<Id, Data, D2, Error, Pl, T, F, G> Transport<Id, D2, Error, Pl> for MapData<T, Data, D2, F, G>
+where
+    Id: PeerId,
+    Pl: PeerList<Id, Error>,
+    Data: Serialize + DeserializeOwned,
+    D2: Serialize + DeserializeOwned,
+    T: Transport<Id, Data, Error, Pl>,
+    F: Fn(D2) -> Data,
+    G: Fn(Data) -> D2,
+{
+    /// A MapData cannot be constructed from an address alone, use `Transport::map_data`.
+    fn new<C: TransportConfiguration>(_cfg: C) -> Result<Self> {
+        Err(TransportError::Unsupported(String::from("MapData::new")).into())
+    }
+
+    fn bind(&mut self, addr: String) -> Result<()> {
+        self.inner.bind(addr)
+    }
+
+    fn set_accept_backlog(&mut self, backlog: u32) -> Result<()> {
+        self.inner.set_accept_backlog(backlog)
+    }
+
+    fn set_max_concurrent_handshakes(&mut self, max: usize) {
+        self.inner.set_max_concurrent_handshakes(max)
+    }
+
+    fn peak_concurrent_handshakes(&self) -> Option<usize> {
+        self.inner.peak_concurrent_handshakes()
+    }
+
+    fn is_ready(&self) -> bool {
+        self.inner.is_ready()
+    }
+
+    fn is_reliable(&self) -> bool {
+        self.inner.is_reliable()
+    }
+
+    fn preserves_order(&self) -> bool {
+        self.inner.preserves_order()
+    }
+
+    fn send(&mut self, peer_address: String, data: D2) -> Result<()> {
+        let data = (self.to)(data);
+        self.inner.send(peer_address, data)
+    }
+
+    fn send_peer(&mut self, peer: &Pl::P, data: D2) -> Result<()> {
+        let data = (self.to)(data);
+        self.inner.send_peer(peer, data)
+    }
+
+    fn send_fut(&mut self, peer_address: String, data: D2) -> SendFut<'_> {
+        let data = (self.to)(data);
+        self.inner.send_fut(peer_address, data)
+    }
+
+    fn try_send(&mut self, peer_address: String, data: D2) -> Result<bool> {
+        let data = (self.to)(data);
+        self.inner.try_send(peer_address, data)
+    }
+
+    fn broadcast(&mut self, peers: &mut Pl, data: D2) -> Result<()> {
+        let data = (self.to)(data);
+        self.inner.broadcast(peers, data)
+    }
+
+    fn broadcast_n(&mut self, peers: &mut Pl, n: usize, data: D2) -> Result<()> {
+        let data = (self.to)(data);
+        self.inner.broadcast_n(peers, n, data)
+    }
+
+    fn get_quit_tx(&self) -> Option<Sender<()>> {
+        self.inner.get_quit_tx()
+    }
+
+    fn connect(&mut self, peer_address: String) -> Result<()> {
+        self.inner.connect(peer_address)
+    }
+
+    fn disconnect(&mut self, peer_address: String) -> Result<()> {
+        self.inner.disconnect(peer_address)
+    }
+
+    fn close_all(&mut self) -> Result<()> {
+        self.inner.close_all()
+    }
+
+    fn peer_count(&self) -> usize {
+        self.inner.peer_count()
+    }
+
+    fn export_connections(self) -> Result<ConnectionState> {
+        self.into_inner().export_connections()
+    }
+
+    fn import_connections(&mut self, state: ConnectionState) -> Result<()> {
+        self.inner.import_connections(state)
+    }
+
+    fn is_connected_to(&self, peer_address: &str) -> bool {
+        self.inner.is_connected_to(peer_address)
+    }
+
+    fn reconnect(&mut self, peer_address: String) -> Result<()> {
+        self.inner.reconnect(peer_address)
+    }
+
+    fn set_on_connect(&mut self, cb: Box<dyn Fn(&str) + Send + Sync>) {
+        self.inner.set_on_connect(cb)
+    }
+
+    fn version(&self) -> u8 {
+        self.inner.version()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn set_max_frame_size(&mut self, max_frame_size: u32) {
+        self.inner.set_max_frame_size(max_frame_size)
+    }
+
+    fn negotiated_max_frame_size(&self, peer_address: &str) -> Option<u32> {
+        self.inner.negotiated_max_frame_size(peer_address)
+    }
+
+    fn set_features(&mut self, features: u32) {
+        self.inner.set_features(features)
+    }
+
+    fn peer_features(&self, peer_address: &str) -> Option<u32> {
+        self.inner.peer_features(peer_address)
+    }
+
+    fn set_stream_compression(&mut self, algo: Compression) {
+        self.inner.set_stream_compression(algo)
+    }
+
+    /// Unsupported: the inner transport serializes its own `Data`, not `D2`.
+    fn set_codec(&mut self, _codec: Box<dyn Codec<D2> + Send + Sync>) -> Result<()> {
+        Err(TransportError::Unsupported(String::from("MapData::set_codec")).into())
+    }
+
+    fn rotate_keys(&mut self, peer_address: String) -> Result<()> {
+        self.inner.rotate_keys(peer_address)
+    }
+
+    fn preconnect_all<'a>(
+        &'a mut self,
+        peers: &mut Pl,
+    ) -> Pin<Box<dyn Future<Output = Vec<(Id, Result<()>)>> + 'a>>
+    where
+        Id: 'a,
+    {
+        self.inner.preconnect_all(peers)
+    }
+
+    fn on_peers_changed(&mut self, peers: &Pl) -> Result<()> {
+        self.inner.on_peers_changed(peers)
+    }
+
+    fn shutdown_send(&mut self, peer_address: String) -> Result<()> {
+        self.inner.shutdown_send(peer_address)
+    }
+
+    fn local_addr(&self) -> Option<String> {
+        self.inner.local_addr()
+    }
+
+    fn advertised_addr(&self) -> Result<String> {
+        self.inner.advertised_addr()
+    }
+
+    fn last_remote_addr(&self) -> Option<String> {
+        self.inner.last_remote_addr()
+    }
+
+    fn pooled_connection_count(&self) -> usize {
+        self.inner.pooled_connection_count()
+    }
+
+    fn metrics(&self) -> Metrics {
+        self.inner.metrics()
+    }
+
+    fn peer_metrics(&self, peer_address: &str) -> Option<Metrics> {
+        self.inner.peer_metrics(peer_address)
+    }
+
+    fn uptime(&self) -> Duration {
+        self.inner.uptime()
+    }
+
+    fn connections(&self) -> Vec<ConnectionInfo> {
+        self.inner.connections()
+    }
+
+    fn stats(&self) -> TransportStats {
+        self.inner.stats()
+    }
+
+    fn reset_metrics(&mut self) {
+        self.inner.reset_metrics()
+    }
+
+    fn subscribe_events(&mut self) -> Option<Receiver<TransportEvent>> {
+        self.inner.subscribe_events()
+    }
+
+    fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.inner.set_idle_timeout(timeout)
+    }
+
+    fn set_send_limiter(&mut self, limiter: SendLimiter) {
+        self.inner.set_send_limiter(limiter)
+    }
+
+    fn set_circuit_breaker(&mut self, failures: u32, cooldown: Duration) {
+        self.inner.set_circuit_breaker(failures, cooldown)
+    }
+
+    fn set_coalesce_window(&mut self, window: Option<Duration>) {
+        self.inner.set_coalesce_window(window)
+    }
+
+    fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        self.inner.set_write_timeout(timeout)
+    }
+
+    fn set_poll_interval(&mut self, interval: Duration) {
+        self.inner.set_poll_interval(interval)
+    }
+
+    fn send_with_priority(
+        &mut self,
+        peer_address: String,
+        data: D2,
+        priority: Priority,
+    ) -> Result<()> {
+        let data = (self.to)(data);
+        self.inner.send_with_priority(peer_address, data, priority)
+    }
+
+    fn set_priority_weights(&mut self, high: u32, normal: u32, low: u32) {
+        self.inner.set_priority_weights(high, normal, low)
+    }
+
+    fn set_sequence_tracking(&mut self, enabled: bool) {
+        self.inner.set_sequence_tracking(enabled)
+    }
+
+    fn set_pending_capacity(&mut self, capacity: usize) {
+        self.inner.set_pending_capacity(capacity)
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        self.inner.pause()
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.inner.resume()
+    }
+
+    fn set_inbound_rate_limit(&mut self, bytes_per_sec: u64) {
+        self.inner.set_inbound_rate_limit(bytes_per_sec)
+    }
+
+    fn set_flush_on_drop(&mut self, flush: bool) {
+        self.inner.set_flush_on_drop(flush)
+    }
+
+    fn shutdown_handle(&self) -> Option<ShutdownHandle> {
+        self.inner.shutdown_handle()
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.inner.close()
+    }
+
+    fn set_resolver(&mut self, resolver: Resolver<Id>) {
+        self.inner.set_resolver(resolver)
+    }
+
+    fn resolve(&self, id: &Id) -> Option<String> {
+        self.inner.resolve(id)
+    }
+
+    fn send_by_id(&mut self, id: &Id, data: D2) -> Result<()> {
+        let data = (self.to)(data);
+        self.inner.send_by_id(id, data)
+    }
+
+    fn send_on_channel(&mut self, peer_address: String, channel: u16, data: D2) -> Result<()> {
+        let data = (self.to)(data);
+        self.inner.send_on_channel(peer_address, channel, data)
+    }
+
+    fn stream_for_channel(&mut self, channel: u16) -> Pin<Box<dyn Stream<Item = D2> + '_>> {
+        let from = &self.from;
+        Box::pin(self.inner.stream_for_channel(channel).map(from))
+    }
+
+    fn send_raw(&mut self, peer_address: String, bytes: Vec<u8>) -> Result<()> {
+        self.inner.send_raw(peer_address, bytes)
+    }
+
+    fn raw_stream(&mut self) -> Result<Pin<Box<dyn Stream<Item = Vec<u8>> + '_>>> {
+        self.inner.raw_stream()
+    }
+
+    fn send_tracked(&mut self, peer_address: String, data: D2) -> Result<u64> {
+        let data = (self.to)(data);
+        self.inner.send_tracked(peer_address, data)
+    }
+
+    fn tracked_stream(&mut self) -> Pin<Box<dyn Stream<Item = (u64, D2)> + '_>> {
+        let from = &self.from;
+        Box::pin(
+            self.inner
+                .tracked_stream()
+                .map(move |(id, data)| (id, from(data))),
+        )
+    }
+
+    fn try_broadcast(&mut self, peers: &mut Pl, data: D2) -> Result<Vec<Id>> {
+        let data = (self.to)(data);
+        self.inner.try_broadcast(peers, data)
+    }
+
+    fn broadcast_batch(&mut self, peers: &mut Pl, items: Vec<D2>) -> Result<usize> {
+        let items = items.into_iter().map(|data| (self.to)(data)).collect();
+        self.inner.broadcast_batch(peers, items)
+    }
+
+    fn negotiated_addr(&self, peer_address: &str) -> Option<String> {
+        self.inner.negotiated_addr(peer_address)
+    }
+
+    fn broadcast_recording(&mut self, peers: &mut Pl, n: usize, data: D2) -> Result<()>
+    where
+        Error: Fail,
+    {
+        let data = (self.to)(data);
+        self.inner.broadcast_recording(peers, n, data)
+    }
+
+    fn send_with_headers(
+        &mut self,
+        peer_address: String,
+        headers: Headers,
+        data: D2,
+    ) -> Result<()> {
+        let data = (self.to)(data);
+        self.inner.send_with_headers(peer_address, headers, data)
+    }
+
+    fn result_stream(&mut self) -> Pin<Box<dyn Stream<Item = Result<D2>> + '_>> {
+        let from = &self.from;
+        Box::pin(self.inner.result_stream().map(move |data| data.map(from)))
+    }
+
+    fn headers_stream(&mut self) -> Pin<Box<dyn Stream<Item = (Headers, D2)> + '_>> {
+        let from = &self.from;
+        Box::pin(
+            self.inner
+                .headers_stream()
+                .map(move |(headers, data)| (headers, from(data))),
+        )
+    }
+}
@@ -0,0 +1,917 @@
+/// # Fantom Libtransport/memory
+///
+/// This file defines `MemoryTransport`, a Transport exchanging messages through an in-process
+/// `MemoryNetwork` instead of sockets, for tests and simulations.
+///
+/// Transports created from the same `MemoryNetwork` can reach each other by address; transports
+/// created with `Transport::new` join the network entered on the current thread with
+/// `MemoryNetwork::enter`, so every test can enter a network of its own. Messages are
+/// serialized with bincode as on a real network. A network created with `MemoryNetwork::new`
+/// delivers every message immediately, while one created with `MemoryNetwork::scheduled` queues
+/// messages on their (sender, receiver) link until a `Scheduler` delivers them.
+///
+/// Messages are serialized with bincode unless another codec was set with `Transport::set_codec`.
+/// With `Transport::set_sequence_tracking` enabled, the messages of every (sender, receiver) link
+/// are numbered (see the `sequence` module) and gaps are yielded by `Transport::result_stream`.
+///
+/// A `MemoryTransport` is also a `TransportReceiver`, and `MemorySender` the matching
+/// `TransportSender`, for the code and tests using separate sending and receiving halves.
+use crate::breaker::CircuitBreaker;
+use crate::codec::{bincode_decode, bincode_encode, Codec};
+use crate::concurrency::SendLimiter;
+use crate::errors::{Error, Result};
+use crate::rate::RateLimiter;
+use crate::sequence::{self, SequenceChecker, SequenceCounter};
+use crate::shutdown::ShutdownHandle;
+use crate::timer::{self, Delay};
+use crate::{Transport, TransportConfiguration, TransportReceiver, TransportSender};
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use futures::future::Future;
+use futures::stream::{self, FusedStream, Stream};
+use libcommon_rs::peer::{Peer, PeerId, PeerList};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::{self, ErrorKind};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Messages received by a transport but not yet read from its stream, with their sender address.
+#[derive(Default)]
+struct Inbox {
+    queue: VecDeque<(String, Vec<u8>)>,
+    waker: Option<Waker>,
+}
+
+// Shared state of a MemoryNetwork.
+#[derive(Default)]
+pub(crate) struct Network {
+    inboxes: HashMap<String, Inbox>,
+    scheduled: bool,
+    // Messages awaiting delivery per (sender, receiver) link, only used when scheduled.
+    links: BTreeMap<(String, String), VecDeque<Vec<u8>>>,
+    // Links of the queued messages in the order they were sent, only used when scheduled.
+    order: VecDeque<(String, String)>,
+}
+
+impl Network {
+    fn send(&mut self, from: &str, to: &str, bytes: Vec<u8>) -> Result<()> {
+        if !self.inboxes.contains_key(to) {
+            return Err(Error::PeerNotFound(to.to_string()).into());
+        }
+        if self.scheduled {
+            let link = (from.to_string(), to.to_string());
+            self.links.entry(link.clone()).or_default().push_back(bytes);
+            self.order.push_back(link);
+            Ok(())
+        } else {
+            self.push(from, to, bytes);
+            Ok(())
+        }
+    }
+
+    // Appends a message from `from` to the inbox of `to`, waking its reader.
+    fn push(&mut self, from: &str, to: &str, bytes: Vec<u8>) {
+        if let Some(inbox) = self.inboxes.get_mut(to) {
+            inbox.queue.push_back((from.to_string(), bytes));
+            if let Some(waker) = inbox.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+
+    // Delivers the oldest message queued on the link from `from` to `to`, returning false if
+    // there is none.
+    pub(crate) fn deliver(&mut self, from: &str, to: &str) -> bool {
+        match self.dequeue(from, to) {
+            Some(bytes) => {
+                self.push(from, to, bytes);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Drops the oldest message queued on the link from `from` to `to` without delivering it,
+    // returning false if there is none.
+    pub(crate) fn discard(&mut self, from: &str, to: &str) -> bool {
+        self.dequeue(from, to).is_some()
+    }
+
+    // Removes the oldest message queued on the link from `from` to `to`.
+    fn dequeue(&mut self, from: &str, to: &str) -> Option<Vec<u8>> {
+        let link = (from.to_string(), to.to_string());
+        let bytes = self
+            .links
+            .get_mut(&link)
+            .and_then(|queue| queue.pop_front())?;
+        if self
+            .links
+            .get(&link)
+            .map_or(false, |queue| queue.is_empty())
+        {
+            self.links.remove(&link);
+        }
+        if let Some(pos) = self.order.iter().position(|l| *l == link) {
+            self.order.remove(pos);
+        }
+        Some(bytes)
+    }
+
+    // Delivers the oldest queued message of the whole network, returning false if there is none.
+    pub(crate) fn step(&mut self) -> bool {
+        match self.order.front().cloned() {
+            Some((from, to)) => self.deliver(&from, &to),
+            None => false,
+        }
+    }
+
+    // Returns the links with queued messages and the number of messages queued on each.
+    pub(crate) fn pending(&self) -> Vec<(String, String, usize)> {
+        self.links
+            .iter()
+            .map(|((from, to), queue)| (from.clone(), to.clone(), queue.len()))
+            .collect()
+    }
+}
+
+thread_local! {
+    // The network joined by transports created with `Transport::new` on this thread.
+    static CURRENT: RefCell<Option<MemoryNetwork>> = RefCell::new(None);
+}
+
+/// An in-process network connecting `MemoryTransport`s.
+#[derive(Clone, Default)]
+pub struct MemoryNetwork {
+    pub(crate) inner: Arc<Mutex<Network>>,
+}
+
+impl MemoryNetwork {
+    // Returns the network entered on the current thread, failing with `Error::NotReady` if there
+    // is none.
+    fn current() -> Result<MemoryNetwork> {
+        CURRENT
+            .with(|current| current.borrow().clone())
+            .ok_or_else(|| Error::NotReady.into())
+    }
+
+    /// Creates a network delivering every message immediately.
+    pub fn new() -> MemoryNetwork {
+        MemoryNetwork::default()
+    }
+
+    /// Creates a network queueing every message until it is delivered by a `Scheduler`.
+    pub fn scheduled() -> MemoryNetwork {
+        let network = MemoryNetwork::default();
+        if let Ok(mut inner) = network.inner.lock() {
+            inner.scheduled = true;
+        }
+        network
+    }
+
+    /// Makes this the network joined by transports created with `Transport::new` on the current
+    /// thread, so they can reach each other as sockets on one host would, until the returned
+    /// guard is dropped. Transports created on other threads don't join it.
+    pub fn enter(&self) -> EnterGuard {
+        let previous = CURRENT.with(|current| current.replace(Some(self.clone())));
+        EnterGuard { previous }
+    }
+
+    /// Creates a transport on this network, reachable at `addr`. Fails with an `AddrInUse` I/O
+    /// error if another transport of the network is already reachable there.
+    pub fn transport<Data>(&self, addr: String) -> Result<MemoryTransport<Data>> {
+        let mut inner = self.inner.lock().map_err(Error::from)?;
+        if inner.inboxes.contains_key(&addr) {
+            return Err(Error::Io(io::Error::new(ErrorKind::AddrInUse, addr)).into());
+        }
+        inner.inboxes.insert(addr.clone(), Inbox::default());
+        Ok(MemoryTransport {
+            addr,
+            network: self.clone(),
+            closed: false,
+            terminated: false,
+            paused: false,
+            codec: None,
+            limiter: SendLimiter::default(),
+            breaker: None,
+            shutdown: ShutdownHandle::new(),
+            inbound: None,
+            held: None,
+            negotiated: HashMap::new(),
+            sequences: None,
+            _data: PhantomData,
+        })
+    }
+}
+
+/// Keeps a `MemoryNetwork` entered on the current thread, see `MemoryNetwork::enter`. Dropping
+/// it restores the network entered before, if any.
+pub struct EnterGuard {
+    previous: Option<MemoryNetwork>,
+}
+
+impl Drop for EnterGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        // The thread local is gone if the guard is dropped while the thread exits.
+        let _ = CURRENT.try_with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// A Transport exchanging messages through a `MemoryNetwork`.
+pub struct MemoryTransport<Data> {
+    addr: String,
+    network: MemoryNetwork,
+    closed: bool,
+    terminated: bool,
+    // Set by `Transport::pause`: messages stay in the inbox until resumed.
+    paused: bool,
+    // The codec set with `Transport::set_codec`, None for bincode.
+    codec: Option<Box<dyn Codec<Data> + Send + Sync>>,
+    // Caps the sends in flight, see `Transport::set_send_limiter`.
+    limiter: SendLimiter,
+    // Fails sends to failing peers fast, see `Transport::set_circuit_breaker`.
+    breaker: Option<CircuitBreaker>,
+    shutdown: ShutdownHandle,
+    // Paces reading, see `Transport::set_inbound_rate_limit`.
+    inbound: Option<RateLimiter>,
+    // A message read, with its sender, held back by the inbound rate limit until the delay has
+    // passed.
+    held: Option<(String, Vec<u8>, Delay)>,
+    // The inbox address each peer address was last delivered to, see `Transport::negotiated_addr`.
+    negotiated: HashMap<String, String>,
+    // The sequence numbers of sent and received frames, while `Transport::set_sequence_tracking`
+    // is enabled.
+    sequences: Option<(SequenceCounter, SequenceChecker)>,
+    _data: PhantomData<fn() -> Data>,
+}
+
+impl<Data> MemoryTransport<Data> {
+    /// Returns the address the transport is reachable at.
+    pub fn addr(&self) -> &str {
+        &self.addr
+    }
+
+    fn send_bytes(&mut self, peer_address: &str, bytes: Vec<u8>) -> Result<()> {
+        if let Some(ref breaker) = self.breaker {
+            breaker.check(peer_address)?;
+        }
+        let _permit = self.limiter.acquire()?;
+        let bytes = match self.sequences {
+            Some((ref mut counter, _)) => sequence::encode(counter.next(peer_address, 0), &bytes),
+            None => bytes,
+        };
+        let result = match self.network.inner.lock() {
+            Ok(mut inner) => inner.send(&self.addr, peer_address, bytes),
+            Err(e) => Err(Error::from(e).into()),
+        };
+        if result.is_ok() {
+            self.negotiated
+                .insert(peer_address.to_string(), peer_address.to_string());
+        }
+        if let Some(ref mut breaker) = self.breaker {
+            match result {
+                Ok(()) => breaker.record_success(peer_address),
+                Err(_) => breaker.record_failure(peer_address),
+            }
+        }
+        result
+    }
+
+    // Pops the payload of the next message received, stripping its sequence number while
+    // sequence tracking is enabled. A sequence gap before the message is returned along with it.
+    // Messages too short for a sequence number are skipped.
+    fn poll_payload(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<(Option<failure::Error>, Vec<u8>)>> {
+        loop {
+            let (from, bytes) = match self.poll_bytes(cx) {
+                Poll::Ready(Some(message)) => message,
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+            let checker = match self.sequences {
+                Some((_, ref mut checker)) => checker,
+                None => return Poll::Ready(Some((None, bytes))),
+            };
+            if let Ok((seq, payload)) = sequence::decode(&bytes) {
+                let gap = checker.check(&from, 0, seq).err();
+                return Poll::Ready(Some((gap, payload.to_vec())));
+            }
+        }
+    }
+
+    // Pops the next message received with its sender, holding it back as long as the inbound
+    // rate limit requires. Ends the stream once closed or shut down.
+    fn poll_bytes(&mut self, cx: &mut Context<'_>) -> Poll<Option<(String, Vec<u8>)>> {
+        if self.closed || self.shutdown.poll_triggered(cx) {
+            self.terminated = true;
+            return Poll::Ready(None);
+        }
+        loop {
+            if let Some((_, _, ref mut delay)) = self.held {
+                if Pin::new(delay).poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                return Poll::Ready(self.held.take().map(|(from, bytes, _)| (from, bytes)));
+            }
+            let (from, bytes) = match self.poll_inbox(cx) {
+                Poll::Ready(Some(message)) => message,
+                other => return other,
+            };
+            let wait = match self.inbound {
+                Some(ref mut limiter) => limiter.delay_for(bytes.len() as u64),
+                None => Duration::from_secs(0),
+            };
+            if wait == Duration::from_secs(0) {
+                return Poll::Ready(Some((from, bytes)));
+            }
+            self.held = Some((from, bytes, timer::delay(wait)));
+        }
+    }
+
+    // Pops the next message from the inbox with its sender, registering the reader's waker if
+    // there is none. Ends the stream once the network is unusable.
+    fn poll_inbox(&mut self, cx: &mut Context<'_>) -> Poll<Option<(String, Vec<u8>)>> {
+        match self.network.inner.lock() {
+            Ok(mut inner) => match inner.inboxes.get_mut(&self.addr) {
+                Some(inbox) => match inbox.queue.front() {
+                    Some(_) if !self.paused => Poll::Ready(inbox.queue.pop_front()),
+                    _ => {
+                        inbox.waker = Some(cx.waker().clone());
+                        Poll::Pending
+                    }
+                },
+                None => {
+                    self.terminated = true;
+                    Poll::Ready(None)
+                }
+            },
+            // The network is unusable since a thread panicked holding it: end the stream.
+            Err(_) => {
+                self.terminated = true;
+                Poll::Ready(None)
+            }
+        }
+    }
+
+    // Pops and decodes the next message received, along with the sequence gap before it, if
+    // any. Undecodable messages are skipped, as a network transport drops malformed frames.
+    fn poll_data(&mut self, cx: &mut Context<'_>) -> Poll<Option<(Option<failure::Error>, Data)>>
+    where
+        Data: DeserializeOwned,
+    {
+        loop {
+            let (gap, bytes) = match self.poll_payload(cx) {
+                Poll::Ready(Some(message)) => message,
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+            let decoded = match self.codec {
+                Some(ref codec) => codec.decode(&bytes),
+                None => bincode_decode(&bytes),
+            };
+            if let Ok(data) = decoded {
+                return Poll::Ready(Some((gap, data)));
+            }
+        }
+    }
+
+    fn encode(&self, data: &Data) -> Result<Vec<u8>>
+    where
+        Data: Serialize,
+    {
+        match self.codec {
+            Some(ref codec) => codec.encode(data),
+            None => bincode_encode(data),
+        }
+    }
+}
+
+impl<Data> Drop for MemoryTransport<Data> {
+    fn drop(&mut self) {
+        if let Ok(mut inner) = self.network.inner.lock() {
+            inner.inboxes.remove(&self.addr);
+        }
+    }
+}
+
+impl<Data: DeserializeOwned> Stream for MemoryTransport<Data> {
+    type Item = Data;
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Data>> {
+        self.poll_data(cx)
+            .map(|message| message.map(|(_, data)| data))
+    }
+}
+
+impl<Data: DeserializeOwned> FusedStream for MemoryTransport<Data> {
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
+
+impl<Id, Data, Error, Pl> Transport<Id, Data, Error, Pl> for MemoryTransport<Data>
+where
+    Id: PeerId,
+    Pl: PeerList<Id, Error>,
+    Data: Serialize + DeserializeOwned,
+{
+    /// Creates a transport on the network entered on the current thread with
+    /// `MemoryNetwork::enter`, failing with `Error::NotReady` if there is none; use
+    /// `MemoryNetwork::transport` to create transports on a network directly.
+    fn new<C: TransportConfiguration>(cfg: C) -> Result<Self> {
+        MemoryNetwork::current()?.transport(cfg.bind_addr().to_string())
+    }
+
+    /// A MemoryTransport is reachable as soon as it is created.
+    fn is_ready(&self) -> bool {
+        true
+    }
+
+    fn send(&mut self, peer_address: String, data: Data) -> Result<()> {
+        self.send_bytes(&peer_address, self.encode(&data)?)
+    }
+
+    fn broadcast(&mut self, peers: &mut Pl, data: Data) -> Result<()> {
+        let bytes = self.encode(&data)?;
+        for peer in peers.iter() {
+            self.send_bytes(&peer.get_base_addr(), bytes.clone())?;
+        }
+        Ok(())
+    }
+
+    fn broadcast_n(&mut self, peers: &mut Pl, n: usize, data: Data) -> Result<()> {
+        let bytes = self.encode(&data)?;
+        for peer in peers.iter() {
+            self.send_bytes(&peer.get_net_addr(n), bytes.clone())?;
+        }
+        Ok(())
+    }
+
+    fn get_quit_tx(&self) -> Option<Sender<()>> {
+        None
+    }
+
+    fn shutdown_handle(&self) -> Option<ShutdownHandle> {
+        Some(self.shutdown.clone())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.closed = true;
+        Ok(())
+    }
+
+    fn set_codec(&mut self, codec: Box<dyn Codec<Data> + Send + Sync>) -> Result<()> {
+        self.codec = Some(codec);
+        Ok(())
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        self.paused = true;
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.paused = false;
+        let mut inner = self.network.inner.lock().map_err(Error::from)?;
+        if let Some(waker) = inner
+            .inboxes
+            .get_mut(&self.addr)
+            .and_then(|inbox| inbox.waker.take())
+        {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    fn set_send_limiter(&mut self, limiter: SendLimiter) {
+        self.limiter = limiter;
+    }
+
+    fn set_circuit_breaker(&mut self, failures: u32, cooldown: Duration) {
+        self.breaker = Some(CircuitBreaker::new(failures, cooldown));
+    }
+
+    fn set_inbound_rate_limit(&mut self, bytes_per_sec: u64) {
+        self.inbound = Some(RateLimiter::new(bytes_per_sec));
+    }
+
+    fn negotiated_addr(&self, peer_address: &str) -> Option<String> {
+        self.negotiated.get(peer_address).cloned()
+    }
+
+    fn send_ref(&mut self, peer_address: String, data: &Data) -> Result<()> {
+        self.send_bytes(&peer_address, self.encode(data)?)
+    }
+
+    fn broadcast_ref(&mut self, peers: &mut Pl, data: &Data) -> Result<()> {
+        let bytes = self.encode(data)?;
+        for peer in peers.iter() {
+            self.send_bytes(&peer.get_base_addr(), bytes.clone())?;
+        }
+        Ok(())
+    }
+
+    fn send_raw(&mut self, peer_address: String, bytes: Vec<u8>) -> Result<()> {
+        self.send_bytes(&peer_address, bytes)
+    }
+
+    fn encode(&self, data: &Data) -> Result<Vec<u8>> {
+        MemoryTransport::encode(self, data)
+    }
+
+    fn raw_stream(&mut self) -> Result<Pin<Box<dyn Stream<Item = Vec<u8>> + '_>>> {
+        Ok(Box::pin(stream::poll_fn(move |cx| {
+            self.poll_payload(cx)
+                .map(|message| message.map(|(_, bytes)| bytes))
+        })))
+    }
+
+    fn set_sequence_tracking(&mut self, enabled: bool) {
+        self.sequences = if enabled {
+            Some((SequenceCounter::new(), SequenceChecker::new()))
+        } else {
+            None
+        };
+    }
+
+    /// Yields a sequence gap as `Error::SequenceGap` ahead of the message received after it.
+    fn result_stream(&mut self) -> Pin<Box<dyn Stream<Item = Result<Data>> + '_>> {
+        let mut next: Option<Data> = None;
+        Box::pin(stream::poll_fn(move |cx| {
+            if let Some(data) = next.take() {
+                return Poll::Ready(Some(Ok(data)));
+            }
+            self.poll_data(cx).map(|message| {
+                message.map(|(gap, data)| match gap {
+                    Some(gap) => {
+                        next = Some(data);
+                        Err(gap)
+                    }
+                    None => Ok(data),
+                })
+            })
+        }))
+    }
+}
+
+impl<Id, Data, Error, Pl> TransportReceiver<Id, Data, Error, Pl> for MemoryTransport<Data>
+where
+    Id: PeerId,
+    Pl: PeerList<Id, Error>,
+    Data: DeserializeOwned,
+{
+    /// Creates a transport on the network entered on the current thread, as `Transport::new`.
+    fn new<C>(cfg: C) -> Result<Self>
+    where
+        C: TransportConfiguration,
+    {
+        MemoryNetwork::current()?.transport(cfg.bind_addr().to_string())
+    }
+
+    fn get_quit_tx(&self) -> Option<Sender<()>> {
+        None
+    }
+
+    fn shutdown_handle(&self) -> Option<ShutdownHandle> {
+        Some(self.shutdown.clone())
+    }
+}
+
+/// A TransportSender sending through a `MemoryNetwork` from an address of its own, which no
+/// transport is reachable at. Clones share the address.
+pub struct MemorySender<Data> {
+    addr: String,
+    network: MemoryNetwork,
+    _data: PhantomData<fn(Data)>,
+}
+
+impl<Data> Clone for MemorySender<Data> {
+    fn clone(&self) -> MemorySender<Data> {
+        MemorySender {
+            addr: self.addr.clone(),
+            network: self.network.clone(),
+            _data: PhantomData,
+        }
+    }
+}
+
+impl<Data> MemorySender<Data> {
+    fn send_bytes(&self, peer_address: &str, bytes: Vec<u8>) -> Result<()> {
+        let mut inner = self.network.inner.lock().map_err(Error::from)?;
+        inner.send(&self.addr, peer_address, bytes)
+    }
+}
+
+impl<Id, Data, Error, Pl> TransportSender<Id, Data, Error, Pl> for MemorySender<Data>
+where
+    Id: PeerId,
+    Pl: PeerList<Id, Error>,
+    Data: Serialize,
+{
+    /// Creates a sender on the network entered on the current thread, failing with
+    /// `Error::NotReady` if there is none.
+    fn new() -> Result<Self> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        Ok(MemorySender {
+            addr: format!("sender-{}", NEXT.fetch_add(1, Ordering::SeqCst)),
+            network: MemoryNetwork::current()?,
+            _data: PhantomData,
+        })
+    }
+
+    fn send(&mut self, peer_address: String, data: Data) -> Result<()> {
+        self.send_bytes(&peer_address, bincode_encode(&data)?)
+    }
+
+    fn broadcast(&mut self, peers: &mut Pl, data: Data) -> Result<()> {
+        let bytes = bincode_encode(&data)?;
+        for peer in peers.iter() {
+            self.send_bytes(&peer.get_base_addr(), bytes.clone())?;
+        }
+        Ok(())
+    }
+
+    fn broadcast_n(&mut self, peers: &mut Pl, n: usize, data: Data) -> Result<()> {
+        let bytes = bincode_encode(&data)?;
+        for peer in peers.iter() {
+            self.send_bytes(&peer.get_net_addr(n), bytes.clone())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::SerializationFormat;
+    use crate::framing;
+    use crate::generic_test::{
+        expect_next, free_local_addr, test_broadcast_abortable, test_broadcast_batch,
+        test_broadcast_fanout, test_broadcast_parallel, test_broadcast_unserializable,
+        test_circuit_breaker, test_inbound_rate_limit, test_merge_receivers, test_shutdown_handle,
+        test_zero_length, BulkData, Data, EmptyData, FailingData, Id, MixedData, TestPeer,
+        TestPeerList, BROADCAST_FANOUT_PEERS,
+    };
+    use crate::{BroadcastPolicy, Capabilities};
+
+    fn new_transport(addr: &str) -> Result<MemoryTransport<u32>> {
+        Transport::<Id, u32, Error, TestPeerList<Id>>::new(String::from(addr))
+    }
+
+    // Returns `n` distinct addresses.
+    fn addrs(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("node-{}", i)).collect()
+    }
+
+    // `new` must join the entered network only, and fail once no network is entered.
+    #[test]
+    fn enter_test() {
+        match new_transport("a") {
+            Err(e) => match e.downcast_ref::<Error>() {
+                Some(Error::NotReady) => {}
+                _ => panic!("unexpected error {}", e),
+            },
+            Ok(_) => panic!("created a transport without a network"),
+        }
+
+        let outer = MemoryNetwork::new();
+        let outer_guard = outer.enter();
+        let a = new_transport("a").unwrap();
+        {
+            let _inner_guard = MemoryNetwork::new().enter();
+            // The inner network is separate: "a" is free on it.
+            let _a = new_transport("a").unwrap();
+        }
+        // The outer network is restored: "a" is taken on it.
+        assert!(new_transport("a").is_err());
+        drop(a);
+        drop(outer_guard);
+        assert!(new_transport("a").is_err());
+        assert!(outer.transport::<u32>(String::from("a")).is_ok());
+    }
+
+    /*
+        Tests capabilities of MemoryTransport: it must report a coherent set, and every format it
+        lists must round-trip a message.
+    */
+    #[test]
+    fn capabilities_test() -> Result<()> {
+        let _network = MemoryNetwork::new().enter();
+        let addrs = vec![free_local_addr()?, free_local_addr()?];
+        let mut a: MemoryTransport<Data> =
+            Transport::<Id, Data, Error, TestPeerList<Id>>::new(addrs[0].clone())?;
+        let mut b: MemoryTransport<Data> =
+            Transport::<Id, Data, Error, TestPeerList<Id>>::new(addrs[1].clone())?;
+
+        let caps: Capabilities = Transport::<Id, Data, Error, TestPeerList<Id>>::capabilities(&a);
+        assert_eq!(framing::FRAME_VERSION, caps.version);
+        assert!(caps.max_message_size > 0);
+        assert_eq!(vec![SerializationFormat::Bincode], caps.supported_formats);
+
+        let d: Data = Data(31);
+        for format in caps.supported_formats.iter() {
+            assert_eq!(d, format.decode::<Data>(&format.encode(&d)?)?);
+        }
+        Transport::<Id, Data, Error, TestPeerList<Id>>::send(&mut b, addrs[0].clone(), d.clone())?;
+        expect_next(&mut a, &d);
+
+        Ok(())
+    }
+
+    // The generic tests whose Data type differs from the one of `transport_test_suite!`.
+    #[test]
+    fn broadcast_unserializable_test() {
+        let _network = MemoryNetwork::new().enter();
+        test_broadcast_unserializable::<MemoryTransport<FailingData>>(addrs(3)).unwrap();
+    }
+
+    #[test]
+    fn broadcast_batch_test() {
+        let _network = MemoryNetwork::new().enter();
+        test_broadcast_batch::<MemoryTransport<MixedData>>(addrs(3)).unwrap();
+    }
+
+    #[test]
+    fn zero_length_test() {
+        let _network = MemoryNetwork::new().enter();
+        test_zero_length::<MemoryTransport<EmptyData>>(addrs(2)).unwrap();
+    }
+
+    #[test]
+    fn broadcast_fanout_test() {
+        let _network = MemoryNetwork::new().enter();
+        test_broadcast_fanout::<MemoryTransport<BulkData>>(addrs(BROADCAST_FANOUT_PEERS)).unwrap();
+    }
+
+    #[test]
+    fn broadcast_abortable_test() {
+        let _network = MemoryNetwork::new().enter();
+        test_broadcast_abortable::<MemorySender<Data>, MemoryTransport<Data>>(addrs(3)).unwrap();
+    }
+
+    #[test]
+    fn broadcast_parallel_test() {
+        let _network = MemoryNetwork::new().enter();
+        test_broadcast_parallel::<MemorySender<Data>, MemoryTransport<Data>>(addrs(50)).unwrap();
+    }
+
+    #[test]
+    fn merge_receivers_test() {
+        let _network = MemoryNetwork::new().enter();
+        test_merge_receivers::<MemorySender<Data>, MemoryTransport<Data>>(addrs(2)).unwrap();
+    }
+
+    #[test]
+    fn circuit_breaker_test() {
+        let _network = MemoryNetwork::new().enter();
+        test_circuit_breaker::<MemoryTransport<Data>>(addrs(2)).unwrap();
+    }
+
+    #[test]
+    fn inbound_rate_limit_test() {
+        let _network = MemoryNetwork::new().enter();
+        test_inbound_rate_limit::<MemoryTransport<Data>>(addrs(2), 400).unwrap();
+    }
+
+    #[test]
+    fn shutdown_handle_test() {
+        let _network = MemoryNetwork::new().enter();
+        test_shutdown_handle::<MemoryTransport<Data>>(addrs(1).remove(0)).unwrap();
+    }
+
+    // A bincode codec counting the messages it encodes.
+    struct CountingCodec(Arc<AtomicUsize>);
+
+    impl Codec<Data> for CountingCodec {
+        fn encode(&self, data: &Data) -> Result<Vec<u8>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            bincode_encode(data)
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Result<Data> {
+            bincode_decode(bytes)
+        }
+    }
+
+    /*
+        Tests that the broadcast helpers serialize a message once for all peers, through
+        Transport::encode and send_raw, and that every peer receives it.
+    */
+    #[test]
+    fn broadcast_helpers_encode_once_test() -> Result<()> {
+        type T = MemoryTransport<Data>;
+        let network = MemoryNetwork::new();
+        let mut sender: T = network.transport(String::from("sender"))?;
+        let encoded = Arc::new(AtomicUsize::new(0));
+        Transport::<Id, Data, Error, TestPeerList<Id>>::set_codec(
+            &mut sender,
+            Box::new(CountingCodec(encoded.clone())),
+        )?;
+        let mut pl: TestPeerList<Id> = TestPeerList::new();
+        let mut receivers: Vec<T> = Vec::new();
+        for (i, addr) in addrs(3).into_iter().enumerate() {
+            pl.add(TestPeer::new(i.into(), addr.clone()))?;
+            receivers.push(network.transport(addr)?);
+        }
+
+        for i in 0..6 {
+            let d = Data(i);
+            encoded.store(0, Ordering::SeqCst);
+            let sender = &mut sender;
+            let pl = &mut pl;
+            match i {
+                0 => Transport::<Id, Data, Error, TestPeerList<Id>>::broadcast_with_policy(
+                    sender,
+                    pl,
+                    d.clone(),
+                    BroadcastPolicy::BestEffort,
+                )?,
+                1 => {
+                    let addrs: Vec<String> = pl.iter().map(|peer| peer.get_base_addr()).collect();
+                    Transport::<Id, Data, Error, TestPeerList<Id>>::broadcast_to(
+                        sender,
+                        addrs,
+                        d.clone(),
+                    )?
+                }
+                2 => Transport::<Id, Data, Error, TestPeerList<Id>>::broadcast_filtered(
+                    sender,
+                    pl,
+                    d.clone(),
+                    |_| true,
+                )?,
+                3 => Transport::<Id, Data, Error, TestPeerList<Id>>::broadcast_ordered(
+                    sender,
+                    pl,
+                    d.clone(),
+                )?,
+                4 => Transport::<Id, Data, Error, TestPeerList<Id>>::broadcast_unique_addr(
+                    sender,
+                    pl,
+                    d.clone(),
+                )?,
+                _ => {
+                    let outcome =
+                        Transport::<Id, Data, Error, TestPeerList<Id>>::broadcast_reporting(
+                            sender,
+                            pl,
+                            d.clone(),
+                        )?;
+                    assert_eq!(3, outcome.succeeded);
+                }
+            }
+            assert_eq!(1, encoded.load(Ordering::SeqCst), "helper {} encoded", i);
+            for receiver in receivers.iter_mut() {
+                expect_next(receiver, &d);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn set_codec_test() {
+        let _network = MemoryNetwork::new().enter();
+        crate::generic_test::test_set_codec::<MemoryTransport<Data>>(addrs(2)).unwrap();
+    }
+
+    // Self-test of transport_test_suite!: transports created with `new` reach each other on the
+    // in-process network entered by each test. The unsupported tests check connections, events,
+    // metrics and other features a connectionless in-process transport doesn't have.
+    crate::transport_test_suite!(
+        memory_transport_suite,
+        MemoryTransport<Data>,
+        || free_local_addr().unwrap(),
+        || MemoryNetwork::new().enter(),
+        unsupported: [
+            send_by_id,
+            preconnect_all,
+            idle_timeout,
+            disconnect_reason,
+            last_remote_addr,
+            channels,
+            send_tracked,
+            shutdown_send,
+            reset_metrics,
+            stats,
+            is_connected_to,
+            bind,
+            on_connect,
+            negotiated_max_frame_size,
+            peer_features,
+            rotate_keys,
+            pooled_connections,
+            broadcast_tree,
+            broadcast_during_reconnect,
+            connection_handoff,
+        ]
+    );
+}
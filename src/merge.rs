@@ -0,0 +1,68 @@
+/// # Fantom Libtransport/merge
+///
+/// This file contains a fan-in helper for nodes sharded across several bind addresses: the
+/// streams of multiple `TransportReceiver`s (or Transports) are merged into one inbound stream,
+/// so a node can listen on several ports and consume a single stream.
+use futures::stream::{select_all, SelectAll, Stream};
+
+/// Merges the receivers into one stream yielding messages from all of them as they arrive. The
+/// merged stream ends once every receiver has terminated. Any `TransportReceiver` qualifies, as
+/// the trait requires `Stream + Unpin`.
+pub fn merge_receivers<R, Data>(receivers: Vec<R>) -> SelectAll<R>
+where
+    R: Stream<Item = Data> + Unpin,
+{
+    select_all(receivers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::{Error, Result};
+    use crate::generic_test::{Data, Id, TestPeerList};
+    use crate::memory::{MemoryNetwork, MemoryTransport};
+    use crate::Transport;
+    use futures::executor::block_on;
+    use futures::stream::StreamExt;
+    use std::collections::HashSet;
+
+    /*
+        Tests merge_receivers: messages sent to either of two merged MemoryTransports must all be
+        yielded by the merged stream, which must end once both transports are shut down.
+    */
+    #[test]
+    fn merge_receivers_test() -> Result<()> {
+        let network = MemoryNetwork::new();
+        let a: MemoryTransport<Data> = network.transport(String::from("a"))?;
+        let b: MemoryTransport<Data> = network.transport(String::from("b"))?;
+        let mut sender: MemoryTransport<Data> = network.transport(String::from("sender"))?;
+        let shutdowns: Vec<_> = [&a, &b]
+            .iter()
+            .filter_map(|trn| Transport::<Id, Data, Error, TestPeerList<Id>>::shutdown_handle(*trn))
+            .collect();
+        let mut merged = merge_receivers(vec![a, b]);
+
+        for (i, addr) in ["a", "b", "a", "b"].iter().enumerate() {
+            let data = Data(i as u32);
+            Transport::<Id, Data, Error, TestPeerList<Id>>::send(
+                &mut sender,
+                addr.to_string(),
+                data,
+            )?;
+        }
+        let mut received: HashSet<Data> = HashSet::new();
+        for _ in 0..4 {
+            received.insert(block_on(merged.next()).expect("merged stream ended"));
+        }
+        let expected: HashSet<Data> = (0..4).map(Data).collect();
+        assert_eq!(expected, received);
+
+        assert_eq!(2, shutdowns.len());
+        for shutdown in shutdowns {
+            shutdown.trigger();
+        }
+        assert_eq!(None, block_on(merged.next()));
+
+        Ok(())
+    }
+}
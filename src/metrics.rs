@@ -0,0 +1,107 @@
+/// # Fantom Libtransport/metrics
+///
+/// This file defines the traffic counters a Transport may report through `Transport::metrics`
+/// and `Transport::peer_metrics`, and `MetricsCounters`, a thread-safe set of counters which
+/// implementations can embed (globally and per connection) to maintain them.
+///
+/// `TransportStats`, returned by `Transport::stats`, aggregates the counters and the connection
+/// list into one serializable object, e.g. to expose at a `/metrics` endpoint.
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// A snapshot of traffic counters.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Metrics {
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub messages_sent: u64,
+    pub messages_received: u64,
+}
+
+/// A live connection and its traffic counters.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ConnectionInfo {
+    /// The remote peer address.
+    pub address: String,
+    pub metrics: Metrics,
+}
+
+/// A snapshot of the state of a Transport for monitoring.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TransportStats {
+    /// Time since the Transport was created.
+    pub uptime: Duration,
+    pub peer_count: usize,
+    /// Counters summed over all connections.
+    pub total: Metrics,
+    pub per_peer: Vec<ConnectionInfo>,
+}
+
+/// Thread-safe traffic counters.
+#[derive(Debug, Default)]
+pub struct MetricsCounters {
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    messages_sent: AtomicU64,
+    messages_received: AtomicU64,
+}
+
+impl MetricsCounters {
+    /// Creates a set of zeroed counters.
+    pub fn new() -> MetricsCounters {
+        MetricsCounters::default()
+    }
+
+    /// Records one sent message of `bytes` bytes.
+    pub fn record_sent(&self, bytes: u64) {
+        self.bytes_sent.fetch_add(bytes, Ordering::Relaxed);
+        self.messages_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records one received message of `bytes` bytes.
+    pub fn record_received(&self, bytes: u64) {
+        self.bytes_received.fetch_add(bytes, Ordering::Relaxed);
+        self.messages_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the current counter values.
+    pub fn snapshot(&self) -> Metrics {
+        Metrics {
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            messages_sent: self.messages_sent.load(Ordering::Relaxed),
+            messages_received: self.messages_received.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Zeroes all counters.
+    pub fn reset(&self) {
+        self.bytes_sent.store(0, Ordering::Relaxed);
+        self.bytes_received.store(0, Ordering::Relaxed);
+        self.messages_sent.store(0, Ordering::Relaxed);
+        self.messages_received.store(0, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A stats snapshot must serialize to JSON, e.g. for a `/metrics` endpoint.
+    #[test]
+    fn stats_json_test() {
+        let stats = TransportStats {
+            uptime: Duration::from_secs(1),
+            peer_count: 1,
+            total: Metrics::default(),
+            per_peer: vec![ConnectionInfo {
+                address: String::from("127.0.0.1:9000"),
+                metrics: Metrics::default(),
+            }],
+        };
+        let json = serde_json::to_string(&stats).unwrap();
+        assert!(json.contains("per_peer"));
+        assert!(json.contains("127.0.0.1:9000"));
+    }
+}
@@ -0,0 +1,145 @@
+/// # Fantom Libtransport/null
+///
+/// This file defines `NullTransport`, a Transport which disables networking: `send` and
+/// `broadcast` succeed but discard the data, and the stream never yields a message. It lets code
+/// which requires a Transport run in tests or single-node modes without real networking.
+use crate::errors::Result;
+use crate::{Transport, TransportConfiguration};
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use futures::stream::{FusedStream, Stream};
+use libcommon_rs::peer::{PeerId, PeerList};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::sync::mpsc::Sender;
+
+/// A Transport which discards everything sent and never receives.
+pub struct NullTransport<Data> {
+    closed: bool,
+    terminated: bool,
+    // Waker of the task last polling the stream, woken on close so it observes the end.
+    waker: Option<Waker>,
+    _data: PhantomData<fn() -> Data>,
+}
+
+impl<Data> Drop for NullTransport<Data> {
+    fn drop(&mut self) {}
+}
+
+impl<Data> Stream for NullTransport<Data> {
+    type Item = Data;
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Data>> {
+        if self.closed {
+            self.terminated = true;
+            return Poll::Ready(None);
+        }
+        self.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl<Data> FusedStream for NullTransport<Data> {
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
+
+impl<Id, Data, Error, Pl> Transport<Id, Data, Error, Pl> for NullTransport<Data>
+where
+    Id: PeerId,
+    Pl: PeerList<Id, Error>,
+    Data: Serialize + DeserializeOwned,
+{
+    fn new<C: TransportConfiguration>(_cfg: C) -> Result<Self> {
+        Ok(NullTransport {
+            closed: false,
+            terminated: false,
+            waker: None,
+            _data: PhantomData,
+        })
+    }
+
+    fn is_ready(&self) -> bool {
+        true
+    }
+
+    fn send(&mut self, _peer_address: String, _data: Data) -> Result<()> {
+        Ok(())
+    }
+
+    fn broadcast(&mut self, _peers: &mut Pl, _data: Data) -> Result<()> {
+        Ok(())
+    }
+
+    fn broadcast_n(&mut self, _peers: &mut Pl, _n: usize, _data: Data) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_quit_tx(&self) -> Option<Sender<()>> {
+        None
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.closed = true;
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::Error;
+    use crate::generic_test::{try_next, Data, Id, TestPeer, TestPeerList};
+    use libcommon_rs::peer::Peer;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    /*
+        Tests NullTransport: send and broadcast succeed, while nothing is ever received. A task
+        waiting on the stream is woken by close, and then sees the end of the stream.
+    */
+    #[test]
+    fn null_transport_test() -> Result<()> {
+        let mut pl: TestPeerList<Id> = TestPeerList::new();
+        pl.add(TestPeer::new(0.into(), String::from("null")))?;
+        let mut trn = <NullTransport<Data> as Transport<Id, Data, Error, TestPeerList<Id>>>::new(
+            String::from("null"),
+        )?;
+
+        Transport::<Id, Data, Error, TestPeerList<Id>>::send(
+            &mut trn,
+            String::from("null"),
+            Data(1),
+        )?;
+        Transport::<Id, Data, Error, TestPeerList<Id>>::broadcast(&mut trn, &mut pl, Data(2))?;
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(None, try_next(&mut trn));
+
+        let woken = Arc::new(WakeCounter::default());
+        let waker = futures::task::waker(woken.clone());
+        let mut cx = Context::from_waker(&waker);
+        assert_eq!(Poll::Pending, Pin::new(&mut trn).poll_next(&mut cx));
+        Transport::<Id, Data, Error, TestPeerList<Id>>::close(&mut trn)?;
+        assert_eq!(1, woken.0.load(Ordering::SeqCst));
+        assert_eq!(Poll::Ready(None), Pin::new(&mut trn).poll_next(&mut cx));
+        assert!(trn.is_terminated());
+
+        Ok(())
+    }
+
+    // Waker counting how many times it was woken.
+    #[derive(Default)]
+    struct WakeCounter(AtomicUsize);
+
+    impl futures::task::ArcWake for WakeCounter {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
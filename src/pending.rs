@@ -0,0 +1,119 @@
+/// # Fantom Libtransport/pending
+///
+/// This file defines `PendingQueue`, the per-peer queue of frames waiting for a connection, which
+/// Transport implementations use to honour the `Transport::broadcast` contract for peers being
+/// (re)connected.
+///
+/// A peer which was connected and whose connection is being re-established, e.g. after it
+/// restarted, is in the `Connecting` state. Broadcasts must not silently lose the message for it:
+/// the encoded frame is queued on the peer's `PendingQueue` instead, and all queued frames are
+/// flushed in order once the connection is up. A `Disconnected` peer is not queued for: if it
+/// can't be reached the send fails as usual. Each peer queues at most `capacity` frames (by
+/// default `DEFAULT_PENDING_CAPACITY`); beyond that `push` fails with `Error::QueueFull`, so a
+/// peer which never comes back can't make memory grow without bounds.
+///
+/// Queues are keyed by the peer address as normalized by `addr::normalize_address`, which callers
+/// compute once per peer rather than on every call, so aliases of one endpoint share a queue.
+use crate::errors::{Error, Result};
+use std::collections::{HashMap, VecDeque};
+
+/// Number of frames queued per connecting peer by default.
+pub const DEFAULT_PENDING_CAPACITY: usize = 1024;
+
+/// The state of the connection to a peer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PeerState {
+    /// No connection, and none being established.
+    Disconnected,
+    /// A connection is being established; sends are queued.
+    Connecting,
+    /// The connection is up.
+    Connected,
+}
+
+/// Frames waiting for the connection to their peer, keyed by normalized peer address.
+#[derive(Debug)]
+pub struct PendingQueue {
+    capacity: usize,
+    queues: HashMap<String, VecDeque<Vec<u8>>>,
+}
+
+impl PendingQueue {
+    /// Creates an empty queue holding at most `capacity` frames per peer.
+    pub fn new(capacity: usize) -> PendingQueue {
+        PendingQueue {
+            capacity,
+            queues: HashMap::new(),
+        }
+    }
+
+    /// Queues `frame` for the peer with normalized address `key`, failing with
+    /// `Error::QueueFull` if the peer already has `capacity` frames queued.
+    pub fn push(&mut self, key: &str, frame: Vec<u8>) -> Result<()> {
+        let capacity = self.capacity;
+        let queue = self.queues.entry(key.to_string()).or_default();
+        if queue.len() >= capacity {
+            return Err(Error::QueueFull(key.to_string()).into());
+        }
+        queue.push_back(frame);
+        Ok(())
+    }
+
+    /// Removes and returns the frames queued for the peer with normalized address `key`, oldest
+    /// first, to be flushed once it is connected.
+    pub fn take(&mut self, key: &str) -> VecDeque<Vec<u8>> {
+        self.queues.remove(key).unwrap_or_default()
+    }
+
+    /// Returns the number of frames queued for the peer with normalized address `key`.
+    pub fn len(&self, key: &str) -> usize {
+        self.queues.get(key).map_or(0, VecDeque::len)
+    }
+
+    /// Returns true if no frame is queued for any peer.
+    pub fn is_empty(&self) -> bool {
+        self.queues.values().all(VecDeque::is_empty)
+    }
+}
+
+impl Default for PendingQueue {
+    fn default() -> PendingQueue {
+        PendingQueue::new(DEFAULT_PENDING_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::addr::normalize_address;
+
+    /*
+        Tests PendingQueue: frames are queued per normalized peer address up to the capacity and
+        taken back in order.
+    */
+    #[test]
+    fn pending_queue_test() -> Result<()> {
+        let key = normalize_address("127.0.0.1:9000")?;
+        assert_eq!(key, normalize_address("localhost:9000")?);
+        let mut queue = PendingQueue::new(2);
+        queue.push(&key, vec![1])?;
+        queue.push(&key, vec![2])?;
+        assert_eq!(2, queue.len(&key));
+        match queue.push(&key, vec![3]) {
+            Err(e) => match e.downcast_ref::<Error>() {
+                Some(Error::QueueFull(_)) => {}
+                _ => panic!("unexpected error: {}", e),
+            },
+            Ok(()) => panic!("queued beyond capacity"),
+        }
+        queue.push("127.0.0.1:9001", vec![4])?;
+
+        assert_eq!(vec![vec![1], vec![2]], Vec::from(queue.take(&key)));
+        assert_eq!(0, queue.len(&key));
+        assert!(!queue.is_empty());
+        queue.take("127.0.0.1:9001");
+        assert!(queue.is_empty());
+
+        Ok(())
+    }
+}
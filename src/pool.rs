@@ -0,0 +1,102 @@
+/// # Fantom Libtransport/pool
+///
+/// This file defines `ConnectionPool`, the connection-reuse cache Transport implementations keep
+/// in their send path. Opening a fresh connection for every `send` is slow and exhausts ephemeral
+/// ports, so connections are cached per peer and repeated sends to the same address reuse the
+/// cached connection. Addresses are keyed in normalized form (see `addr::normalize_address`), so
+/// different spellings of one endpoint share a connection.
+use crate::addr::normalize_address;
+use crate::errors::Result;
+use std::collections::HashMap;
+
+/// Open connections of type `C`, keyed by peer address.
+pub struct ConnectionPool<C> {
+    connections: HashMap<String, C>,
+}
+
+impl<C> Default for ConnectionPool<C> {
+    fn default() -> ConnectionPool<C> {
+        ConnectionPool::new()
+    }
+}
+
+impl<C> ConnectionPool<C> {
+    /// Creates an empty pool.
+    pub fn new() -> ConnectionPool<C> {
+        ConnectionPool {
+            connections: HashMap::new(),
+        }
+    }
+
+    // Addresses which don't resolve are keyed as written.
+    fn key(peer_address: &str) -> String {
+        normalize_address(peer_address).unwrap_or_else(|_| peer_address.to_string())
+    }
+
+    /// Returns the cached connection to the peer, opening and caching one with `connect` if
+    /// there is none.
+    pub fn get_or_connect<F>(&mut self, peer_address: &str, connect: F) -> Result<&mut C>
+    where
+        F: FnOnce(&str) -> Result<C>,
+    {
+        let key = ConnectionPool::<C>::key(peer_address);
+        if !self.connections.contains_key(&key) {
+            let connection = connect(peer_address)?;
+            self.connections.insert(key.clone(), connection);
+        }
+        Ok(self
+            .connections
+            .get_mut(&key)
+            .expect("connection was just inserted"))
+    }
+
+    /// Removes and returns the cached connection to the peer, e.g. after it failed.
+    pub fn remove(&mut self, peer_address: &str) -> Option<C> {
+        self.connections
+            .remove(&ConnectionPool::<C>::key(peer_address))
+    }
+
+    /// Returns the number of cached connections.
+    pub fn len(&self) -> usize {
+        self.connections.len()
+    }
+
+    /// Returns true if no connection is cached.
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+
+    /// Drops all cached connections.
+    pub fn clear(&mut self) {
+        self.connections.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /*
+        Tests ConnectionPool: connections are opened once per endpoint, also when it is spelled
+        differently, and reopened after removal.
+    */
+    #[test]
+    fn connection_pool_test() -> Result<()> {
+        let mut pool: ConnectionPool<usize> = ConnectionPool::new();
+        let mut opened = 0;
+        for addr in ["127.0.0.1:8000", "127.0.0.1:8000", "localhost:8000"].iter() {
+            pool.get_or_connect(addr, |_| {
+                opened += 1;
+                Ok(opened)
+            })?;
+        }
+        assert_eq!(1, opened);
+        assert_eq!(1, pool.len());
+
+        assert_eq!(Some(1), pool.remove("127.0.0.1:8000"));
+        assert!(pool.is_empty());
+        assert_eq!(2, *pool.get_or_connect("127.0.0.1:8000", |_| Ok(2))?);
+
+        Ok(())
+    }
+}
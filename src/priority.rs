@@ -0,0 +1,131 @@
+/// # Fantom Libtransport/priority
+///
+/// This file defines send priorities and `WeightedQueue`, a weighted round-robin scheduler
+/// Transport implementations can use to order queued outbound messages, e.g. to honour
+/// `Transport::send_with_priority` and `Transport::set_priority_weights`.
+///
+/// Strict priority lets a flood of `High` messages starve `Low` ones forever. The queue instead
+/// serves the priorities in rounds: in every round it takes up to `high` messages from the High
+/// queue, then up to `normal` from the Normal queue, then up to `low` from the Low queue,
+/// skipping empty queues. Each priority thus gets at least its weight's share of the send
+/// bandwidth while it has messages queued, and a queued Low message waits for at most
+/// `high + normal` messages of other priorities before a Low message is sent.
+use std::collections::VecDeque;
+
+/// Weights used unless configured otherwise.
+pub const DEFAULT_PRIORITY_WEIGHTS: (u32, u32, u32) = (8, 4, 1);
+
+/// The priority of an outbound message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Priority {
+    High,
+    Normal,
+    Low,
+}
+
+/// A queue serving messages of the three priorities in weighted round-robin order.
+#[derive(Clone, Debug)]
+pub struct WeightedQueue<T> {
+    queues: [VecDeque<T>; 3],
+    weights: [u32; 3],
+    // The priority currently served and how many more messages it may take this round.
+    current: usize,
+    credit: u32,
+}
+
+impl<T> WeightedQueue<T> {
+    /// Creates an empty queue with the given weights, each at least 1 so no priority starves.
+    pub fn new(high: u32, normal: u32, low: u32) -> WeightedQueue<T> {
+        let weights = [high.max(1), normal.max(1), low.max(1)];
+        WeightedQueue {
+            queues: [VecDeque::new(), VecDeque::new(), VecDeque::new()],
+            weights,
+            current: 0,
+            credit: weights[0],
+        }
+    }
+
+    /// Changes the weights, taking effect from the next round.
+    pub fn set_weights(&mut self, high: u32, normal: u32, low: u32) {
+        self.weights = [high.max(1), normal.max(1), low.max(1)];
+    }
+
+    /// Queues `item` with the given priority.
+    pub fn push(&mut self, priority: Priority, item: T) {
+        self.queues[priority as usize].push_back(item);
+    }
+
+    /// Removes the next message to send, or returns None if all queues are empty.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        loop {
+            if self.credit > 0 {
+                if let Some(item) = self.queues[self.current].pop_front() {
+                    self.credit -= 1;
+                    return Some(item);
+                }
+            }
+            self.current = (self.current + 1) % self.queues.len();
+            self.credit = self.weights[self.current];
+        }
+    }
+
+    /// Returns the number of queued messages.
+    pub fn len(&self) -> usize {
+        self.queues.iter().map(VecDeque::len).sum()
+    }
+
+    /// Returns true if no message is queued.
+    pub fn is_empty(&self) -> bool {
+        self.queues.iter().all(VecDeque::is_empty)
+    }
+}
+
+impl<T> Default for WeightedQueue<T> {
+    fn default() -> WeightedQueue<T> {
+        let (high, normal, low) = DEFAULT_PRIORITY_WEIGHTS;
+        WeightedQueue::new(high, normal, low)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /*
+        Tests WeightedQueue against starvation: with High flooded and Low trickled, Low messages
+        must be served after at most `high + normal` messages of other priorities.
+    */
+    #[test]
+    fn priority_starvation_test() {
+        let (high, normal, low) = (8, 4, 1);
+        let mut queue: WeightedQueue<(Priority, u32)> = WeightedQueue::new(high, normal, low);
+        for i in 0..1000 {
+            queue.push(Priority::High, (Priority::High, i));
+        }
+        for i in 0..10 {
+            queue.push(Priority::Low, (Priority::Low, i));
+        }
+
+        // Count the other messages served since the last Low one until every Low one was served.
+        let mut since_low = 0;
+        let mut lows = Vec::new();
+        while lows.len() < 10 {
+            match queue.pop() {
+                Some((Priority::Low, i)) => {
+                    lows.push(i);
+                    since_low = 0;
+                }
+                Some(_) => {
+                    since_low += 1;
+                    assert!(since_low <= high + normal, "Low starved");
+                }
+                None => panic!("unexpected None"),
+            }
+        }
+        assert_eq!((0..10).collect::<Vec<u32>>(), lows);
+        assert!(!queue.is_empty(), "High flood drained before Low");
+    }
+}
@@ -0,0 +1,108 @@
+/// # Fantom Libtransport/rate
+///
+/// This file contains a token bucket rate limiter which Transport implementations can use to pace
+/// traffic, e.g. in the receive loop to honour `Transport::set_inbound_rate_limit`.
+///
+/// The bucket holds at most one second worth of bytes, so short bursts up to the configured rate
+/// pass without delay while the long-term average stays under the limit.
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A token bucket limiting throughput to `bytes_per_sec`.
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    bytes_per_sec: u64,
+    allowance: f64,
+    last: Instant,
+}
+
+impl RateLimiter {
+    /// Creates a limiter allowing `bytes_per_sec` bytes per second (at least 1), starting full.
+    pub fn new(bytes_per_sec: u64) -> RateLimiter {
+        let bytes_per_sec = bytes_per_sec.max(1);
+        RateLimiter {
+            bytes_per_sec,
+            allowance: bytes_per_sec as f64,
+            last: Instant::now(),
+        }
+    }
+
+    /// Returns the configured rate in bytes per second.
+    pub fn bytes_per_sec(&self) -> u64 {
+        self.bytes_per_sec
+    }
+
+    /// Accounts for `bytes` of traffic and returns how long the caller should wait before
+    /// handling it to stay within the rate.
+    pub fn delay_for(&mut self, bytes: u64) -> Duration {
+        let rate = self.bytes_per_sec as f64;
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.last = now;
+        self.allowance = (self.allowance + elapsed * rate).min(rate) - bytes as f64;
+        if self.allowance >= 0.0 {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_secs_f64(-self.allowance / rate)
+        }
+    }
+
+    /// Accounts for `bytes` of traffic, blocking the current thread as long as needed to stay
+    /// within the rate. Blocking the reading thread applies backpressure to the sender.
+    pub fn throttle(&mut self, bytes: u64) {
+        let delay = self.delay_for(bytes);
+        if delay > Duration::from_secs(0) {
+            thread::sleep(delay);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /*
+        Tests delay_for: traffic within the allowance of a full bucket passes without delay, while
+        traffic over it is delayed in proportion to the excess.
+    */
+    #[test]
+    fn delay_for_test() {
+        let mut limiter = RateLimiter::new(1000);
+        assert_eq!(Duration::from_secs(0), limiter.delay_for(600));
+        assert_eq!(Duration::from_secs(0), limiter.delay_for(400));
+
+        // The bucket is empty: 500 bytes over budget take half a second at 1000 bytes/s, 500
+        // more a second. The refill between the calls is negligible.
+        let first = limiter.delay_for(500).as_secs_f64();
+        assert!(
+            first > 0.45 && first <= 0.5,
+            "delay {}s for 500 bytes",
+            first
+        );
+        let second = limiter.delay_for(500).as_secs_f64();
+        assert!(
+            second > 0.95 && second <= 1.0,
+            "delay {}s for 1000 bytes",
+            second
+        );
+    }
+
+    /*
+        Tests that the bucket refills with time, but never beyond one second worth of bytes.
+    */
+    #[test]
+    fn refill_test() {
+        let mut limiter = RateLimiter::new(1000);
+        assert_eq!(Duration::from_secs(0), limiter.delay_for(1000));
+        thread::sleep(Duration::from_millis(300));
+        assert_eq!(Duration::from_secs(0), limiter.delay_for(250));
+
+        thread::sleep(Duration::from_millis(1500));
+        let delay = limiter.delay_for(1500).as_secs_f64();
+        assert!(
+            delay > 0.45 && delay <= 0.5,
+            "delay {}s after a full refill",
+            delay
+        );
+    }
+}
@@ -0,0 +1,229 @@
+/// # Fantom Libtransport/retry
+///
+/// This file provides a bounded retry utility for Transport implementors, so reliable transports
+/// don't each reinvent their retry loops in `send` or when connecting to peers.
+///
+/// `retry_with_backoff` retries an operation with exponential backoff and random jitter, as
+/// configured by a `ReconnectPolicy`, and returns the last error once the retries are exhausted.
+///
+/// Jitter keeps many nodes reconnecting to a restarted peer at the same time from retrying in
+/// lockstep (a thundering herd). Following the AWS "Exponential Backoff And Jitter" scheme, with
+/// `backoff` the exponential backoff of the current retry:
+/// - `JitterKind::None` sleeps exactly `backoff`;
+/// - `JitterKind::Full` sleeps a random delay in `[0, backoff]`;
+/// - `JitterKind::Equal` sleeps `backoff / 2` plus a random delay in `[0, backoff / 2]`, trading
+///   some spread for a guaranteed minimum wait.
+use crate::errors::Result;
+use rand::Rng;
+use std::thread;
+use std::time::Duration;
+
+/// Selects how a backoff is randomized, see the module documentation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JitterKind {
+    None,
+    Full,
+    Equal,
+}
+
+/// Configures how often and how quickly a failed operation is retried.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    /// Number of retries after the first attempt; 0 disables retrying.
+    pub max_retries: u32,
+    /// Backoff before the first retry.
+    pub initial_backoff: Duration,
+    /// Upper bound of the backoff between two attempts.
+    pub max_backoff: Duration,
+    /// Factor the backoff grows by after every retry.
+    pub multiplier: u32,
+    /// How the backoff is randomized.
+    pub jitter: JitterKind,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> ReconnectPolicy {
+        ReconnectPolicy {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2,
+            jitter: JitterKind::Full,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Returns the backoff (before jitter) preceding the retry with the given 0-based index.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .checked_mul(self.multiplier.saturating_pow(retry))
+            .map_or(self.max_backoff, |b| b.min(self.max_backoff))
+    }
+
+    /// Returns the backoff preceding the retry with the given 0-based index with the configured
+    /// jitter applied, drawing randomness from `rng`.
+    pub fn jittered_backoff<R: Rng>(&self, retry: u32, rng: &mut R) -> Duration {
+        let backoff = self.backoff(retry);
+        match self.jitter {
+            JitterKind::None => backoff,
+            JitterKind::Full => random_up_to(backoff, rng),
+            JitterKind::Equal => {
+                let half = backoff / 2;
+                half + random_up_to(backoff - half, rng)
+            }
+        }
+    }
+}
+
+/// Picks a random delay between zero and `max`, inclusive.
+fn random_up_to<R: Rng>(max: Duration, rng: &mut R) -> Duration {
+    let micros = max.as_micros() as u64;
+    if micros == 0 {
+        return max;
+    }
+    Duration::from_micros(rng.gen_range(0, micros + 1))
+}
+
+/// Calls `op` until it succeeds, retrying at most `policy.max_retries` times with exponential
+/// backoff and jitter in between. Returns the error of the last attempt if all attempts fail.
+pub fn retry_with_backoff<F, T>(policy: &ReconnectPolicy, op: F) -> Result<T>
+where
+    F: FnMut() -> Result<T>,
+{
+    retry_with_backoff_rng(policy, &mut rand::thread_rng(), op)
+}
+
+/// Same as `retry_with_backoff`, drawing the jitter from `rng`, e.g. a seeded RNG for
+/// deterministic tests.
+pub fn retry_with_backoff_rng<F, T, R>(
+    policy: &ReconnectPolicy,
+    rng: &mut R,
+    mut op: F,
+) -> Result<T>
+where
+    F: FnMut() -> Result<T>,
+    R: Rng,
+{
+    let mut retry: u32 = 0;
+    loop {
+        match op() {
+            Ok(v) => return Ok(v),
+            Err(e) => {
+                if retry >= policy.max_retries {
+                    return Err(e);
+                }
+                thread::sleep(policy.jittered_backoff(retry, rng));
+                retry += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::Error;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /*
+        Tests retry_with_backoff: success on the first try, success on the third try, and failure
+        after exhausting the retries, asserting the number of attempts in each case.
+    */
+    #[test]
+    fn retry_test() -> Result<()> {
+        let policy = ReconnectPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(4),
+            multiplier: 2,
+            jitter: JitterKind::Full,
+        };
+
+        let mut attempts = 0;
+        let v = retry_with_backoff(&policy, || {
+            attempts += 1;
+            Ok(attempts)
+        })?;
+        assert_eq!(1, v);
+        assert_eq!(1, attempts);
+
+        let mut attempts = 0;
+        let v = retry_with_backoff(&policy, || {
+            attempts += 1;
+            if attempts < 3 {
+                Err(Error::Incomplete.into())
+            } else {
+                Ok(attempts)
+            }
+        })?;
+        assert_eq!(3, v);
+        assert_eq!(3, attempts);
+
+        let mut attempts = 0;
+        let r: Result<()> = retry_with_backoff(&policy, || {
+            attempts += 1;
+            Err(Error::Incomplete.into())
+        });
+        assert!(r.is_err());
+        assert_eq!(policy.max_retries + 1, attempts);
+
+        Ok(())
+    }
+
+    /*
+        Tests the ReconnectPolicy jitter kinds with a seeded RNG: None keeps the computed backoff,
+        Full stays within [0, backoff] and Equal within [backoff / 2, backoff].
+    */
+    #[test]
+    fn jitter_test() -> Result<()> {
+        let mut policy = ReconnectPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(100),
+            multiplier: 3,
+            jitter: JitterKind::None,
+        };
+        let mut rng = StdRng::seed_from_u64(42);
+
+        for retry in 0..8 {
+            let backoff = policy.backoff(retry);
+
+            policy.jitter = JitterKind::None;
+            assert_eq!(backoff, policy.jittered_backoff(retry, &mut rng));
+
+            policy.jitter = JitterKind::Full;
+            for _ in 0..100 {
+                assert!(policy.jittered_backoff(retry, &mut rng) <= backoff);
+            }
+
+            policy.jitter = JitterKind::Equal;
+            for _ in 0..100 {
+                let delay = policy.jittered_backoff(retry, &mut rng);
+                assert!(delay >= backoff / 2 && delay <= backoff);
+            }
+        }
+
+        // The same seed yields the same delays.
+        policy.jitter = JitterKind::Full;
+        let mut a = StdRng::seed_from_u64(7);
+        let mut b = StdRng::seed_from_u64(7);
+        for retry in 0..8 {
+            assert_eq!(
+                policy.jittered_backoff(retry, &mut a),
+                policy.jittered_backoff(retry, &mut b)
+            );
+        }
+
+        let mut attempts = 0;
+        let r: Result<()> = retry_with_backoff_rng(&policy, &mut rng, || {
+            attempts += 1;
+            Err(Error::Incomplete.into())
+        });
+        assert!(r.is_err());
+        assert_eq!(policy.max_retries + 1, attempts);
+
+        Ok(())
+    }
+}
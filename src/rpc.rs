@@ -0,0 +1,220 @@
+/// # Fantom Libtransport/rpc
+///
+/// This file defines a typed request/response layer on top of the fire-and-forget `send`.
+///
+/// `RpcNode` wraps a Transport carrying `RpcMessage<Data>`. Every request is tagged with a
+/// message id from a `MessageIdGenerator` (as tracked messages are, see the `tracking` module)
+/// and the address to reply to; the serving side answers with a response carrying the same id,
+/// which `request` matches against. A request fails with `Error::Timeout` when no matching
+/// response arrives in time. Requests received while waiting for a response are kept, in order,
+/// for `serve`.
+use crate::errors::{Error as TransportError, Result};
+use crate::timer;
+use crate::tracking::MessageIdGenerator;
+use crate::Transport;
+use futures::executor::block_on;
+use futures::future::{select, Either};
+use futures::stream::StreamExt;
+use libcommon_rs::peer::{PeerId, PeerList};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// The message exchanged by `RpcNode`s.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub enum RpcMessage<Data> {
+    Request {
+        id: u64,
+        reply_to: String,
+        body: Data,
+    },
+    Response {
+        id: u64,
+        body: Data,
+    },
+}
+
+/// A Transport wrapper sending requests and serving responses.
+pub struct RpcNode<T, Data> {
+    trn: T,
+    local_addr: String,
+    ids: MessageIdGenerator,
+    // Requests received by `request` while waiting for a response, not yet served.
+    backlog: VecDeque<RpcMessage<Data>>,
+}
+
+impl<T, Data> RpcNode<T, Data> {
+    /// Wraps `trn`, which peers reach at `local_addr`; responses to requests are sent there.
+    pub fn new(trn: T, local_addr: String) -> RpcNode<T, Data> {
+        RpcNode {
+            trn,
+            local_addr,
+            ids: MessageIdGenerator::new(),
+            backlog: VecDeque::new(),
+        }
+    }
+
+    /// Unwraps the transport.
+    pub fn into_inner(self) -> T {
+        self.trn
+    }
+
+    /// Sends `req` to the peer at `addr` and resolves with the matching response, or with
+    /// `Error::Timeout` if none arrives within `timeout`. Requests received meanwhile are kept for
+    /// `serve`; other responses are discarded.
+    pub async fn request<Id, Error, Pl>(
+        &mut self,
+        addr: String,
+        req: Data,
+        timeout: Duration,
+    ) -> Result<Data>
+    where
+        Id: PeerId,
+        Pl: PeerList<Id, Error>,
+        Data: Serialize + DeserializeOwned,
+        T: Transport<Id, RpcMessage<Data>, Error, Pl>,
+    {
+        let id = self.ids.next_id();
+        self.trn.send(
+            addr,
+            RpcMessage::Request {
+                id,
+                reply_to: self.local_addr.clone(),
+                body: req,
+            },
+        )?;
+        let trn = &mut self.trn;
+        let backlog = &mut self.backlog;
+        let response = async move {
+            while let Some(msg) = trn.next().await {
+                match msg {
+                    RpcMessage::Response { id: rid, body } => {
+                        if rid == id {
+                            return Some(body);
+                        }
+                    }
+                    request => backlog.push_back(request),
+                }
+            }
+            None
+        };
+        match select(Box::pin(response), timer::delay(timeout)).await {
+            Either::Left((Some(body), _)) => Ok(body),
+            Either::Left((None, _)) => Err(TransportError::Incomplete.into()),
+            Either::Right(_) => Err(TransportError::Timeout(timeout).into()),
+        }
+    }
+
+    /// Answers every request received with the response computed by `handler`, starting with
+    /// those kept by `request`, until the transport's stream ends. Responses received meanwhile
+    /// are discarded.
+    pub fn serve<Id, Error, Pl>(&mut self, handler: impl Fn(Data) -> Data) -> Result<()>
+    where
+        Id: PeerId,
+        Pl: PeerList<Id, Error>,
+        Data: Serialize + DeserializeOwned,
+        T: Transport<Id, RpcMessage<Data>, Error, Pl>,
+    {
+        loop {
+            let msg = match self.backlog.pop_front() {
+                Some(msg) => msg,
+                None => match block_on(self.trn.next()) {
+                    Some(msg) => msg,
+                    None => break,
+                },
+            };
+            if let RpcMessage::Request { id, reply_to, body } = msg {
+                self.trn.send(
+                    reply_to,
+                    RpcMessage::Response {
+                        id,
+                        body: handler(body),
+                    },
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::Error;
+    use crate::generic_test::{Data, Id, TestPeerList};
+    use crate::memory::{MemoryNetwork, MemoryTransport};
+    use crate::shutdown::ShutdownHandle;
+    use std::thread::{self, JoinHandle};
+
+    type RpcTransport = MemoryTransport<RpcMessage<Data>>;
+
+    // Serves requests received by `trn`, reachable at `addr`, with their doubled value on another
+    // thread until the returned handle is triggered.
+    fn spawn_server<T>(trn: T, addr: &str) -> (ShutdownHandle, JoinHandle<Result<()>>)
+    where
+        T: Transport<Id, RpcMessage<Data>, Error, TestPeerList<Id>> + Send + 'static,
+    {
+        let shutdown = trn.shutdown_handle().expect("no shutdown handle");
+        let mut server: RpcNode<T, Data> = RpcNode::new(trn, addr.to_string());
+        let handle = thread::spawn(move || server.serve(|Data(x)| Data(x * 2)));
+        (shutdown, handle)
+    }
+
+    // Sends `req` to the peer at `addr` and waits for the response.
+    fn request<T>(
+        client: &mut RpcNode<T, Data>,
+        addr: &str,
+        req: Data,
+        timeout: Duration,
+    ) -> Result<Data>
+    where
+        T: Transport<Id, RpcMessage<Data>, Error, TestPeerList<Id>>,
+    {
+        block_on(client.request(addr.to_string(), req, timeout))
+    }
+
+    /*
+        Tests a request/response round trip: every request must resolve with the response of the
+        server to it, and the server must stop once shut down.
+    */
+    #[test]
+    fn round_trip_test() -> Result<()> {
+        let network = MemoryNetwork::new();
+        let server: RpcTransport = network.transport(String::from("server"))?;
+        let (shutdown, handle) = spawn_server(server, "server");
+        let client: RpcTransport = network.transport(String::from("client"))?;
+        let mut client: RpcNode<RpcTransport, Data> = RpcNode::new(client, String::from("client"));
+
+        for x in 0..3 {
+            let response = request(&mut client, "server", Data(x), Duration::from_secs(5))?;
+            assert_eq!(Data(x * 2), response);
+        }
+
+        shutdown.trigger();
+        handle.join().expect("server panicked")
+    }
+
+    /*
+        Tests a request to a peer which never answers: it must fail with Error::Timeout carrying
+        the timeout.
+    */
+    #[test]
+    fn timeout_test() -> Result<()> {
+        let network = MemoryNetwork::new();
+        let _silent: RpcTransport = network.transport(String::from("silent"))?;
+        let client: RpcTransport = network.transport(String::from("client"))?;
+        let mut client: RpcNode<RpcTransport, Data> = RpcNode::new(client, String::from("client"));
+
+        let timeout = Duration::from_millis(200);
+        match request(&mut client, "silent", Data(1), timeout) {
+            Err(e) => match e.downcast_ref::<Error>() {
+                Some(Error::Timeout(d)) => assert_eq!(timeout, *d),
+                _ => panic!("unexpected error: {}", e),
+            },
+            Ok(d) => panic!("unexpected response {:?}", d),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,41 @@
+/// # Fantom Libtransport/safe
+///
+/// This file defines `BroadcastSafe`, a sealed marker trait guarding `Transport::broadcast_safe`
+/// against accidentally broadcasting oversized types.
+///
+/// `BroadcastSafe` cannot be implemented directly; `Data` types opt in with the
+/// `impl_broadcast_safe!` macro, which fails to compile unless the type's in-memory size is at
+/// most `MAX_BROADCAST_SAFE_SIZE` bytes. The in-memory size only bounds the serialized size of
+/// types without heap data, so types owning unbounded data (`Vec`, `String`, `HashMap`, ...)
+/// must not opt in and are thereby excluded from `broadcast_safe`; they keep using `broadcast`.
+use serde::Serialize;
+
+/// Upper bound, in bytes, of the in-memory size of a `BroadcastSafe` type.
+pub const MAX_BROADCAST_SAFE_SIZE: usize = 1024;
+
+/// Seals `BroadcastSafe`. Not part of the public API; public only for `impl_broadcast_safe!`.
+#[doc(hidden)]
+pub mod sealed {
+    pub trait Sealed {}
+}
+
+/// Marker for `Data` types small enough to be broadcast with `Transport::broadcast_safe`.
+pub trait BroadcastSafe: Serialize + sealed::Sealed {}
+
+/// Marks a fixed-size type as `BroadcastSafe`, failing to compile if its size exceeds
+/// `MAX_BROADCAST_SAFE_SIZE`.
+#[macro_export]
+macro_rules! impl_broadcast_safe {
+    ($($t:ty),+ $(,)?) => {
+        $(
+            const _: () = assert!(
+                core::mem::size_of::<$t>() <= $crate::safe::MAX_BROADCAST_SAFE_SIZE,
+                "type too large to be BroadcastSafe"
+            );
+            impl $crate::safe::sealed::Sealed for $t {}
+            impl $crate::safe::BroadcastSafe for $t {}
+        )+
+    };
+}
+
+impl_broadcast_safe!(bool, char, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
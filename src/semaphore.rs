@@ -0,0 +1,50 @@
+/// # Fantom Libtransport/semaphore
+///
+/// This file defines a small blocking counting semaphore used to cap how many operations run at
+/// the same time on threads of a transport, e.g. inbound handshakes, so a flood of work can't
+/// exhaust the node.
+use crate::errors::{Error, Result};
+use std::sync::{Condvar, Mutex};
+
+/// A counting semaphore. Permits are returned when the guard handed out by `acquire` is dropped.
+pub struct Semaphore {
+    permits: Mutex<usize>,
+    available: Condvar,
+}
+
+/// A permit acquired from a Semaphore, released on drop.
+pub struct SemaphoreGuard<'a> {
+    semaphore: &'a Semaphore,
+}
+
+impl Semaphore {
+    /// Creates a semaphore with `permits` permits (at least one).
+    pub fn new(permits: usize) -> Semaphore {
+        Semaphore {
+            permits: Mutex::new(permits.max(1)),
+            available: Condvar::new(),
+        }
+    }
+
+    /// Blocks until a permit is available and takes it.
+    pub fn acquire(&self) -> Result<SemaphoreGuard<'_>> {
+        let mut permits = self.permits.lock().map_err(Error::from)?;
+        while *permits == 0 {
+            permits = self
+                .available
+                .wait(permits)
+                .map_err(|e| Error::PoisonError(e.to_string()))?;
+        }
+        *permits -= 1;
+        Ok(SemaphoreGuard { semaphore: self })
+    }
+}
+
+impl<'a> Drop for SemaphoreGuard<'a> {
+    fn drop(&mut self) {
+        if let Ok(mut permits) = self.semaphore.permits.lock() {
+            *permits += 1;
+            self.semaphore.available.notify_one();
+        }
+    }
+}
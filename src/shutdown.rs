@@ -0,0 +1,125 @@
+/// # Fantom Libtransport/shutdown
+///
+/// This file defines `ShutdownHandle`, a cloneable handle used to terminate a Transport's receive
+/// stream from another task. Once `trigger` is called the stream yields `None`, and every task
+/// blocked in `next()` is woken so it can observe the end of the stream.
+///
+/// Implementations keep one handle and call `poll_triggered` at the start of `poll_next`:
+///
+/// ```ignore
+/// if self.shutdown.poll_triggered(cx) {
+///     return Poll::Ready(None);
+/// }
+/// ```
+use core::task::{Context, Waker};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+struct Shared {
+    triggered: AtomicBool,
+    wakers: Mutex<Vec<Waker>>,
+}
+
+/// A cloneable handle which terminates the stream it was obtained from.
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    shared: Arc<Shared>,
+}
+
+impl Default for ShutdownHandle {
+    fn default() -> ShutdownHandle {
+        ShutdownHandle::new()
+    }
+}
+
+impl ShutdownHandle {
+    /// Creates a new, untriggered handle.
+    pub fn new() -> ShutdownHandle {
+        ShutdownHandle {
+            shared: Arc::new(Shared {
+                triggered: AtomicBool::new(false),
+                wakers: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Terminates the stream and wakes every task waiting on it.
+    pub fn trigger(&self) {
+        self.shared.triggered.store(true, Ordering::SeqCst);
+        if let Ok(mut wakers) = self.shared.wakers.lock() {
+            for waker in wakers.drain(..) {
+                waker.wake();
+            }
+        }
+    }
+
+    /// Returns true once `trigger` has been called.
+    pub fn is_triggered(&self) -> bool {
+        self.shared.triggered.load(Ordering::SeqCst)
+    }
+
+    /// Returns true once `trigger` has been called; otherwise registers the current task to be
+    /// woken when it is.
+    pub fn poll_triggered(&self, cx: &mut Context<'_>) -> bool {
+        if self.is_triggered() {
+            return true;
+        }
+        if let Ok(mut wakers) = self.shared.wakers.lock() {
+            if !wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                wakers.push(cx.waker().clone());
+            }
+        }
+        // Re-check in case trigger ran before the waker was registered.
+        self.is_triggered()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::pin::Pin;
+    use core::task::Poll;
+    use futures::executor::block_on;
+    use futures::stream::{Stream, StreamExt};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    // A stream which never yields a message and ends once its handle is triggered.
+    struct Endless {
+        shutdown: ShutdownHandle,
+    }
+
+    impl Stream for Endless {
+        type Item = ();
+        fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<()>> {
+            if self.shutdown.poll_triggered(cx) {
+                return Poll::Ready(None);
+            }
+            Poll::Pending
+        }
+    }
+
+    /*
+        Tests trigger: a task blocked in next() on another thread must be woken and end with None.
+    */
+    #[test]
+    fn trigger_test() {
+        let handle = ShutdownHandle::new();
+        let mut stream = Endless {
+            shutdown: handle.clone(),
+        };
+        let reader = thread::spawn(move || block_on(stream.next()));
+
+        // Wait for the reader to register, so that trigger has to wake it.
+        let deadline = Instant::now() + Duration::from_secs(3);
+        while handle.shared.wakers.lock().unwrap().is_empty() {
+            assert!(Instant::now() < deadline, "the reader never polled");
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(!handle.is_triggered());
+        handle.trigger();
+
+        assert_eq!(None, reader.join().expect("reader panicked"));
+        assert!(handle.is_triggered());
+    }
+}
@@ -0,0 +1,562 @@
+/// # Fantom Libtransport/suite
+///
+/// This file defines `transport_test_suite!`, which gives implementor crates full coverage by the
+/// generic tests in one line instead of hand-wiring calls to `generic_test::common_test` & co.
+///
+/// Given a module name, a transport type implementing
+/// `Transport<Id, Data, Error, TestPeerList<Id>>` (the types of `generic_test`) and an address
+/// generator, i.e. a non-capturing closure or function returning a fresh, unused address on
+/// every call, it expands into a module of `#[test]` functions:
+///
+/// ```ignore
+/// libtransport::transport_test_suite!(tcp_suite, TCPtransport<Data>, || {
+///     libtransport::generic_test::free_local_addr().unwrap()
+/// });
+/// ```
+///
+/// An optional fourth argument, again a non-capturing closure or function, is called at the
+/// start of every test and its result kept until the test ends, e.g. to enter the
+/// `MemoryNetwork` the transports of the test join. The tests of the suite create all transports
+/// on the test's thread.
+///
+/// ```ignore
+/// libtransport::transport_test_suite!(memory_suite, MemoryTransport<Data>, addr, || {
+///     MemoryNetwork::new().enter()
+/// });
+/// ```
+///
+/// Names used in the arguments are resolved in the scope invoking the macro, which should be a
+/// `#[cfg(test)]` module or an integration test.
+///
+/// Tests of optional features skip their checks for transports which opt out, e.g. by returning
+/// `Error::Unsupported`, as described on each test. A skip is logged, and fails the test unless
+/// the test is listed in the optional fifth argument, which must name exactly the tests that skip
+/// checks for the transport:
+///
+/// ```ignore
+/// libtransport::transport_test_suite!(
+///     memory_suite,
+///     MemoryTransport<Data>,
+///     addr,
+///     || MemoryNetwork::new().enter(),
+///     unsupported: [channels, send_tracked, rotate_keys]
+/// );
+/// ```
+///
+/// Some tests move a transport to another thread, so the type must be `Send + Sync + 'static`.
+/// `loopback_pair`, `send_raw`, `reconnect` and `send_peer` bind `generic_test::free_local_addr`
+/// addresses instead of generated ones.
+///
+/// Not part of the suite, to be called directly where they apply, are the tests taking sender
+/// and receiver halves (`test_broadcast_parallel`, `test_broadcast_abortable`,
+/// `test_merge_receivers`, `test_shutdown_handle`), those for other Data types
+/// (`test_broadcast_unserializable`, `test_broadcast_batch`, `test_zero_length`,
+/// `test_broadcast_fanout`, `test_stream_compression`, `test_rpc`), `test_set_codec`, which needs
+/// the `json` feature, `test_max_concurrent_handshakes`, which creates transports on other
+/// threads, and the tests of features without a way to opt out (`test_inbound_rate_limit`,
+/// `test_circuit_breaker`).
+///
+/// Socket-based implementations must in addition call the tests of socket features, which the
+/// suite can't run as they need real sockets (`test_try_broadcast` and `test_write_timeout` stall
+/// a plain TCP listener, `test_advertised_addr` binds the wildcard address) or a transport with
+/// background work and a listen queue (`test_new_on_executor`, `test_accept_backlog`). Nothing
+/// else runs them:
+///
+/// ```ignore
+/// mod tcp_socket_tests {
+///     use libtransport::generic_test::*;
+///
+///     #[test]
+///     fn write_timeout() {
+///         test_write_timeout::<TCPtransport<Data>>(free_local_addr().unwrap()).unwrap();
+///     }
+///
+///     #[test]
+///     fn accept_backlog() {
+///         test_accept_backlog::<TCPtransport<Data>>(free_local_addr().unwrap()).unwrap();
+///     }
+///
+///     #[test]
+///     fn try_broadcast() {
+///         let addrs = vec![free_local_addr().unwrap(), free_local_addr().unwrap()];
+///         test_try_broadcast::<TCPtransport<Data>>(addrs).unwrap();
+///     }
+///
+///     #[test]
+///     fn new_on_executor() {
+///         let addrs = vec![free_local_addr().unwrap(), free_local_addr().unwrap()];
+///         test_new_on_executor::<TCPtransport<Data>>(addrs).unwrap();
+///     }
+///
+///     #[test]
+///     fn advertised_addr() {
+///         test_advertised_addr::<TCPtransport<Data>>().unwrap();
+///     }
+/// }
+/// ```
+
+/// Expands into `#[test]` functions running the generic tests against a transport type.
+#[macro_export]
+macro_rules! transport_test_suite {
+    ($suite:ident, $transport:ty, $addr:expr) => {
+        $crate::transport_test_suite!($suite, $transport, $addr, || ());
+    };
+    ($suite:ident, $transport:ty, $addr:expr, $setup:expr) => {
+        $crate::transport_test_suite!($suite, $transport, $addr, $setup, unsupported: []);
+    };
+    (
+        $suite:ident,
+        $transport:ty,
+        $addr:expr,
+        $setup:expr,
+        unsupported: [$($unsupported:ident),* $(,)?]
+    ) => {
+        mod $suite {
+            #[allow(unused_imports)]
+            use super::*;
+
+            // Returns `n` fresh addresses from the address generator.
+            #[allow(dead_code)]
+            fn addrs(n: usize) -> Vec<String> {
+                let addr: fn() -> String = $addr;
+                (0..n).map(|_| addr()).collect()
+            }
+
+            // Runs the setup, returning what must be kept until the end of the test.
+            #[allow(dead_code)]
+            fn setup() -> impl Sized {
+                let setup: fn() -> _ = $setup;
+                setup()
+            }
+
+            // The tests which must skip checks for features the transport lacks.
+            #[allow(dead_code)]
+            const UNSUPPORTED: &[&str] = &[$(stringify!($unsupported)),*];
+
+            // Runs the setup and then the generic test `name`, see generic_test::run_suite_test.
+            #[allow(dead_code)]
+            fn run<F>(name: &str, test: F)
+            where
+                F: FnOnce() -> $crate::errors::Result<()>,
+            {
+                let _guard = setup();
+                $crate::generic_test::run_suite_test(name, UNSUPPORTED, test);
+            }
+
+            // Fails to compile if a test listed as unsupported isn't one of the suite.
+            #[cfg(test)]
+            #[allow(dead_code)]
+            fn unsupported_tests_exist() {
+                $(let _: fn() = $unsupported;)*
+            }
+
+            #[test]
+            fn common_test() {
+                run("common_test", || {
+                    $crate::generic_test::common_test::<$transport>(addrs(3))
+                });
+            }
+
+            #[test]
+            fn send_unknown_peer() {
+                run("send_unknown_peer", || {
+                    let addrs = addrs(2);
+                    $crate::generic_test::test_send_unknown_peer::<$transport>(
+                        addrs[0].clone(),
+                        addrs[1].clone(),
+                    )
+                });
+            }
+
+            #[test]
+            fn ordering() {
+                run("ordering", || {
+                    $crate::generic_test::test_ordering::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn drop_rebinds() {
+                run("drop_rebinds", || {
+                    $crate::generic_test::test_drop_rebinds::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn recv_timeout() {
+                run("recv_timeout", || {
+                    $crate::generic_test::test_recv_timeout::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn broadcast_empty() {
+                run("broadcast_empty", || {
+                    $crate::generic_test::test_broadcast_empty::<$transport>(addrs(1).remove(0))
+                });
+            }
+
+            #[test]
+            fn unreliable_common_test() {
+                run("unreliable_common_test", || {
+                    $crate::generic_test::unreliable_common_test::<$transport>(addrs(3))
+                });
+            }
+
+            #[test]
+            fn broadcast_best_effort() {
+                run("broadcast_best_effort", || {
+                    $crate::generic_test::test_broadcast_best_effort::<$transport>(
+                        addrs(3),
+                        addrs(1).remove(0),
+                    )
+                });
+            }
+
+            #[test]
+            fn broadcast_reporting() {
+                run("broadcast_reporting", || {
+                    $crate::generic_test::test_broadcast_reporting::<$transport>(
+                        addrs(2),
+                        addrs(1).remove(0),
+                    )
+                });
+            }
+
+            #[test]
+            fn send_across_threads() {
+                run("send_across_threads", || {
+                    $crate::generic_test::test_send_across_threads::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn send_by_id() {
+                run("send_by_id", || {
+                    $crate::generic_test::test_send_by_id::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn preconnect_all() {
+                run("preconnect_all", || {
+                    $crate::generic_test::test_preconnect_all::<$transport>(
+                        addrs(3),
+                        addrs(1).remove(0),
+                    )
+                });
+            }
+
+            #[test]
+            fn idle_timeout() {
+                run("idle_timeout", || {
+                    $crate::generic_test::test_idle_timeout::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn disconnect_reason() {
+                run("disconnect_reason", || {
+                    $crate::generic_test::test_disconnect_reason::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn close_terminates() {
+                run("close_terminates", || {
+                    $crate::generic_test::test_close_terminates::<$transport>(addrs(1).remove(0))
+                });
+            }
+
+            #[test]
+            fn last_remote_addr() {
+                run("last_remote_addr", || {
+                    $crate::generic_test::test_last_remote_addr::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn channels() {
+                run("channels", || {
+                    $crate::generic_test::test_channels::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn flush_on_drop() {
+                run("flush_on_drop", || {
+                    $crate::generic_test::test_flush_on_drop::<$transport>(addrs(3))
+                });
+            }
+
+            #[test]
+            fn send_tracked() {
+                run("send_tracked", || {
+                    $crate::generic_test::test_send_tracked::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn not_ready() {
+                run("not_ready", || {
+                    $crate::generic_test::test_not_ready::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn shutdown_send() {
+                run("shutdown_send", || {
+                    $crate::generic_test::test_shutdown_send::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn reset_metrics() {
+                run("reset_metrics", || {
+                    $crate::generic_test::test_reset_metrics::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn stats() {
+                run("stats", || {
+                    $crate::generic_test::test_stats::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn loopback_pair() {
+                run("loopback_pair", || {
+                    $crate::generic_test::loopback_pair_test::<$transport>()
+                });
+            }
+
+            #[test]
+            fn send_raw() {
+                run("send_raw", || {
+                    $crate::generic_test::test_send_raw::<$transport>()
+                });
+            }
+
+            #[test]
+            fn logging() {
+                run("logging", || {
+                    $crate::generic_test::test_logging::<$transport>(addrs(3))
+                });
+            }
+
+            #[test]
+            fn reconnect() {
+                run("reconnect", || {
+                    $crate::generic_test::test_reconnect::<$transport>()
+                });
+            }
+
+            #[test]
+            fn close_all() {
+                run("close_all", || {
+                    $crate::generic_test::test_close_all::<$transport>(addrs(3))
+                });
+            }
+
+            #[test]
+            fn is_connected_to() {
+                run("is_connected_to", || {
+                    $crate::generic_test::test_is_connected_to::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn send_peer() {
+                run("send_peer", || {
+                    $crate::generic_test::test_send_peer::<$transport>()
+                });
+            }
+
+            #[test]
+            fn broadcast_recording() {
+                run("broadcast_recording", || {
+                    $crate::generic_test::test_broadcast_recording::<$transport>(addrs(3))
+                });
+            }
+
+            #[test]
+            fn on_peers_changed() {
+                run("on_peers_changed", || {
+                    $crate::generic_test::test_on_peers_changed::<$transport>(addrs(3))
+                });
+            }
+
+            #[test]
+            fn broadcast_safe() {
+                run("broadcast_safe", || {
+                    $crate::generic_test::test_broadcast_safe::<$transport>(addrs(3))
+                });
+            }
+
+            #[test]
+            fn bind() {
+                run("bind", || {
+                    $crate::generic_test::test_bind::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn broadcast_filtered() {
+                run("broadcast_filtered", || {
+                    $crate::generic_test::test_broadcast_filtered::<$transport>(addrs(4))
+                });
+            }
+
+            #[test]
+            fn broadcast_ref() {
+                run("broadcast_ref", || {
+                    $crate::generic_test::test_broadcast_ref::<$transport>(addrs(3))
+                });
+            }
+
+            #[test]
+            fn on_connect() {
+                run("on_connect", || {
+                    $crate::generic_test::test_on_connect::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn negotiated_max_frame_size() {
+                run("negotiated_max_frame_size", || {
+                    $crate::generic_test::test_negotiated_max_frame_size::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn peer_features() {
+                run("peer_features", || {
+                    $crate::generic_test::test_peer_features::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn rotate_keys() {
+                run("rotate_keys", || {
+                    $crate::generic_test::test_rotate_keys::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn send_fut() {
+                run("send_fut", || {
+                    $crate::generic_test::test_send_fut::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn send_fut_interleaved() {
+                run("send_fut_interleaved", || {
+                    $crate::generic_test::test_send_fut_interleaved::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn broadcast_unique_addr() {
+                run("broadcast_unique_addr", || {
+                    $crate::generic_test::test_broadcast_unique_addr::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn coalesce_window() {
+                run("coalesce_window", || {
+                    $crate::generic_test::test_coalesce_window::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn pooled_connections() {
+                run("pooled_connections", || {
+                    $crate::generic_test::test_pooled_connections::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn observe() {
+                run("observe", || {
+                    $crate::generic_test::test_observe::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn broadcast_to() {
+                run("broadcast_to", || {
+                    $crate::generic_test::test_broadcast_to::<$transport>(addrs(3))
+                });
+            }
+
+            #[test]
+            fn send_failover() {
+                run("send_failover", || {
+                    $crate::generic_test::test_send_failover::<$transport>(
+                        addrs(2),
+                        addrs(1).remove(0),
+                    )
+                });
+            }
+
+            #[test]
+            fn poll_interval() {
+                run("poll_interval", || {
+                    $crate::generic_test::test_poll_interval::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn stream() {
+                run("stream", || {
+                    $crate::generic_test::test_stream::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn broadcast_tree() {
+                run("broadcast_tree", || {
+                    $crate::generic_test::test_broadcast_tree::<$transport>(addrs(5))
+                });
+            }
+
+            #[test]
+            fn pause_resume() {
+                run("pause_resume", || {
+                    $crate::generic_test::test_pause_resume::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn broadcast_during_reconnect() {
+                run("broadcast_during_reconnect", || {
+                    $crate::generic_test::test_broadcast_during_reconnect::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn sequence_tracking() {
+                run("sequence_tracking", || {
+                    $crate::generic_test::test_sequence_tracking::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn run_receive_loop() {
+                run("run_receive_loop", || {
+                    $crate::generic_test::test_run_receive_loop::<$transport>(addrs(2))
+                });
+            }
+
+            #[test]
+            fn connection_handoff() {
+                run("connection_handoff", || {
+                    $crate::generic_test::test_connection_handoff::<$transport>(addrs(2))
+                });
+            }
+        }
+    };
+}
@@ -0,0 +1,194 @@
+/// # Fantom Libtransport/timer
+///
+/// This file provides `delay`, a minimal runtime-agnostic timer future for deadlines such as
+/// request timeouts, and `recv_timeout`, waiting for the next item of a stream up to a timeout.
+/// The crate doesn't depend on an async runtime, so pending `Delay`s register
+/// their deadline and waker with a timer thread, which wakes them once the deadline has passed.
+/// Each thread creating delays is served by its own timer thread, started on first use and
+/// stopping once that thread has exited and its last deadline has passed.
+use crate::errors::{Error, Result};
+use core::cmp::Ordering;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use futures::future::Future;
+use futures::stream::Stream;
+use std::collections::BinaryHeap;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// A waker to wake at `deadline`, ordered so the earliest deadline is the greatest.
+struct Entry {
+    deadline: Instant,
+    waker: Waker,
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.deadline == other.deadline
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.deadline.cmp(&self.deadline)
+    }
+}
+
+thread_local! {
+    // Registers deadlines with the timer thread serving this thread.
+    static TIMER: Sender<Entry> = start_timer();
+}
+
+// Starts a timer thread, returning the sender registering deadlines with it.
+fn start_timer() -> Sender<Entry> {
+    let (tx, rx) = mpsc::channel::<Entry>();
+    thread::spawn(move || {
+        let mut entries: BinaryHeap<Entry> = BinaryHeap::new();
+        let mut connected = true;
+        loop {
+            let now = Instant::now();
+            while entries.peek().map_or(false, |entry| entry.deadline <= now) {
+                if let Some(entry) = entries.pop() {
+                    entry.waker.wake();
+                }
+            }
+            let next = entries.peek().map(|entry| entry.deadline - now);
+            if !connected {
+                match next {
+                    Some(wait) => thread::sleep(wait),
+                    None => return,
+                }
+                continue;
+            }
+            let received = match next {
+                Some(wait) => rx.recv_timeout(wait),
+                None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match received {
+                Ok(entry) => entries.push(entry),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => connected = false,
+            }
+        }
+    });
+    tx
+}
+
+/// A future completing once its deadline has passed.
+pub struct Delay {
+    deadline: Instant,
+    // The waker last registered with the timer thread.
+    waker: Option<Waker>,
+}
+
+/// Returns a future completing after `duration`.
+pub fn delay(duration: Duration) -> Delay {
+    Delay {
+        deadline: Instant::now() + duration,
+        waker: None,
+    }
+}
+
+impl Future for Delay {
+    type Output = ();
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if Instant::now() >= self.deadline {
+            return Poll::Ready(());
+        }
+        let registered = self
+            .waker
+            .as_ref()
+            .map_or(false, |waker| waker.will_wake(cx.waker()));
+        if !registered {
+            let entry = Entry {
+                deadline: self.deadline,
+                waker: cx.waker().clone(),
+            };
+            // The timer thread only stops once its sender is gone, so sending can't fail.
+            let _ = TIMER.try_with(|timer| timer.send(entry));
+            self.waker = Some(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+/// A future resolving to the next item of a stream, or to `Ok(None)` once its timeout elapsed.
+pub struct RecvTimeout<'a, S> {
+    stream: &'a mut S,
+    delay: Delay,
+}
+
+/// Returns a future resolving to the next item of `stream`, to `Ok(None)` if none arrived within
+/// `duration`, or to `Error::Incomplete` if the stream ended first.
+pub fn recv_timeout<S: Stream + Unpin>(stream: &mut S, duration: Duration) -> RecvTimeout<'_, S> {
+    RecvTimeout {
+        stream,
+        delay: delay(duration),
+    }
+}
+
+impl<'a, S: Stream + Unpin> Future for RecvTimeout<'a, S> {
+    type Output = Result<Option<S::Item>>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match Pin::new(&mut *self.stream).poll_next(cx) {
+            Poll::Ready(Some(item)) => return Poll::Ready(Ok(Some(item))),
+            Poll::Ready(None) => return Poll::Ready(Err(Error::Incomplete.into())),
+            Poll::Pending => {}
+        }
+        Pin::new(&mut self.delay).poll(cx).map(|()| Ok(None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc;
+    use futures::executor::block_on;
+    use futures::future::{select, Either};
+    use futures::stream;
+
+    #[test]
+    fn delay_test() {
+        let start = Instant::now();
+        block_on(delay(Duration::from_millis(50)));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    // The earlier of two delays must complete first, whatever order they were created in.
+    #[test]
+    fn delay_order_test() {
+        let long = delay(Duration::from_secs(5));
+        let short = delay(Duration::from_millis(20));
+        match block_on(select(long, short)) {
+            Either::Right(_) => {}
+            Either::Left(_) => panic!("the longer delay completed first"),
+        }
+    }
+    #[test]
+    fn recv_timeout_test() {
+        // The sender stays alive, so the receiver stays pending.
+        let (_tx, mut pending) = mpsc::unbounded::<u32>();
+        let start = Instant::now();
+        assert_eq!(
+            None,
+            block_on(recv_timeout(&mut pending, Duration::from_millis(50))).unwrap()
+        );
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        let mut items = stream::iter(vec![7u32]);
+        assert_eq!(
+            Some(7),
+            block_on(recv_timeout(&mut items, Duration::from_secs(5))).unwrap()
+        );
+        assert!(block_on(recv_timeout(&mut items, Duration::from_secs(5))).is_err());
+    }
+}
@@ -0,0 +1,53 @@
+/// # Fantom Libtransport/tracking
+///
+/// This file contains support for tracked messages, which carry a per-transport unique message id
+/// usable for request/response correlation. A tracked frame is laid out as:
+///
+/// [ message id: u64, big-endian ][ bincode(data) ]
+use crate::codec::bincode_decode;
+use crate::errors::{Error, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Size in bytes of the message id prefix.
+const MESSAGE_ID_SIZE: usize = 8;
+
+/// Generates monotonically increasing message ids. Safe to share between threads: concurrent
+/// callers never receive the same id.
+#[derive(Debug, Default)]
+pub struct MessageIdGenerator {
+    next: AtomicU64,
+}
+
+impl MessageIdGenerator {
+    /// Creates a generator whose first id is 0.
+    pub fn new() -> MessageIdGenerator {
+        MessageIdGenerator::default()
+    }
+
+    /// Returns the next message id.
+    pub fn next_id(&self) -> u64 {
+        self.next.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+/// Serializes `data` prefixed with its message `id`.
+pub fn encode<Data: Serialize>(id: u64, data: &Data) -> Result<Vec<u8>> {
+    let data_bytes = bincode::serialize(data).map_err(Error::from)?;
+    let mut out = Vec::with_capacity(MESSAGE_ID_SIZE + data_bytes.len());
+    out.extend_from_slice(&id.to_be_bytes());
+    out.extend_from_slice(&data_bytes);
+    Ok(out)
+}
+
+/// Splits a frame produced by `encode` into its message id and `Data`.
+pub fn decode<Data: DeserializeOwned>(bytes: &[u8]) -> Result<(u64, Data)> {
+    if bytes.len() < MESSAGE_ID_SIZE {
+        return Err(Error::Incomplete.into());
+    }
+    let mut id_bytes = [0u8; MESSAGE_ID_SIZE];
+    id_bytes.copy_from_slice(&bytes[..MESSAGE_ID_SIZE]);
+    let data: Data = bincode_decode(&bytes[MESSAGE_ID_SIZE..])?;
+    Ok((u64::from_be_bytes(id_bytes), data))
+}
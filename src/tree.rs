@@ -0,0 +1,100 @@
+/// # Fantom Libtransport/tree
+///
+/// This file defines the spanning tree used by `Transport::broadcast_tree`. A full-mesh
+/// broadcast costs the sender one send per peer; over the tree every node sends at most `fanout`
+/// messages and the message reaches all peers in about log_fanout(n) hops.
+///
+/// The tree members are the peer addresses except the origin's, sorted and deduplicated, so
+/// every node derives the same tree from the same peer list. The origin is the root and sends to
+/// the first `fanout` members; the member at index i forwards to the members at
+/// (i + 1) * fanout .. (i + 2) * fanout, i.e. a heap laid over the sorted list.
+///
+/// Forwarded messages carry two headers (see the `headers` module): the origin address, from
+/// which receivers rebuild the tree, and a hop count (TTL) decremented on every hop. The origin
+/// sets it to the number of members, the depth of the deepest possible tree, so a message is
+/// dropped once it has travelled further than any valid tree allows. This stops infinite
+/// forwarding when peer lists disagree.
+///
+/// Delivery is epidemic, not end-to-end: a message reaches a node only if every node on its path
+/// from the root received it and forwarded it. A node which is down, or whose peer list differs,
+/// cuts its subtree off. Callers needing guaranteed delivery must add acknowledgements or fall
+/// back to `broadcast`.
+use crate::headers::Headers;
+use std::convert::TryInto;
+
+/// Header carrying the address of the node which started the broadcast.
+pub const ORIGIN_HEADER: &str = "tree-origin";
+/// Header carrying the number of hops the message may still be forwarded, as a big-endian u32.
+pub const TTL_HEADER: &str = "tree-ttl";
+
+/// Returns the members of the tree rooted at `origin`: the addresses except `origin`, sorted
+/// and deduplicated.
+pub fn members<I: IntoIterator<Item = String>>(addrs: I, origin: &str) -> Vec<String> {
+    let mut members: Vec<String> = addrs.into_iter().filter(|addr| addr != origin).collect();
+    members.sort();
+    members.dedup();
+    members
+}
+
+/// Returns the members the root (`index` None) or the member at `index` forwards to.
+pub fn children(members: &[String], index: Option<usize>, fanout: usize) -> &[String] {
+    let node = index.map_or(0, |i| i + 1);
+    let start = node.saturating_mul(fanout).min(members.len());
+    let end = start.saturating_add(fanout).min(members.len());
+    &members[start..end]
+}
+
+/// Returns the forwarding headers of a message from `origin` which may travel `ttl` more hops.
+pub fn headers(origin: &str, ttl: u32) -> Headers {
+    let mut headers = Headers::new();
+    headers.insert(ORIGIN_HEADER.to_string(), origin.as_bytes().to_vec());
+    headers.insert(TTL_HEADER.to_string(), ttl.to_be_bytes().to_vec());
+    headers
+}
+
+/// Returns the origin and TTL of a forwarded message, or None if `headers` carry no valid
+/// forwarding headers.
+pub fn parse(headers: &Headers) -> Option<(String, u32)> {
+    let origin = String::from_utf8(headers.get(ORIGIN_HEADER)?.clone()).ok()?;
+    let ttl_bytes: [u8; 4] = headers.get(TTL_HEADER)?.as_slice().try_into().ok()?;
+    Some((origin, u32::from_be_bytes(ttl_bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /*
+        Tests the spanning tree of broadcast_tree: for several fanouts the children of the root and
+        of every member must reach each member exactly once, within the TTL set by the origin.
+    */
+    #[test]
+    fn tree_test() {
+        let addrs: Vec<String> = (0..10).map(|i| format!("node-{}", i)).collect();
+        let members = members(addrs.iter().cloned(), "node-3");
+        assert_eq!(9, members.len());
+        assert!(!members.contains(&String::from("node-3")));
+
+        for fanout in 1..5 {
+            let mut received = vec![0; members.len()];
+            let mut hops = vec![0; members.len()];
+            let mut queue: VecDeque<(Option<usize>, u32)> = VecDeque::new();
+            queue.push_back((None, 0));
+            while let Some((index, depth)) = queue.pop_front() {
+                for child in children(&members, index, fanout) {
+                    let i = members.iter().position(|m| m == child).unwrap();
+                    received[i] += 1;
+                    hops[i] = depth + 1;
+                    queue.push_back((Some(i), depth + 1));
+                }
+            }
+            assert!(received.iter().all(|&n| n == 1), "fanout {}", fanout);
+            assert!(hops.iter().all(|&h| h as usize <= members.len()));
+        }
+
+        let headers = headers("node-3", 7);
+        assert_eq!(Some((String::from("node-3"), 7)), parse(&headers));
+        assert_eq!(None, parse(&Headers::new()));
+    }
+}